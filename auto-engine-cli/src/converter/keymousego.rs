@@ -53,6 +53,10 @@ impl Converter {
                                         interval: None,
                                         conditions: None,
                                         err_return: None,
+                                        timeout_ms: None,
+                                        retry_policy: None,
+                                        join: None,
+                                        disabled: false,
                                     },
                                 };
                                 pipelines.push(Stage { stage: vec![node] })
@@ -66,6 +70,10 @@ impl Converter {
                                     interval: Some(0),
                                     conditions: None,
                                     err_return: None,
+                                    timeout_ms: None,
+                                    retry_policy: None,
+                                    join: None,
+                                    disabled: false,
                                     description: None,
                                 },
                                 params: KeyBoardParams {
@@ -53,6 +53,10 @@ impl Converter {
                                 interval: Some(0),
                                 conditions: self.conditions.clone(),
                                 err_return: None,
+                                timeout_ms: None,
+                                retry_policy: None,
+                                join: None,
+                                disabled: false,
                                 description: None,
                             },
                             params,
@@ -78,6 +82,10 @@ impl Converter {
                             retry: Some(0),
                             interval: Some(0),
                             err_return: None,
+                            timeout_ms: None,
+                            retry_policy: None,
+                            join: None,
+                            disabled: false,
                         },
                     };
 
@@ -1,14 +1,41 @@
+use crate::secret::{SECRET_PREFIX, SecretStore};
 use crate::utils;
 use serde::Serialize;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, Weak};
 use tauri::async_runtime::RwLock;
 use tauri::Manager;
+use tokio::sync::RwLockReadGuard;
 
+type Values = Arc<RwLock<HashMap<String, serde_json::Value>>>;
+type OpenScopes = Arc<Mutex<Vec<Weak<RwLock<HashMap<String, serde_json::Value>>>>>>;
+
+/// What a scope of a [`Context`] was opened for. A run starts with the `Run`
+/// scope, the others are nested in it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Run,
+    /// One of the branches a node with several outgoing connections starts.
+    Branch,
+    /// One iteration of a loop body.
+    Iteration,
+    SubWorkflow,
+}
+
+/// The values of a run. Reads fall back to the enclosing scopes, writes go
+/// to the current one unless they are published to its parent.
 #[derive(Debug)]
 pub struct Context {
-    pub string_value: Arc<RwLock<HashMap<String, serde_json::Value>>>,
+    /// The values of the current scope.
+    pub string_value: Values,
+    pub(crate) scope: Scope,
+    /// The scopes the current one is nested in, outermost first.
+    pub(crate) parents: Vec<(Scope, Values)>,
+    /// The run scope and every scope opened in it, outermost first. A sub
+    /// workflow starts its own list.
+    pub(crate) open_scopes: OpenScopes,
+    pub(crate) secrets: Arc<SecretStore>,
     pub(crate) screen_scale: f64,
     pub(crate) pipeline_path: PathBuf,
     pub(crate) workflow_path: PathBuf,
@@ -19,8 +46,13 @@ pub struct Context {
 impl Context {
     #[cfg(feature = "tauri")]
     pub fn new(path: PathBuf, app_handle: Option<tauri::AppHandle>) -> Self {
+        let string_value: Values = Default::default();
         Self {
-            string_value: Arc::new(RwLock::new(HashMap::new())),
+            open_scopes: open_scopes(&string_value),
+            string_value,
+            scope: Scope::Run,
+            parents: vec![],
+            secrets: Default::default(),
             screen_scale: 1.0,
             pipeline_path: path.clone(),
             workflow_path: path.clone(),
@@ -30,8 +62,13 @@ impl Context {
 
     #[cfg(not(feature = "tauri"))]
     pub fn new(path: PathBuf) -> Self {
+        let string_value: Values = Default::default();
         Self {
-            string_value: Arc::new(RwLock::new(HashMap::new())),
+            open_scopes: open_scopes(&string_value),
+            string_value,
+            scope: Scope::Run,
+            parents: vec![],
+            secrets: Default::default(),
             screen_scale: 1.0,
             pipeline_path: path.clone(),
             workflow_path: path.clone(),
@@ -43,6 +80,72 @@ impl Context {
         self
     }
 
+    pub fn with_secrets(mut self, secrets: SecretStore) -> Self {
+        self.secrets = Arc::new(secrets);
+        self
+    }
+
+    pub fn secrets(&self) -> &SecretStore {
+        &self.secrets
+    }
+
+    pub fn scope(&self) -> Scope {
+        self.scope
+    }
+
+    /// Opens an empty `scope` nested in the current one. Everything else is
+    /// shared with this context.
+    pub fn scoped(&self, scope: Scope) -> Self {
+        let mut parents = self.parents.clone();
+        parents.push((self.scope, self.string_value.clone()));
+        let string_value: Values = Default::default();
+        let open_scopes = if scope == Scope::SubWorkflow {
+            open_scopes(&string_value)
+        } else {
+            let mut scopes = self.open_scopes.lock().unwrap_or_else(|e| e.into_inner());
+            scopes.retain(|scope| scope.strong_count() > 0);
+            scopes.push(Arc::downgrade(&string_value));
+            self.open_scopes.clone()
+        };
+        Self {
+            string_value,
+            scope,
+            parents,
+            open_scopes,
+            pipeline_path: self.pipeline_path.clone(),
+            workflow_path: self.workflow_path.clone(),
+            ..self.child(PathBuf::new())
+        }
+    }
+
+    /// Opens the scope of a nested workflow stored in `path`.
+    pub fn sub_workflow(&self, path: PathBuf) -> Self {
+        Self {
+            pipeline_path: path.clone(),
+            workflow_path: path,
+            ..self.scoped(Scope::SubWorkflow)
+        }
+    }
+
+    /// Creates an empty context for a workflow stored in `path`, with no
+    /// scope to fall back to. The screen scale, secrets and app handle are
+    /// shared with this context.
+    pub fn child(&self, path: PathBuf) -> Self {
+        let string_value: Values = Default::default();
+        Self {
+            open_scopes: open_scopes(&string_value),
+            string_value,
+            scope: Scope::Run,
+            parents: vec![],
+            secrets: self.secrets.clone(),
+            screen_scale: self.screen_scale,
+            pipeline_path: path.clone(),
+            workflow_path: path,
+            #[cfg(feature = "tauri")]
+            app_handle: self.app_handle.clone(),
+        }
+    }
+
     pub async fn set_string_value(&self, key: &str, value: &str) -> Result<(), String> {
         self.set_value::<String>(key, value.to_string()).await
     }
@@ -55,9 +158,103 @@ impl Context {
         );
         Ok(())
     }
+
+    /// Sets `key` in the scope the current one is nested in, so it outlives
+    /// the current scope. The run scope sets it itself.
+    pub async fn publish_value<T: Serialize>(&self, key: &str, value: T) -> Result<(), String> {
+        let values = match self.parents.last() {
+            Some((_, values)) => values,
+            None => &self.string_value,
+        };
+        values.write().await.insert(
+            key.to_string(),
+            serde_json::to_value(value).map_err(|e| format!("{:?}", e))?,
+        );
+        Ok(())
+    }
+
+    /// Copies every value of the current scope and the branches it is nested
+    /// in to the nearest scope that is not a branch, where the other
+    /// branches see them.
+    pub(crate) async fn publish_branches(&self) {
+        if self.scope != Scope::Branch {
+            return;
+        }
+        let mut values = HashMap::new();
+        let mut target = None;
+        let scopes = std::iter::once((self.scope, &self.string_value)).chain(
+            self.parents
+                .iter()
+                .rev()
+                .map(|(scope, values)| (*scope, values)),
+        );
+        for (scope, scope_values) in scopes {
+            if scope != Scope::Branch {
+                target = Some(scope_values);
+                break;
+            }
+            for (key, value) in scope_values.read().await.iter() {
+                values.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+        }
+        if let Some(target) = target {
+            target.write().await.extend(values);
+        }
+    }
+
+    /// Moves every value of the current scope to the scope it is nested in,
+    /// except those in `keep`.
+    pub(crate) async fn close(&self, keep: &[&str]) {
+        let Some((_, parent)) = self.parents.last() else {
+            return;
+        };
+        let mut values = std::mem::take(&mut *self.string_value.write().await);
+        values.retain(|key, _| !keep.contains(&key.as_str()));
+        parent.write().await.extend(values);
+    }
+
     pub async fn get_value(&self, key: &str) -> Option<serde_json::Value> {
-        let map = self.string_value.read().await;
-        map.get(key).cloned()
+        self.read_values().await.get(key).cloned()
+    }
+
+    /// Read access to the values of the current scope and every scope it is
+    /// nested in.
+    pub(crate) async fn read_values(&self) -> ScopeValues<'_> {
+        let mut scopes = vec![self.string_value.read().await];
+        for (_, values) in self.parents.iter().rev() {
+            scopes.push(values.read().await);
+        }
+        ScopeValues { scopes }
+    }
+
+    /// Every value visible from the current scope.
+    pub async fn values(&self) -> HashMap<String, serde_json::Value> {
+        let mut values = HashMap::new();
+        for scope in self.read_values().await.scopes.iter() {
+            for (key, value) in scope.iter() {
+                values.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+        }
+        values
+    }
+
+    /// Every value of the run, with those of the scopes still open in it, as
+    /// if they were all closed now.
+    pub(crate) async fn snapshot(&self) -> HashMap<String, serde_json::Value> {
+        let scopes: Vec<Values> = self
+            .open_scopes
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect();
+        let mut values = HashMap::new();
+        for scope in scopes {
+            for (key, value) in scope.read().await.iter() {
+                values.insert(key.clone(), value.clone());
+            }
+        }
+        values
     }
 
     pub async fn get_value_parse(&self, key: &str) -> Option<serde_json::Value> {
@@ -72,6 +269,11 @@ impl Context {
             key = var_name;
         }
 
+        if let Some(name) = key.strip_prefix(SECRET_PREFIX)
+            && let Some(secret) = self.secrets.get(name)
+        {
+            return Some(serde_json::Value::String(secret.to_string()));
+        }
         let value = self.get_value(&key).await;
         if value.is_some() {
             return value;
@@ -98,3 +300,84 @@ impl Context {
         PathBuf::from("")
     }
 }
+
+fn open_scopes(run: &Values) -> OpenScopes {
+    Arc::new(Mutex::new(vec![Arc::downgrade(run)]))
+}
+
+/// The values of a scope and the scopes it is nested in, innermost first.
+pub(crate) struct ScopeValues<'a> {
+    scopes: Vec<RwLockReadGuard<'a, HashMap<String, serde_json::Value>>>,
+}
+
+impl ScopeValues<'_> {
+    /// The value at `key` in the innermost scope that has it, see [`lookup`].
+    pub(crate) fn get(&self, key: &str) -> Option<&serde_json::Value> {
+        self.scopes.iter().find_map(|values| lookup(values, key))
+    }
+}
+
+/// The value at `key` in `values`. A key that is not set itself may point
+/// into a value that is, e.g. `ctx.http.body.id` into the object stored as
+/// `ctx.http.body`, with array items addressed by their index.
+pub(crate) fn lookup<'a>(
+    values: &'a HashMap<String, serde_json::Value>,
+    key: &str,
+) -> Option<&'a serde_json::Value> {
+    if let Some(value) = values.get(key) {
+        return Some(value);
+    }
+    let mut end = key.len();
+    while let Some(dot) = key[..end].rfind('.') {
+        if let Some(value) = values.get(&key[..dot]) {
+            return key[dot + 1..]
+                .split('.')
+                .try_fold(value, |value, segment| match value {
+                    serde_json::Value::Array(items) => segment
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|index| items.get(index)),
+                    value => value.get(segment),
+                });
+        }
+        end = dot;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scopes() {
+        #[cfg(feature = "tauri")]
+        let run = Context::new(PathBuf::new(), None);
+
+        #[cfg(not(feature = "tauri"))]
+        let run = Context::new(PathBuf::new());
+
+        run.set_value("a", 1).await.unwrap();
+        run.set_value("b", 1).await.unwrap();
+        let branch = run.scoped(Scope::Branch);
+        let iteration = branch.scoped(Scope::Iteration);
+        iteration.set_value("b", 2).await.unwrap();
+        iteration.set_value("c", 3).await.unwrap();
+        iteration.publish_value("d", 4).await.unwrap();
+
+        assert_eq!(iteration.get_value("a").await, Some(1.into()));
+        assert_eq!(iteration.get_value("b").await, Some(2.into()));
+        assert_eq!(branch.get_value("b").await, Some(1.into()));
+        assert_eq!(branch.get_value("c").await, None);
+        assert_eq!(branch.get_value("d").await, Some(4.into()));
+        assert_eq!(iteration.values().await.len(), 4);
+
+        iteration.close(&["c"]).await;
+        assert_eq!(branch.get_value("b").await, Some(2.into()));
+        assert_eq!(branch.get_value("c").await, None);
+        assert_eq!(run.get_value("d").await, None);
+
+        branch.publish_branches().await;
+        assert_eq!(run.get_value("d").await, Some(4.into()));
+    }
+}
@@ -0,0 +1,26 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+pub const DEBUG_EVENT: &str = "debug";
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum DebugStatus {
+    /// The run stopped before `node_id`. Sent again after every value
+    /// changed while it is stopped.
+    Halted,
+    Continued,
+    SteppedOver,
+    /// `node_id` was skipped, the run went on without its outputs.
+    Skipped,
+}
+
+#[derive(Serialize, Clone)]
+pub struct DebugEventPayload {
+    pub status: DebugStatus,
+    pub node_id: String,
+    /// The `Context` values while the run is halted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<HashMap<String, Value>>,
+}
@@ -9,3 +9,11 @@ pub use node::*;
 mod workflow;
 
 pub use workflow::*;
+
+mod schedule;
+
+pub use schedule::*;
+
+mod debug;
+
+pub use debug::*;
@@ -7,6 +7,9 @@ pub struct NodeEventPayload {
     pub status: String,
     pub name: String,
     pub result: Option<serde_json::Value>,
+    /// Attempt of the node this event belongs to, starting at 1.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attempt: Option<u32>,
 }
 
 impl NodeEventPayload {
@@ -20,9 +23,15 @@ impl NodeEventPayload {
             status,
             name,
             result: res,
+            attempt: None,
         }
     }
 
+    pub fn with_attempt(mut self, attempt: u32) -> NodeEventPayload {
+        self.attempt = Some(attempt);
+        self
+    }
+
     pub fn running(name: String) -> NodeEventPayload {
         NodeEventPayload::new::<String>("running".to_string(), name, None)
     }
@@ -30,6 +39,10 @@ impl NodeEventPayload {
         NodeEventPayload::new::<String>("waiting".to_string(), name, None)
     }
 
+    pub fn disabled(name: String) -> NodeEventPayload {
+        NodeEventPayload::new::<String>("disabled".to_string(), name, None)
+    }
+
     pub fn skip<D: Serialize>(name: String, result: Option<D>) -> NodeEventPayload {
         NodeEventPayload::new("skip".to_string(), name, result)
     }
@@ -42,6 +55,10 @@ impl NodeEventPayload {
         NodeEventPayload::new("error".to_string(), name, result)
     }
 
+    pub fn timeout(name: String) -> NodeEventPayload {
+        NodeEventPayload::new::<String>("timeout".to_string(), name, None)
+    }
+
     pub fn cancel() -> NodeEventPayload {
         NodeEventPayload::new::<String>("cancel".to_string(), "*".to_string(), None)
     }
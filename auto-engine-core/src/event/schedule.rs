@@ -0,0 +1,26 @@
+use serde::Serialize;
+
+pub const SCHEDULE_EVENT: &str = "schedule";
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleStatus {
+    /// The next run is due at `next_fire_at`.
+    Scheduled,
+    /// A run started for `fire_at`.
+    Fired,
+    /// The run for `fire_at` was dropped, the previous one still runs.
+    Skipped,
+    /// The run for `fire_at` was missed and is not made up for.
+    Missed,
+    Stopped,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ScheduleEventPayload {
+    pub status: ScheduleStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fire_at: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_fire_at: Option<u64>,
+}
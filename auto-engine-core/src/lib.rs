@@ -16,6 +16,8 @@ pub mod pipeline;
 pub mod runner;
 #[cfg(feature = "types")]
 pub mod schema;
+#[cfg(feature = "context")]
+pub mod secret;
 #[cfg(feature = "types")]
 pub mod types;
 #[cfg(feature = "utils")]
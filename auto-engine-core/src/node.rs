@@ -1,4 +1,7 @@
+pub mod branch;
 pub mod data_aggregator;
+pub mod end;
+pub mod for_each;
 pub mod http;
 pub mod image_match;
 pub mod keyboard;
@@ -7,6 +10,7 @@ pub mod mouse_move;
 pub mod ocr;
 pub mod screen_capture;
 pub mod start;
+pub mod sub_workflow;
 pub mod time_wait;
 #[cfg(feature = "wasm")]
 pub mod wasm;
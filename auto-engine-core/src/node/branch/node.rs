@@ -0,0 +1,88 @@
+use crate::types::field::{FieldType, SchemaField};
+use crate::types::node::{I18nValue, NodeDefine};
+use std::collections::HashMap;
+
+pub const NODE_TYPE: &str = "Branch";
+
+pub const PORT_TRUE: &str = "true";
+pub const PORT_FALSE: &str = "false";
+
+#[derive(Default)]
+pub struct BranchNode;
+
+impl BranchNode {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl NodeDefine for BranchNode {
+    fn action_type(&self) -> String {
+        NODE_TYPE.to_string()
+    }
+
+    fn name(&self) -> I18nValue {
+        I18nValue {
+            zh: "条件分支".to_string(),
+            en: "Branch".to_string(),
+        }
+    }
+
+    fn icon(&self) -> String {
+        String::from(
+            "data:image/svg+xml;base64,PHN2ZyB4bWxucz0iaHR0cDovL3d3dy53My5vcmcvMjAwMC9zdmciIHdpZHRoPSIyNCIgaGVpZ2h0PSIyNCIgdmlld0JveD0iMCAwIDI0IDI0IiBmaWxsPSJub25lIiBzdHJva2U9ImN1cnJlbnRDb2xvciIgc3Ryb2tlLXdpZHRoPSIxLjUiIHN0cm9rZS1saW5lY2FwPSJyb3VuZCIgc3Ryb2tlLWxpbmVqb2luPSJyb3VuZCIgY2xhc3M9Imx1Y2lkZSBsdWNpZGUtc3BsaXQtaWNvbiBsdWNpZGUtc3BsaXQiPjxwYXRoIGQ9Ik0xNiAzaDV2NSIvPjxwYXRoIGQ9Ik04IDNIM3Y1Ii8+PHBhdGggZD0iTTEyIDIydi04LjNhNCA0IDAgMCAwLTEuMTcyLTIuODcyTDMgMyIvPjxwYXRoIGQ9Im0xNSA5IDYtNiIvPjwvc3ZnPg==",
+        )
+    }
+
+    fn category(&self) -> Option<I18nValue> {
+        Some(I18nValue {
+            zh: "基础节点".to_string(),
+            en: "Basic Node".to_string(),
+        })
+    }
+
+    fn description(&self) -> Option<I18nValue> {
+        Some(I18nValue {
+            zh: "根据条件表达式选择 true 或 false 分支".to_string(),
+            en: "Follow the true or false branch depending on a condition".to_string(),
+        })
+    }
+
+    fn output_schema(&self, _input: HashMap<String, serde_json::Value>) -> Vec<SchemaField> {
+        vec![SchemaField {
+            name: "result".to_string(),
+            field_type: FieldType::Boolean,
+            item_type: None,
+            description: Some(I18nValue {
+                zh: "条件表达式的计算结果".to_string(),
+                en: "Result of the condition expression".to_string(),
+            }),
+            enums: vec![],
+            default: None,
+            condition: None,
+        }]
+    }
+
+    fn input_schema(&self) -> Vec<SchemaField> {
+        vec![SchemaField {
+            name: "condition".to_string(),
+            field_type: FieldType::String,
+            item_type: None,
+            description: Some(I18nValue {
+                zh: "布尔表达式，例如：${ctx.find.score} > 0.9".to_string(),
+                en: "Boolean expression, e.g.: ${ctx.find.score} > 0.9".to_string(),
+            }),
+            enums: vec![],
+            default: None,
+            condition: None,
+        }]
+    }
+
+    fn output_ports(&self) -> Vec<String> {
+        vec![PORT_TRUE.to_string(), PORT_FALSE.to_string()]
+    }
+
+    fn has_side_effects(&self) -> bool {
+        false
+    }
+}
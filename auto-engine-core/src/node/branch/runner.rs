@@ -0,0 +1,62 @@
+use crate::context::Context;
+use crate::node::branch::node::{PORT_FALSE, PORT_TRUE};
+use crate::types::node::{
+    NodeRunner, NodeRunnerControl, NodeRunnerController, NodeRunnerFactory, OUTPUT_PORT,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BranchParam {
+    pub condition: String,
+}
+
+#[derive(Default)]
+pub struct BranchRunner;
+
+impl BranchRunner {
+    pub fn new() -> Self {
+        BranchRunner
+    }
+}
+
+#[async_trait::async_trait]
+impl NodeRunner for BranchRunner {
+    type ParamType = BranchParam;
+
+    async fn run(
+        &mut self,
+        _ctx: &Context,
+        param: Self::ParamType,
+    ) -> Result<Option<HashMap<String, Value>>, String> {
+        // Variables are already substituted by the controller.
+        if param.condition.trim().is_empty() {
+            return Err("Branch condition is empty".to_string());
+        }
+        let result = evalexpr::eval_boolean(&param.condition)
+            .map_err(|err| format!("{} is not boolean: {}", param.condition, err))?;
+
+        let port = if result { PORT_TRUE } else { PORT_FALSE };
+
+        let mut output = HashMap::new();
+        output.insert("result".to_string(), Value::Bool(result));
+        output.insert(OUTPUT_PORT.to_string(), Value::from(port));
+        Ok(Some(output))
+    }
+}
+
+#[derive(Default)]
+pub struct BranchRunnerFactory;
+
+impl BranchRunnerFactory {
+    pub fn new() -> Self {
+        BranchRunnerFactory
+    }
+}
+
+impl NodeRunnerFactory for BranchRunnerFactory {
+    fn create(&self) -> Box<dyn NodeRunnerControl> {
+        Box::new(NodeRunnerController::new(BranchRunner::new()))
+    }
+}
@@ -110,9 +110,13 @@ impl NodeDefine for DataAggregatorNode {
                     en: "List of keys for object mode (optional, defaults to indices)".to_string(),
                 }),
                 enums: vec![],
-                default: None,
+                default: Some("[]".to_string()),
                 condition: None,
             },
         ]
     }
+
+    fn has_side_effects(&self) -> bool {
+        false
+    }
 }
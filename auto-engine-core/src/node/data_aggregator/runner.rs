@@ -30,10 +30,7 @@ impl NodeRunner for DataAggregatorRunner {
         ctx: &Context,
         param: Self::ParamType,
     ) -> Result<Option<HashMap<String, Value>>, String> {
-        {
-            let map = ctx.string_value.read().await;
-            log::info!("map: {:?}", map.keys());
-        }
+        log::info!("map: {:?}", ctx.values().await.keys());
         let mut values = vec![];
 
         for source in param.sources.iter() {
@@ -0,0 +1,87 @@
+use crate::types::field::{FieldType, SchemaField};
+use crate::types::node::{I18nValue, NodeDefine};
+use std::collections::HashMap;
+
+pub const NODE_TYPE: &str = "End";
+
+#[derive(Default)]
+pub struct EndNode;
+
+impl EndNode {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl NodeDefine for EndNode {
+    fn action_type(&self) -> String {
+        NODE_TYPE.to_string()
+    }
+
+    fn name(&self) -> I18nValue {
+        I18nValue {
+            zh: "结束".to_string(),
+            en: "End".to_string(),
+        }
+    }
+
+    fn icon(&self) -> String {
+        String::from(
+            "data:image/svg+xml;base64,PHN2ZyB4bWxucz0iaHR0cDovL3d3dy53My5vcmcvMjAwMC9zdmciIHdpZHRoPSIyNCIgaGVpZ2h0PSIyNCIgdmlld0JveD0iMCAwIDI0IDI0IiBmaWxsPSJub25lIiBzdHJva2U9ImN1cnJlbnRDb2xvciIgc3Ryb2tlLXdpZHRoPSIxLjUiIHN0cm9rZS1saW5lY2FwPSJyb3VuZCIgc3Ryb2tlLWxpbmVqb2luPSJyb3VuZCIgY2xhc3M9Imx1Y2lkZSBsdWNpZGUtY2lyY2xlLXN0b3AtaWNvbiBsdWNpZGUtY2lyY2xlLXN0b3AiPjxjaXJjbGUgY3g9IjEyIiBjeT0iMTIiIHI9IjEwIi8+PHJlY3QgeD0iOSIgeT0iOSIgd2lkdGg9IjYiIGhlaWdodD0iNiIgcng9IjEiLz48L3N2Zz4=",
+        )
+    }
+
+    fn category(&self) -> Option<I18nValue> {
+        Some(I18nValue {
+            zh: "基础节点".to_string(),
+            en: "Basic Node".to_string(),
+        })
+    }
+
+    fn description(&self) -> Option<I18nValue> {
+        Some(I18nValue {
+            zh: "定义工作流的返回值".to_string(),
+            en: "Defines the values the workflow returns".to_string(),
+        })
+    }
+
+    fn output_schema(&self, input: HashMap<String, serde_json::Value>) -> Vec<SchemaField> {
+        let Some(serde_json::Value::Object(outputs)) = input.get("outputs") else {
+            return vec![];
+        };
+
+        outputs
+            .keys()
+            .map(|name| SchemaField {
+                name: name.to_string(),
+                field_type: Default::default(),
+                item_type: None,
+                description: None,
+                enums: vec![],
+                default: None,
+                condition: None,
+            })
+            .collect()
+    }
+
+    fn input_schema(&self) -> Vec<SchemaField> {
+        vec![SchemaField {
+            name: "outputs".to_string(),
+            field_type: FieldType::Object,
+            item_type: None,
+            description: Some(I18nValue {
+                zh: "返回值名称到变量的映射，例如：{\"token\": \"${ctx.login.token}\"}"
+                    .to_string(),
+                en: "Maps every returned name to a variable, e.g.: {\"token\": \"${ctx.login.token}\"}"
+                    .to_string(),
+            }),
+            enums: vec![],
+            default: None,
+            condition: None,
+        }]
+    }
+
+    fn has_side_effects(&self) -> bool {
+        false
+    }
+}
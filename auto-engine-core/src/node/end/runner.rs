@@ -0,0 +1,74 @@
+use crate::context::Context;
+use crate::types::node::{NodeRunner, NodeRunnerControl, NodeRunnerController, NodeRunnerFactory};
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EndParam {
+    #[serde(default)]
+    pub outputs: Option<HashMap<String, Value>>,
+}
+
+/// Resolves the declared outputs. The workflow runner returns them as the
+/// outputs of the run.
+#[derive(Default)]
+pub struct EndRunner;
+
+impl EndRunner {
+    pub fn new() -> Self {
+        EndRunner
+    }
+}
+
+#[async_trait::async_trait]
+impl NodeRunner for EndRunner {
+    type ParamType = EndParam;
+
+    async fn run(
+        &mut self,
+        ctx: &Context,
+        param: Self::ParamType,
+    ) -> Result<Option<HashMap<String, Value>>, String> {
+        let mut outputs = HashMap::new();
+        for (name, value) in param.outputs.unwrap_or_default() {
+            outputs.insert(name, resolve(ctx, value).await);
+        }
+        Ok(Some(outputs))
+    }
+}
+
+/// A value that is a single `${}` reference keeps the type of the variable,
+/// any other string is interpolated.
+async fn resolve(ctx: &Context, value: Value) -> Value {
+    let Value::String(expression) = value else {
+        return value;
+    };
+
+    let is_reference = utils::REGEX_PARSE_VARIABLES
+        .find(&expression)
+        .is_some_and(|found| found.as_str() == expression.trim());
+    if is_reference {
+        return ctx
+            .get_value_parse(expression.trim())
+            .await
+            .unwrap_or(Value::Null);
+    }
+    Value::String(utils::parse_variables(ctx, &expression).await)
+}
+
+#[derive(Default)]
+pub struct EndRunnerFactory;
+
+impl EndRunnerFactory {
+    pub fn new() -> Self {
+        EndRunnerFactory
+    }
+}
+
+impl NodeRunnerFactory for EndRunnerFactory {
+    fn create(&self) -> Box<dyn NodeRunnerControl> {
+        Box::new(NodeRunnerController::new(EndRunner::new()))
+    }
+}
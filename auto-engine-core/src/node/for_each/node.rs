@@ -0,0 +1,187 @@
+use crate::types::field::{FieldType, SchemaField};
+use crate::types::node::{I18nValue, NodeDefine};
+use std::collections::HashMap;
+
+pub const NODE_TYPE: &str = "ForEach";
+
+/// Connections on this port form the loop body that runs once per item.
+pub const PORT_BODY: &str = "body";
+/// Followed once the loop has finished.
+pub const PORT_DONE: &str = "done";
+
+pub const LOOP_INDEX: &str = "loop.index";
+pub const LOOP_ITEM: &str = "loop.item";
+
+pub const DEFAULT_MAX_ITERATIONS: &str = "1000";
+
+#[derive(Default)]
+pub struct ForEachNode;
+
+impl ForEachNode {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl NodeDefine for ForEachNode {
+    fn action_type(&self) -> String {
+        NODE_TYPE.to_string()
+    }
+
+    fn name(&self) -> I18nValue {
+        I18nValue {
+            zh: "循环".to_string(),
+            en: "For Each".to_string(),
+        }
+    }
+
+    fn icon(&self) -> String {
+        String::from(
+            "data:image/svg+xml;base64,PHN2ZyB4bWxucz0iaHR0cDovL3d3dy53My5vcmcvMjAwMC9zdmciIHdpZHRoPSIyNCIgaGVpZ2h0PSIyNCIgdmlld0JveD0iMCAwIDI0IDI0IiBmaWxsPSJub25lIiBzdHJva2U9ImN1cnJlbnRDb2xvciIgc3Ryb2tlLXdpZHRoPSIxLjUiIHN0cm9rZS1saW5lY2FwPSJyb3VuZCIgc3Ryb2tlLWxpbmVqb2luPSJyb3VuZCIgY2xhc3M9Imx1Y2lkZSBsdWNpZGUtcmVwZWF0LWljb24gbHVjaWRlLXJlcGVhdCI+PHBhdGggZD0ibTE3IDIgNCA0LTQgNCIvPjxwYXRoIGQ9Ik0zIDExdi0xYTQgNCAwIDAgMSA0LTRoMTQiLz48cGF0aCBkPSJtNyAyMi00LTQgNC00Ii8+PHBhdGggZD0iTTIxIDEzdjFhNCA0IDAgMCAxLTQgNEgzIi8+PC9zdmc+",
+        )
+    }
+
+    fn category(&self) -> Option<I18nValue> {
+        Some(I18nValue {
+            zh: "基础节点".to_string(),
+            en: "Basic Node".to_string(),
+        })
+    }
+
+    fn description(&self) -> Option<I18nValue> {
+        Some(I18nValue {
+            zh: "对数组中的每一项（或指定次数）重复执行 body 分支".to_string(),
+            en: "Run the body branch once per array item or a fixed number of times".to_string(),
+        })
+    }
+
+    fn output_schema(&self, _input: HashMap<String, serde_json::Value>) -> Vec<SchemaField> {
+        vec![
+            SchemaField {
+                name: "items".to_string(),
+                field_type: FieldType::Array,
+                item_type: None,
+                description: Some(I18nValue {
+                    zh: "需要遍历的数据项".to_string(),
+                    en: "Items the loop iterates over".to_string(),
+                }),
+                enums: vec![],
+                default: None,
+                condition: None,
+            },
+            SchemaField {
+                name: "count".to_string(),
+                field_type: FieldType::Number,
+                item_type: None,
+                description: Some(I18nValue {
+                    zh: "计划执行的次数".to_string(),
+                    en: "Number of planned iterations".to_string(),
+                }),
+                enums: vec![],
+                default: None,
+                condition: None,
+            },
+            SchemaField {
+                name: "index".to_string(),
+                field_type: FieldType::Number,
+                item_type: None,
+                description: Some(I18nValue {
+                    zh: "当前循环序号，从 0 开始，同 ${loop.index}".to_string(),
+                    en: "Current iteration, starting at 0, same as ${loop.index}".to_string(),
+                }),
+                enums: vec![],
+                default: None,
+                condition: None,
+            },
+            SchemaField {
+                name: "item".to_string(),
+                field_type: FieldType::Any,
+                item_type: None,
+                description: Some(I18nValue {
+                    zh: "当前数据项，同 ${loop.item}".to_string(),
+                    en: "Current item, same as ${loop.item}".to_string(),
+                }),
+                enums: vec![],
+                default: None,
+                condition: None,
+            },
+            SchemaField {
+                name: "iterations".to_string(),
+                field_type: FieldType::Number,
+                item_type: None,
+                description: Some(I18nValue {
+                    zh: "实际执行的次数".to_string(),
+                    en: "Number of iterations that actually ran".to_string(),
+                }),
+                enums: vec![],
+                default: None,
+                condition: None,
+            },
+        ]
+    }
+
+    fn input_schema(&self) -> Vec<SchemaField> {
+        vec![
+            SchemaField {
+                name: "items".to_string(),
+                field_type: FieldType::String,
+                item_type: None,
+                description: Some(I18nValue {
+                    zh: "要遍历的数组，例如：${ctx.aggregator.result} 或 ctx.aggregator.result"
+                        .to_string(),
+                    en: "Array to iterate, e.g.: ${ctx.aggregator.result} or ctx.aggregator.result"
+                        .to_string(),
+                }),
+                enums: vec![],
+                default: Some("".to_string()),
+                condition: None,
+            },
+            SchemaField {
+                name: "count".to_string(),
+                field_type: FieldType::Number,
+                item_type: None,
+                description: Some(I18nValue {
+                    zh: "未设置 items 时的循环次数".to_string(),
+                    en: "Number of iterations when items is empty".to_string(),
+                }),
+                enums: vec![],
+                default: Some("0".to_string()),
+                condition: None,
+            },
+            SchemaField {
+                name: "max_iterations".to_string(),
+                field_type: FieldType::Number,
+                item_type: None,
+                description: Some(I18nValue {
+                    zh: "最多执行的次数".to_string(),
+                    en: "Upper bound for the number of iterations".to_string(),
+                }),
+                enums: vec![],
+                default: Some(DEFAULT_MAX_ITERATIONS.to_string()),
+                condition: None,
+            },
+            SchemaField {
+                name: "break_condition".to_string(),
+                field_type: FieldType::String,
+                item_type: None,
+                description: Some(I18nValue {
+                    zh: "每次循环结束后计算，为 true 时退出循环，例如：${ctx.find.matched} == true"
+                        .to_string(),
+                    en: "Checked after every iteration, the loop stops once it is true, e.g.: ${ctx.find.matched} == true"
+                        .to_string(),
+                }),
+                enums: vec![],
+                default: Some("".to_string()),
+                condition: None,
+            },
+        ]
+    }
+
+    fn output_ports(&self) -> Vec<String> {
+        vec![PORT_BODY.to_string(), PORT_DONE.to_string()]
+    }
+
+    fn has_side_effects(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,98 @@
+use crate::context::Context;
+use crate::node::for_each::node::PORT_DONE;
+use crate::types::node::{
+    NodeRunner, NodeRunnerControl, NodeRunnerController, NodeRunnerFactory, OUTPUT_PORT,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ForEachParam {
+    #[serde(default)]
+    pub items: String,
+    #[serde(default)]
+    pub count: i64,
+    pub max_iterations: i64,
+}
+
+/// Resolves the items of a loop. Running the body is up to the workflow
+/// runner, which follows the `body` port once per item.
+#[derive(Default)]
+pub struct ForEachRunner;
+
+impl ForEachRunner {
+    pub fn new() -> Self {
+        ForEachRunner
+    }
+
+    async fn resolve_items(
+        &self,
+        ctx: &Context,
+        param: &ForEachParam,
+    ) -> Result<Vec<Value>, String> {
+        let items = param.items.trim();
+        if items.is_empty() {
+            return Ok((0..param.count.max(0)).map(Value::from).collect());
+        }
+
+        // `${...}` references arrive already serialized by the controller,
+        // a bare context key is looked up directly.
+        let value = match serde_json::from_str::<Value>(items) {
+            Ok(value) => value,
+            Err(_) => ctx
+                .get_value_parse(items)
+                .await
+                .ok_or_else(|| format!("Loop items `{}` not found", items))?,
+        };
+
+        match value {
+            Value::Array(values) => Ok(values),
+            other => Err(format!("Loop items must be an array, got: {}", other)),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl NodeRunner for ForEachRunner {
+    type ParamType = ForEachParam;
+
+    async fn run(
+        &mut self,
+        ctx: &Context,
+        param: Self::ParamType,
+    ) -> Result<Option<HashMap<String, Value>>, String> {
+        let mut items = self.resolve_items(ctx, &param).await?;
+
+        let max_iterations = param.max_iterations.max(0) as usize;
+        if items.len() > max_iterations {
+            log::warn!(
+                "loop has {} items, only the first {} will run",
+                items.len(),
+                max_iterations
+            );
+            items.truncate(max_iterations);
+        }
+
+        let mut output = HashMap::new();
+        output.insert("count".to_string(), Value::from(items.len()));
+        output.insert("items".to_string(), Value::Array(items));
+        output.insert(OUTPUT_PORT.to_string(), Value::from(PORT_DONE));
+        Ok(Some(output))
+    }
+}
+
+#[derive(Default)]
+pub struct ForEachRunnerFactory;
+
+impl ForEachRunnerFactory {
+    pub fn new() -> Self {
+        ForEachRunnerFactory
+    }
+}
+
+impl NodeRunnerFactory for ForEachRunnerFactory {
+    fn create(&self) -> Box<dyn NodeRunnerControl> {
+        Box::new(NodeRunnerController::new(ForEachRunner::new()))
+    }
+}
@@ -109,7 +109,7 @@ impl NodeDefine for HttpNode {
                     en: "Optional headers list, format: Key: Value".to_string(),
                 }),
                 enums: vec![],
-                default: None,
+                default: Some("[]".to_string()),
                 condition: None,
             },
             SchemaField {
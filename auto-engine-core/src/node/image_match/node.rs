@@ -4,6 +4,9 @@ use crate::types::field::{
 use crate::types::node::{I18nValue, NodeDefine};
 use std::collections::HashMap;
 
+pub const PORT_MATCH: &str = "match";
+pub const PORT_NO_MATCH: &str = "no_match";
+
 #[derive(Default)]
 pub struct ImageMatchNode;
 
@@ -59,6 +62,18 @@ impl NodeDefine for ImageMatchNode {
                 default: Some("0.8".to_string()),
                 condition: None,
             },
+            SchemaField {
+                name: "matched".to_string(),
+                field_type: FieldType::Boolean,
+                item_type: None,
+                description: Some(I18nValue {
+                    zh: "是否达到目标匹配分值".to_string(),
+                    en: "Whether the target matching score was reached".to_string(),
+                }),
+                enums: vec![],
+                default: None,
+                condition: None,
+            },
             SchemaField {
                 name: "cost_time".to_string(),
                 field_type: FieldType::Number,
@@ -148,6 +163,18 @@ impl NodeDefine for ImageMatchNode {
                 default: Some(String::from("1")),
                 condition: None,
             },
+            SchemaField {
+                name: "no_match".to_string(),
+                field_type: FieldType::String,
+                item_type: None,
+                description: Some(I18nValue {
+                    zh: "未匹配时的处理方式：Error（节点失败并重试）或 Port（走 no_match 分支）".to_string(),
+                    en: "What to do without a match: Error (fail and retry) or Port (follow the no_match branch)".to_string(),
+                }),
+                enums: vec!["Error".to_string(), "Port".to_string()],
+                default: Some("Error".to_string()),
+                condition: None,
+            },
             SchemaField {
                 name: "template_image".to_string(),
                 field_type: FieldType::File,
@@ -180,4 +207,8 @@ impl NodeDefine for ImageMatchNode {
             },
         ]
     }
+
+    fn output_ports(&self) -> Vec<String> {
+        vec![PORT_MATCH.to_string(), PORT_NO_MATCH.to_string()]
+    }
 }
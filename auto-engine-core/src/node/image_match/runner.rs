@@ -1,6 +1,9 @@
 use crate::action;
 use crate::context::Context;
-use crate::types::node::{NodeRunner, NodeRunnerControl, NodeRunnerController, NodeRunnerFactory};
+use crate::node::image_match::node::{PORT_MATCH, PORT_NO_MATCH};
+use crate::types::node::{
+    NodeRunner, NodeRunnerControl, NodeRunnerController, NodeRunnerFactory, OUTPUT_PORT,
+};
 use opencv::core::{Mat, MatTraitConst, Point, Size};
 use opencv::imgproc::TM_CCOEFF_NORMED;
 use opencv::{imgcodecs, imgproc};
@@ -16,6 +19,8 @@ pub struct ImageMatchParams {
     imread_type: String,
     use_screenshot: bool,
     resize: f64,
+    #[serde(default)]
+    no_match: String,
 }
 
 pub struct ImageMatchRunner {
@@ -139,33 +144,41 @@ impl NodeRunner for ImageMatchRunner {
         )
         .map_err(|e| e.to_string())?;
 
-        let (x, y, score) = if max_val > 0.8 {
-            let template_size = template_mat.size().map_err(|e| e.to_string())?;
-            let center_x = max_loc.x + template_size.width / 2;
-            let center_y = max_loc.y + template_size.height / 2;
-
-            log::info!("center x: {}, center y: {}", center_x, center_y);
-            log::info!(
-                "resized center x: {},resized center y: {}",
-                center_x,
-                center_y
-            );
-            (center_x, center_y, max_val)
-        } else {
-            return Err(
-                format!("Failed to find image image. max image score {max_val}").to_string(),
-            );
-        };
-
         let mut res = HashMap::new();
-        res.insert("score".to_string(), serde_json::json!(score));
-        res.insert("x".to_string(), serde_json::json!(x));
-        res.insert("y".to_string(), serde_json::json!(y));
         res.insert(
             "cost_time".to_string(),
             serde_json::json!(duration.as_secs_f32()),
         );
 
+        if max_val <= 0.8 {
+            if !param.no_match.eq_ignore_ascii_case("port") {
+                return Err(
+                    format!("Failed to find image image. max image score {max_val}").to_string(),
+                );
+            }
+            res.insert("score".to_string(), serde_json::json!(max_val));
+            res.insert("matched".to_string(), serde_json::json!(false));
+            res.insert(OUTPUT_PORT.to_string(), serde_json::json!(PORT_NO_MATCH));
+            return Ok(Some(res));
+        }
+
+        let template_size = template_mat.size().map_err(|e| e.to_string())?;
+        let center_x = max_loc.x + template_size.width / 2;
+        let center_y = max_loc.y + template_size.height / 2;
+
+        log::info!("center x: {}, center y: {}", center_x, center_y);
+        log::info!(
+            "resized center x: {},resized center y: {}",
+            center_x,
+            center_y
+        );
+
+        res.insert("score".to_string(), serde_json::json!(max_val));
+        res.insert("x".to_string(), serde_json::json!(center_x));
+        res.insert("y".to_string(), serde_json::json!(center_y));
+        res.insert("matched".to_string(), serde_json::json!(true));
+        res.insert(OUTPUT_PORT.to_string(), serde_json::json!(PORT_MATCH));
+
         Ok(Some(res))
     }
 }
@@ -87,7 +87,7 @@ impl NodeDefine for KeyboardNode {
                     en: "Text to input when mode is Type".to_owned(),
                 }),
                 enums: vec![],
-                default: None,
+                default: Some("".to_string()),
                 condition: None,
             },
         ]
@@ -97,8 +97,8 @@ impl NodeRunner for KeyboardNodeRunner {
                     .value
                     .ok_or_else(|| "mode Type requires `value`".to_string())?;
 
+                log::info!("will input {}", ctx.secrets().redact(&value));
                 self.with_enigo(ctx, move |enigo| {
-                    log::info!("will input {value}");
                     enigo
                         .text(&value)
                         .map_err(|err| format!("Failed to type text: {err}"))
@@ -1,7 +1,10 @@
 use crate::types::field::{FieldType, SchemaField};
+use crate::types::input::InputField;
 use crate::types::node::{I18nValue, NodeDefine};
 use std::collections::HashMap;
 
+pub const NODE_TYPE: &str = "Start";
+
 pub struct StartNode;
 
 impl StartNode {
@@ -12,7 +15,7 @@ impl StartNode {
 
 impl NodeDefine for StartNode {
     fn action_type(&self) -> String {
-        String::from("Start")
+        NODE_TYPE.to_string()
     }
 
     fn name(&self) -> I18nValue {
@@ -46,12 +49,23 @@ impl NodeDefine for StartNode {
         let val = input.get("params").unwrap_or_default().clone();
         let params: HashMap<String, serde_json::Value> =
             serde_json::from_value(val).unwrap_or_default();
+        let inputs = parse_inputs(&input).unwrap_or_default();
 
         let mut outputs = vec![];
-        for (key, _value) in params.iter() {
+        for (key, value) in params.iter() {
+            if inputs.iter().any(|input| &input.field.name == key) {
+                continue;
+            }
+            let field_type = match value {
+                serde_json::Value::Number(_) => FieldType::Number,
+                serde_json::Value::Bool(_) => FieldType::Boolean,
+                serde_json::Value::Array(_) => FieldType::Array,
+                serde_json::Value::Object(_) => FieldType::Object,
+                _ => FieldType::String,
+            };
             outputs.push(SchemaField {
                 name: key.to_string(),
-                field_type: Default::default(),
+                field_type,
                 item_type: None,
                 description: None,
                 enums: vec![],
@@ -59,22 +73,52 @@ impl NodeDefine for StartNode {
                 condition: None,
             });
         }
+        outputs.extend(inputs.into_iter().map(|input| input.field));
 
         outputs
     }
 
     fn input_schema(&self) -> Vec<SchemaField> {
-        vec![SchemaField {
-            name: "params".to_string(),
-            field_type: FieldType::Object,
-            item_type: None,
-            description: Some(I18nValue {
-                zh: "".to_string(),
-                en: "".to_string(),
-            }),
-            enums: vec![],
-            default: None,
-            condition: None,
-        }]
+        vec![
+            SchemaField {
+                name: "params".to_string(),
+                field_type: FieldType::Object,
+                item_type: None,
+                description: Some(I18nValue {
+                    zh: "".to_string(),
+                    en: "".to_string(),
+                }),
+                enums: vec![],
+                default: None,
+                condition: None,
+            },
+            SchemaField {
+                name: "inputs".to_string(),
+                field_type: FieldType::Array,
+                item_type: Some(FieldType::Object),
+                description: Some(I18nValue {
+                    zh: "启动工作流时传入的参数，每项包含 name、type，可选 default、required、enums"
+                        .to_string(),
+                    en: "Values the workflow is launched with, each with a name and type and an optional default, required flag and enums"
+                        .to_string(),
+                }),
+                enums: vec![],
+                default: Some("[]".to_string()),
+                condition: None,
+            },
+        ]
+    }
+
+    fn has_side_effects(&self) -> bool {
+        false
+    }
+}
+
+/// The inputs a Start node declares in its `inputs` list.
+pub fn parse_inputs(input: &HashMap<String, serde_json::Value>) -> Result<Vec<InputField>, String> {
+    match input.get("inputs") {
+        None | Some(serde_json::Value::Null) => Ok(vec![]),
+        Some(inputs) => serde_json::from_value(inputs.clone())
+            .map_err(|e| format!("Invalid Start node inputs: {}", e)),
     }
 }
@@ -0,0 +1,147 @@
+use crate::types::field::{FieldType, SchemaField};
+use crate::types::node::{I18nValue, NodeDefine};
+use std::collections::HashMap;
+
+pub const NODE_TYPE: &str = "SubWorkflow";
+
+#[derive(Default)]
+pub struct SubWorkflowNode;
+
+impl SubWorkflowNode {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl NodeDefine for SubWorkflowNode {
+    fn action_type(&self) -> String {
+        NODE_TYPE.to_string()
+    }
+
+    fn name(&self) -> I18nValue {
+        I18nValue {
+            zh: "子工作流".to_string(),
+            en: "Sub Workflow".to_string(),
+        }
+    }
+
+    fn icon(&self) -> String {
+        String::from(
+            "data:image/svg+xml;base64,PHN2ZyB4bWxucz0iaHR0cDovL3d3dy53My5vcmcvMjAwMC9zdmciIHdpZHRoPSIyNCIgaGVpZ2h0PSIyNCIgdmlld0JveD0iMCAwIDI0IDI0IiBmaWxsPSJub25lIiBzdHJva2U9ImN1cnJlbnRDb2xvciIgc3Ryb2tlLXdpZHRoPSIxLjUiIHN0cm9rZS1saW5lY2FwPSJyb3VuZCIgc3Ryb2tlLWxpbmVqb2luPSJyb3VuZCIgY2xhc3M9Imx1Y2lkZSBsdWNpZGUtd29ya2Zsb3ctaWNvbiBsdWNpZGUtd29ya2Zsb3ciPjxyZWN0IHdpZHRoPSI4IiBoZWlnaHQ9IjgiIHg9IjMiIHk9IjMiIHJ4PSIyIi8+PHBhdGggZD0iTTcgMTF2NGEyIDIgMCAwIDAgMiAyaDQiLz48cmVjdCB3aWR0aD0iOCIgaGVpZ2h0PSI4IiB4PSIxMyIgeT0iMTMiIHJ4PSIyIi8+PC9zdmc+",
+        )
+    }
+
+    fn category(&self) -> Option<I18nValue> {
+        Some(I18nValue {
+            zh: "基础节点".to_string(),
+            en: "Basic Node".to_string(),
+        })
+    }
+
+    fn description(&self) -> Option<I18nValue> {
+        Some(I18nValue {
+            zh: "运行另一个工作流文件，并把它的部分结果作为本节点的输出".to_string(),
+            en: "Run another workflow file and expose selected results as outputs".to_string(),
+        })
+    }
+
+    fn output_schema(&self, input: HashMap<String, serde_json::Value>) -> Vec<SchemaField> {
+        let outputs = input
+            .get("outputs")
+            .and_then(|outputs| outputs.as_str())
+            .unwrap_or_default();
+
+        let mut fields = vec![SchemaField {
+            name: "workflow".to_string(),
+            field_type: FieldType::String,
+            item_type: None,
+            description: Some(I18nValue {
+                zh: "子工作流文件的完整路径".to_string(),
+                en: "Full path of the sub workflow file".to_string(),
+            }),
+            enums: vec![],
+            default: None,
+            condition: None,
+        }];
+        for (name, _) in parse_outputs(outputs) {
+            fields.push(SchemaField {
+                name,
+                field_type: Default::default(),
+                item_type: None,
+                description: None,
+                enums: vec![],
+                default: None,
+                condition: None,
+            });
+        }
+        fields
+    }
+
+    fn input_schema(&self) -> Vec<SchemaField> {
+        vec![
+            SchemaField {
+                name: "workflow".to_string(),
+                field_type: FieldType::File,
+                item_type: None,
+                description: Some(I18nValue {
+                    zh: "工作流文件，相对于当前工作流所在目录，例如：routines/login.yaml"
+                        .to_string(),
+                    en: "Workflow file relative to the current workflow folder, e.g.: routines/login.yaml"
+                        .to_string(),
+                }),
+                enums: vec![],
+                default: None,
+                condition: None,
+            },
+            SchemaField {
+                name: "inputs".to_string(),
+                field_type: FieldType::String,
+                item_type: None,
+                description: Some(I18nValue {
+                    zh: "传给子工作流 Start 节点 params 的 JSON 对象，例如：{\"user\": \"${ctx.start.user}\"}"
+                        .to_string(),
+                    en: "JSON object passed to the Start params of the sub workflow, e.g.: {\"user\": \"${ctx.start.user}\"}"
+                        .to_string(),
+                }),
+                enums: vec![],
+                default: Some("".to_string()),
+                condition: None,
+            },
+            SchemaField {
+                name: "outputs".to_string(),
+                field_type: FieldType::String,
+                item_type: None,
+                description: Some(I18nValue {
+                    zh: "需要带回的子工作流变量，用逗号分隔，可用 别名=变量 重命名，例如：token=ctx.login.token, ctx.nav.page。留空时带回子工作流 End 节点的返回值"
+                        .to_string(),
+                    en: "Comma separated sub workflow variables to expose, rename with alias=variable, e.g.: token=ctx.login.token, ctx.nav.page. Empty exposes what the End node of the sub workflow returns"
+                        .to_string(),
+                }),
+                enums: vec![],
+                default: Some("".to_string()),
+                condition: None,
+            },
+        ]
+    }
+
+    fn has_side_effects(&self) -> bool {
+        false
+    }
+}
+
+/// Parses the `outputs` input into `(alias, child variable)` pairs. Without
+/// an explicit alias the last segment of the variable is used.
+pub fn parse_outputs(outputs: &str) -> Vec<(String, String)> {
+    outputs
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| match entry.split_once('=') {
+            Some((alias, key)) => (alias.trim().to_string(), key.trim().to_string()),
+            None => {
+                let alias = entry.rsplit('.').next().unwrap_or(entry);
+                (alias.to_string(), entry.to_string())
+            }
+        })
+        .collect()
+}
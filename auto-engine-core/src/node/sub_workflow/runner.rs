@@ -0,0 +1,90 @@
+use crate::context::Context;
+use crate::node::sub_workflow::node::parse_outputs;
+use crate::types::node::{NodeRunner, NodeRunnerControl, NodeRunnerController, NodeRunnerFactory};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SubWorkflowParam {
+    pub workflow: String,
+    #[serde(default)]
+    pub inputs: String,
+    #[serde(default)]
+    pub outputs: String,
+}
+
+/// Resolves which workflow to call and with what. Running it is up to the
+/// workflow runner, which reads `workflow`, `inputs` and `outputs` back from
+/// the result.
+#[derive(Default)]
+pub struct SubWorkflowRunner;
+
+impl SubWorkflowRunner {
+    pub fn new() -> Self {
+        SubWorkflowRunner
+    }
+}
+
+#[async_trait::async_trait]
+impl NodeRunner for SubWorkflowRunner {
+    type ParamType = SubWorkflowParam;
+
+    async fn run(
+        &mut self,
+        ctx: &Context,
+        param: Self::ParamType,
+    ) -> Result<Option<HashMap<String, Value>>, String> {
+        let workflow = param.workflow.trim();
+        if workflow.is_empty() {
+            return Err("Sub workflow file is required".to_string());
+        }
+        let path = ctx.workflow_path.join(workflow);
+        if !path.is_file() {
+            return Err(format!("Sub workflow {} does not exist", path.display()));
+        }
+
+        let inputs = match param.inputs.trim() {
+            "" => Value::Object(Default::default()),
+            inputs => match serde_json::from_str::<Value>(inputs) {
+                Ok(value @ Value::Object(_)) => value,
+                Ok(other) => {
+                    return Err(format!(
+                        "Sub workflow inputs must be an object, got: {}",
+                        other
+                    ));
+                }
+                Err(e) => return Err(format!("Invalid sub workflow inputs: {}", e)),
+            },
+        };
+
+        let outputs = parse_outputs(&param.outputs)
+            .into_iter()
+            .map(|(alias, key)| (alias, Value::String(key)))
+            .collect();
+
+        let mut output = HashMap::new();
+        output.insert(
+            "workflow".to_string(),
+            Value::String(path.to_string_lossy().to_string()),
+        );
+        output.insert("inputs".to_string(), inputs);
+        output.insert("outputs".to_string(), Value::Object(outputs));
+        Ok(Some(output))
+    }
+}
+
+#[derive(Default)]
+pub struct SubWorkflowRunnerFactory;
+
+impl SubWorkflowRunnerFactory {
+    pub fn new() -> Self {
+        SubWorkflowRunnerFactory
+    }
+}
+
+impl NodeRunnerFactory for SubWorkflowRunnerFactory {
+    fn create(&self) -> Box<dyn NodeRunnerControl> {
+        Box::new(NodeRunnerController::new(SubWorkflowRunner::new()))
+    }
+}
@@ -2,6 +2,8 @@ use crate::types::field::{FieldType, SchemaField};
 use crate::types::node::{I18nValue, NodeDefine};
 use std::collections::HashMap;
 
+pub const NODE_TYPE: &str = "TimeWait";
+
 #[derive(Default)]
 pub struct TimeWaitNode;
 
@@ -13,7 +15,7 @@ impl TimeWaitNode {
 
 impl NodeDefine for TimeWaitNode {
     fn action_type(&self) -> String {
-        String::from("TimeWait")
+        NODE_TYPE.to_string()
     }
 
     fn name(&self) -> I18nValue {
@@ -53,8 +55,9 @@ impl NodeDefine for TimeWaitNode {
             field_type: FieldType::Number,
             item_type: None,
             description: Some(I18nValue {
-                zh: "需要等待的时间".to_string(),
-                en: "The time required to wait".to_string(),
+                zh: "需要等待的时间（秒），可以是小数，如 0.5".to_string(),
+                en: "The time required to wait, in seconds, fractions allowed, e.g. 0.5"
+                    .to_string(),
             }),
             enums: vec![],
             default: None,
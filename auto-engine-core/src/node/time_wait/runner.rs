@@ -6,7 +6,10 @@ use std::collections::HashMap;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct TimeWaitParam {
-    pub duration: u64,
+    /// In seconds. Fractions are allowed so legacy pipeline waits, given in
+    /// milliseconds, convert exactly; whole numbers mean what they did
+    /// when this was a `u64`.
+    pub duration: f64,
 }
 
 #[derive(Default)]
@@ -28,7 +31,9 @@ impl NodeRunner for TimeWaitRunner {
         param: Self::ParamType,
     ) -> Result<Option<HashMap<String, Value>>, String> {
         log::info!("Running time wait {:?}", param);
-        tokio::time::sleep(tokio::time::Duration::from_secs(param.duration)).await;
+        let duration = tokio::time::Duration::try_from_secs_f64(param.duration.max(0.0))
+            .map_err(|e| format!("Invalid wait duration {}: {}", param.duration, e))?;
+        tokio::time::sleep(duration).await;
         Ok(None)
     }
 }
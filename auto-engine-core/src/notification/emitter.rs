@@ -1,4 +1,6 @@
+use crate::secret::SecretStore;
 use serde_json::Value;
+use std::sync::Arc;
 
 pub trait Emitter {
     fn emit(&self, event: &str, payload: Value) -> Result<(), String>;
@@ -27,3 +29,44 @@ impl NotificationEmitter {
         Ok(())
     }
 }
+
+/// Adds a `run_id` to every event before passing it on to `inner`, so the
+/// events of runs going on at the same time can be told apart.
+pub struct RunIdEmitter {
+    run_id: String,
+    inner: Arc<NotificationEmitter>,
+}
+
+impl RunIdEmitter {
+    pub fn new(run_id: String, inner: Arc<NotificationEmitter>) -> Self {
+        Self { run_id, inner }
+    }
+}
+
+impl Emitter for RunIdEmitter {
+    fn emit(&self, event: &str, mut payload: Value) -> Result<(), String> {
+        if let Value::Object(map) = &mut payload {
+            map.insert("run_id".to_string(), Value::from(self.run_id.as_str()));
+        }
+        self.inner.emit(event, payload)
+    }
+}
+
+/// Replaces the value of every secret in the events passed on to `inner`,
+/// see [`SecretStore::redact_value`].
+pub struct RedactingEmitter {
+    secrets: Arc<SecretStore>,
+    inner: Arc<NotificationEmitter>,
+}
+
+impl RedactingEmitter {
+    pub fn new(secrets: Arc<SecretStore>, inner: Arc<NotificationEmitter>) -> Self {
+        Self { secrets, inner }
+    }
+}
+
+impl Emitter for RedactingEmitter {
+    fn emit(&self, event: &str, payload: Value) -> Result<(), String> {
+        self.inner.emit(event, self.secrets.redact_value(payload))
+    }
+}
@@ -334,6 +334,13 @@ async fn handle_node_with_app(
     let conditions =
         serde_json::to_value(node.conditions().unwrap_or_default()).unwrap_or_default();
 
+    if node.disabled() {
+        log::info!("node {node_name} is disabled");
+        app.emit("node", event::NodeEventPayload::disabled(node_name))
+            .unwrap_or_default();
+        return RunningResult::Pass;
+    }
+
     if !node.check_conditions(&context).await {
         app.emit(
             "node",
@@ -466,6 +473,11 @@ async fn handle_node_without_app(
     let conditions =
         serde_json::to_value(node.conditions().unwrap_or_default()).unwrap_or_default();
 
+    if node.disabled() {
+        log::info!("node {node_name} is disabled");
+        return RunningResult::Pass;
+    }
+
     if !node.check_conditions(&context).await {
         return RunningResult::Pass;
     }
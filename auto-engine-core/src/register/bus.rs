@@ -1,5 +1,11 @@
+use crate::node::branch::node::BranchNode;
+use crate::node::branch::runner::BranchRunnerFactory;
 use crate::node::data_aggregator::node::DataAggregatorNode;
 use crate::node::data_aggregator::runner::DataAggregatorRunnerFactory;
+use crate::node::end::node::EndNode;
+use crate::node::end::runner::EndRunnerFactory;
+use crate::node::for_each::node::ForEachNode;
+use crate::node::for_each::runner::ForEachRunnerFactory;
 use crate::node::http::node::HttpNode;
 use crate::node::http::runner::HttpRunnerFactory;
 use crate::node::image_match::node::ImageMatchNode;
@@ -16,6 +22,8 @@ use crate::node::screen_capture::node::ScreenCaptureNode;
 use crate::node::screen_capture::runner::ScreenCaptureRunnerFactory;
 use crate::node::start::node::StartNode;
 use crate::node::start::runner::StartRunnerFactory;
+use crate::node::sub_workflow::node::SubWorkflowNode;
+use crate::node::sub_workflow::runner::SubWorkflowRunnerFactory;
 use crate::node::time_wait::node::TimeWaitNode;
 use crate::node::time_wait::runner::TimeWaitRunnerFactory;
 use crate::types::node::{NodeDefine, NodeRunnerControl, NodeRunnerFactory};
@@ -74,6 +82,19 @@ impl NodeRegisterBus {
             Box::new(DataAggregatorNode::new()),
             Box::new(DataAggregatorRunnerFactory::new()),
         );
+        self.register(
+            Box::new(BranchNode::new()),
+            Box::new(BranchRunnerFactory::new()),
+        );
+        self.register(
+            Box::new(ForEachNode::new()),
+            Box::new(ForEachRunnerFactory::new()),
+        );
+        self.register(
+            Box::new(SubWorkflowNode::new()),
+            Box::new(SubWorkflowRunnerFactory::new()),
+        );
+        self.register(Box::new(EndNode::new()), Box::new(EndRunnerFactory::new()));
         self
     }
 
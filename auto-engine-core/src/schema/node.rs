@@ -58,4 +58,26 @@ input_data:
             Some(&serde_json::json!(["a.png"]))
         );
     }
+
+    #[test]
+    pub fn test_disabled_round_trips() {
+        let yaml_str = r#"
+node_id: wait-1
+action_type: TimeWait
+name: wait
+disabled: true
+            "#;
+        let node: NodeSchema = serde_yaml::from_str(yaml_str).unwrap();
+        assert!(node.metadata.disabled);
+
+        let yaml = serde_yaml::to_string(&node).unwrap();
+        assert!(yaml.contains("disabled: true"), "{yaml}");
+        let node: NodeSchema = serde_yaml::from_str(&yaml).unwrap();
+        assert!(node.metadata.disabled);
+
+        let mut enabled = node.clone();
+        enabled.metadata.disabled = false;
+        let yaml = serde_yaml::to_string(&enabled).unwrap();
+        assert!(!yaml.contains("disabled"), "{yaml}");
+    }
 }
@@ -0,0 +1,509 @@
+use crate::node::start::node::NODE_TYPE as START_NODE_TYPE;
+use crate::schema::node::{NodeSchema, Position};
+use crate::schema::workflow::{Connection, WorkflowSchema};
+use crate::types::conditions::Conditions;
+use crate::types::{ImageRecognitionParams, MetaData, Node, Pipeline};
+use crate::utils::REGEX_PARSE_VARIABLES;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+const COLUMN_WIDTH: i64 = 300;
+const ROW_HEIGHT: i64 = 150;
+
+/// Score the legacy image recognition needed to find an image.
+const LEGACY_TARGET_SCORE: f64 = 0.8;
+
+/// Converts a legacy pipeline into a workflow the `WorkflowRunner` runs the
+/// same way.
+///
+/// Every node of a stage is connected to every node of the stage before it,
+/// so a stage starts once the previous one is done. A stage with nodes that
+/// may be skipped by their conditions is followed by a `TimeWait` of 0 ms
+/// that always runs, so the next stage still starts when all of them were
+/// skipped. References to the `<name>.<image>` variables of
+/// `ImageRecognition` nodes are rewritten to the outputs of the `ImageMatch`
+/// nodes replacing them.
+pub fn to_workflow(pipeline: &Pipeline) -> WorkflowSchema {
+    let mut converter = Converter {
+        names: pipeline
+            .iter()
+            .flat_map(|stage| stage.stage.iter())
+            .map(|node| node.metadata().name.clone())
+            .collect(),
+        ..Default::default()
+    };
+
+    // A Start in the first stage starts the workflow, otherwise one is added.
+    let entry = pipeline.first().and_then(|stage| {
+        stage
+            .stage
+            .iter()
+            .position(|node| matches!(node, Node::Start { .. }))
+    });
+    let metadata = match entry {
+        Some(index) => pipeline[0].stage[index].metadata().clone(),
+        None => MetaData {
+            name: converter.unique_name("start"),
+            ..Default::default()
+        },
+    };
+    let start = converter.add(START_NODE_TYPE, metadata, None, 0);
+
+    let mut previous = vec![start];
+    let mut column = 1;
+    for (index, stage) in pipeline.iter().enumerate() {
+        let mut ids = vec![];
+        let mut may_skip = false;
+        for (position, node) in stage.stage.iter().enumerate() {
+            if index == 0 && Some(position) == entry {
+                continue;
+            }
+            may_skip |= node.metadata().conditions.as_ref().is_some_and(can_fail);
+            ids.extend(converter.add_node(node, column));
+        }
+        if ids.is_empty() {
+            continue;
+        }
+        converter.connect_all(&previous, &ids);
+        column += 1;
+
+        if may_skip {
+            let name = converter.unique_name(&format!("stage {}", index + 1));
+            let barrier = converter.add(
+                "TimeWait",
+                MetaData {
+                    name,
+                    ..Default::default()
+                },
+                Some(HashMap::from([("duration".to_string(), Value::from(0))])),
+                column,
+            );
+            let barrier = vec![barrier];
+            converter.connect_all(&previous, &barrier);
+            converter.connect_all(&ids, &barrier);
+            column += 1;
+            previous = barrier;
+        } else {
+            previous = ids;
+        }
+    }
+
+    converter.rewrite_references();
+    WorkflowSchema {
+        nodes: converter.nodes,
+        connections: converter.connections,
+        ..Default::default()
+    }
+}
+
+fn can_fail(conditions: &Conditions) -> bool {
+    [
+        &conditions.exist,
+        &conditions.not_exist,
+        &conditions.condition,
+    ]
+    .iter()
+    .any(|value| value.as_deref().is_some_and(|value| !value.is_empty()))
+}
+
+#[derive(Default)]
+struct Converter {
+    nodes: Vec<NodeSchema>,
+    connections: Vec<Connection>,
+    names: HashSet<String>,
+    /// `<name>.<image>` of every legacy image, by the name of the
+    /// `ImageMatch` node looking for it.
+    images: HashMap<String, String>,
+    /// Nodes in the column being filled.
+    rows: HashMap<i64, i64>,
+}
+
+impl Converter {
+    /// `name`, or `name` with a number appended when a node has it already.
+    fn unique_name(&mut self, name: &str) -> String {
+        let mut unique = name.to_string();
+        let mut count = 1;
+        while self.names.contains(&unique) {
+            count += 1;
+            unique = format!("{} {}", name, count);
+        }
+        self.names.insert(unique.clone());
+        unique
+    }
+
+    #[allow(deprecated)]
+    fn add(
+        &mut self,
+        action_type: &str,
+        metadata: MetaData,
+        input_data: Option<HashMap<String, Value>>,
+        column: i64,
+    ) -> String {
+        let row = self.rows.entry(column).or_default();
+        let node_id = if action_type == START_NODE_TYPE {
+            "start".to_string()
+        } else {
+            format!("node-{}", self.nodes.len())
+        };
+        self.nodes.push(NodeSchema {
+            node_id: node_id.clone(),
+            action_type: action_type.to_string(),
+            metadata,
+            params: None,
+            input_data,
+            position: Position {
+                x: column * COLUMN_WIDTH,
+                y: *row * ROW_HEIGHT,
+            },
+            icon: None,
+            type_define: None,
+        });
+        *row += 1;
+        node_id
+    }
+
+    /// Adds the nodes replacing `node` and returns their ids.
+    fn add_node(&mut self, node: &Node, column: i64) -> Vec<String> {
+        let metadata = node.metadata().clone();
+        let (action_type, input) = match node {
+            // Only the first Start starts the workflow, the others did nothing.
+            Node::Start { .. } => ("TimeWait", vec![("duration", Value::from(0))]),
+            Node::KeyBoard { params, .. } => {
+                let mut input = vec![
+                    (
+                        "mode",
+                        serde_json::to_value(&params.mode).unwrap_or_default(),
+                    ),
+                    ("key", serde_json::to_value(&params.key).unwrap_or_default()),
+                ];
+                if let Some(value) = &params.value {
+                    input.push(("value", Value::from(value.as_str())));
+                }
+                ("KeyBoard", input)
+            }
+            Node::MouseClick { params, .. } => (
+                "MouseClick",
+                vec![("value", Value::from(params.value.as_str()))],
+            ),
+            Node::MouseMove { params, .. } => (
+                "MouseMove",
+                vec![
+                    ("x", Value::from(params.x.as_str())),
+                    ("y", Value::from(params.y.as_str())),
+                ],
+            ),
+            Node::ImageRecognition { params, .. } => {
+                return self.add_image_matches(metadata, params, column);
+            }
+            // Legacy waits are in milliseconds, TimeWait takes seconds.
+            Node::TimeWait { metadata } => (
+                "TimeWait",
+                vec![(
+                    "duration",
+                    Value::from(metadata.duration.unwrap_or(0) as f64 / 1000.0),
+                )],
+            ),
+        };
+        let input = input
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect();
+        vec![self.add(action_type, metadata, Some(input), column)]
+    }
+
+    /// One `ImageMatch` per image. With several images they run side by
+    /// side and a missing image follows the `no_match` port instead of
+    /// failing, as the legacy node only failed when none was found.
+    fn add_image_matches(
+        &mut self,
+        metadata: MetaData,
+        params: &ImageRecognitionParams,
+        column: i64,
+    ) -> Vec<String> {
+        let imread_type = match params.optimization.imread_type.as_deref() {
+            Some(imread_type) if imread_type.eq_ignore_ascii_case("COLOR") => "Color",
+            _ => "Grayscale",
+        };
+        let single = params.images.len() <= 1;
+        let images = if params.images.is_empty() {
+            vec![String::new()]
+        } else {
+            params.images.clone()
+        };
+
+        let mut ids = vec![];
+        for (index, image) in images.iter().enumerate() {
+            let mut metadata = metadata.clone();
+            let legacy = format!("{}.{}", metadata.name, image);
+            if !single {
+                metadata.name = self.unique_name(&format!("{} {}", metadata.name, index + 1));
+            }
+            self.images.insert(legacy, metadata.name.clone());
+            let input = HashMap::from([
+                // Legacy images are in `image/`, templates in `files/`.
+                (
+                    "template_image".to_string(),
+                    Value::from(format!("../image/{}", image)),
+                ),
+                ("use_screenshot".to_string(), Value::Bool(true)),
+                ("target_score".to_string(), Value::from(LEGACY_TARGET_SCORE)),
+                (
+                    "resize".to_string(),
+                    Value::from(params.optimization.resize()),
+                ),
+                ("imread_type".to_string(), Value::from(imread_type)),
+                (
+                    "no_match".to_string(),
+                    Value::from(if single { "Error" } else { "Port" }),
+                ),
+            ]);
+            ids.push(self.add("ImageMatch", metadata, Some(input), column));
+        }
+        ids
+    }
+
+    fn connect_all(&mut self, from: &[String], to: &[String]) {
+        for from in from.iter() {
+            for to in to.iter() {
+                self.connections.push(Connection {
+                    from: from.clone(),
+                    to: to.clone(),
+                    port: None,
+                    condition: None,
+                });
+            }
+        }
+    }
+
+    fn rewrite_references(&mut self) {
+        if self.images.is_empty() {
+            return;
+        }
+        let mut nodes = std::mem::take(&mut self.nodes);
+        for node in nodes.iter_mut() {
+            for value in node
+                .input_data
+                .iter_mut()
+                .flat_map(|input| input.values_mut())
+            {
+                self.rewrite_value(value);
+            }
+            if let Some(conditions) = node.metadata.conditions.as_mut() {
+                // A match sets `x`, so it only exists once the image was found.
+                for key in [&mut conditions.exist, &mut conditions.not_exist] {
+                    if let Some(key) = key
+                        && let Some(rewritten) = self.rewrite_key(key, "x")
+                    {
+                        *key = rewritten;
+                    }
+                }
+                if let Some(condition) = conditions.condition.as_mut() {
+                    *condition = self.rewrite_text(condition);
+                }
+            }
+        }
+        self.nodes = nodes;
+    }
+
+    fn rewrite_value(&self, value: &mut Value) {
+        match value {
+            Value::String(text) => *text = self.rewrite_text(text),
+            Value::Array(items) => items.iter_mut().for_each(|item| self.rewrite_value(item)),
+            Value::Object(map) => map.values_mut().for_each(|item| self.rewrite_value(item)),
+            _ => {}
+        }
+    }
+
+    fn rewrite_text(&self, text: &str) -> String {
+        REGEX_PARSE_VARIABLES
+            .replace_all(text, |caps: &regex::Captures| {
+                // `${<name>.<image>}` was "true" once the image was found.
+                match self.rewrite_key(&caps[1], "matched") {
+                    Some(variable) => match caps.get(2) {
+                        Some(default) => format!("${{{}:{}}}", variable, default.as_str()),
+                        None => format!("${{{}}}", variable),
+                    },
+                    None => caps[0].to_string(),
+                }
+            })
+            .into_owned()
+    }
+
+    /// `<name>.<image>` becomes `ctx.<node>.<found>`, `<name>.<image>.x`
+    /// and `.y` become the coordinates the node found.
+    fn rewrite_key(&self, key: &str, found: &str) -> Option<String> {
+        if let Some(node) = self.images.get(key) {
+            return Some(format!("ctx.{}.{}", node, found));
+        }
+        let (image, field) = key.rsplit_once('.')?;
+        let node = self.images.get(image)?;
+        Some(format!("ctx.{}.{}", node, field))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node<'a>(workflow: &'a WorkflowSchema, name: &str) -> &'a NodeSchema {
+        workflow
+            .nodes
+            .iter()
+            .find(|node| node.metadata.name == name)
+            .unwrap_or_else(|| panic!("no node named {}", name))
+    }
+
+    fn input(node: &NodeSchema, name: &str) -> Value {
+        node.input_data.as_ref().unwrap()[name].clone()
+    }
+
+    fn targets(workflow: &WorkflowSchema, from: &str) -> Vec<String> {
+        workflow
+            .connections
+            .iter()
+            .filter(|connection| connection.from == from)
+            .map(|connection| connection.to.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_convert_pipeline() {
+        let workflow = WorkflowSchema::from_yaml(
+            r#"
+- stage:
+    - action_type: Start
+      name: "main"
+- stage:
+    - action_type: ImageRecognition
+      name: "find-dot"
+      retry: -1
+      params:
+        images:
+          - "dot.png"
+        sub_pipeline: ""
+        optimization:
+          resize: 0.5
+          imread_type: COLOR
+- stage:
+    - action_type: MouseMove
+      name: "move"
+      params:
+        x: "${find-dot.dot.png.x}"
+        y: "${find-dot.dot.png.y:0}"
+      conditions:
+        exist: "find-dot.dot.png"
+    - action_type: KeyBoard
+      name: "press"
+      params:
+        mode: Down
+        key: W
+- stage:
+    - action_type: TimeWait
+      name: "wait"
+      duration: 200
+"#,
+        )
+        .unwrap();
+
+        let start = node(&workflow, "main");
+        assert_eq!(start.action_type, START_NODE_TYPE);
+        assert_eq!(start.node_id, "start");
+
+        let find = node(&workflow, "find-dot");
+        assert_eq!(find.action_type, "ImageMatch");
+        assert_eq!(find.metadata.retry, Some(-1));
+        assert_eq!(input(find, "template_image"), "../image/dot.png");
+        assert_eq!(input(find, "resize"), 0.5);
+        assert_eq!(input(find, "imread_type"), "Color");
+        assert_eq!(input(find, "no_match"), "Error");
+        assert_eq!(targets(&workflow, "start"), vec![find.node_id.clone()]);
+
+        let moved = node(&workflow, "move");
+        assert_eq!(input(moved, "x"), "${ctx.find-dot.x}");
+        assert_eq!(input(moved, "y"), "${ctx.find-dot.y:0}");
+        let conditions = moved.metadata.conditions.as_ref().unwrap();
+        assert_eq!(conditions.exist.as_deref(), Some("ctx.find-dot.x"));
+
+        let press = node(&workflow, "press");
+        assert_eq!(press.action_type, "KeyBoard");
+        assert_eq!(input(press, "mode"), "Down");
+        assert_eq!(input(press, "key"), "W");
+
+        // `move` may be skipped, so the stage ends on a barrier.
+        let barrier = node(&workflow, "stage 3");
+        assert_eq!(input(barrier, "duration"), 0);
+        assert_eq!(
+            targets(&workflow, &find.node_id),
+            vec![
+                moved.node_id.clone(),
+                press.node_id.clone(),
+                barrier.node_id.clone()
+            ]
+        );
+        assert_eq!(
+            targets(&workflow, &moved.node_id),
+            vec![barrier.node_id.clone()]
+        );
+
+        let wait = node(&workflow, "wait");
+        assert_eq!(input(wait, "duration"), 0.2);
+        assert_eq!(
+            targets(&workflow, &barrier.node_id),
+            vec![wait.node_id.clone()]
+        );
+    }
+
+    #[test]
+    fn test_convert_multiple_images() {
+        let workflow = WorkflowSchema::from_yaml(
+            r#"
+- stage:
+    - action_type: ImageRecognition
+      name: "find"
+      params:
+        images: ["a.png", "b.png"]
+        sub_pipeline: ""
+    - action_type: MouseClick
+      name: "click"
+      params:
+        value: "${find.b.png:false}"
+      conditions:
+        condition: "${find.a.png} == true"
+"#,
+        )
+        .unwrap();
+
+        let start = node(&workflow, "start");
+        assert_eq!(start.action_type, START_NODE_TYPE);
+        let a = node(&workflow, "find 1");
+        let b = node(&workflow, "find 2");
+        assert_eq!(input(a, "template_image"), "../image/a.png");
+        assert_eq!(input(b, "no_match"), "Port");
+        assert_eq!(input(b, "imread_type"), "Grayscale");
+
+        let click = node(&workflow, "click");
+        assert_eq!(input(click, "value"), "${ctx.find 2.matched:false}");
+        let conditions = click.metadata.conditions.as_ref().unwrap();
+        assert_eq!(
+            conditions.condition.as_deref(),
+            Some("${ctx.find 1.matched} == true")
+        );
+    }
+
+    #[test]
+    fn test_from_yaml_reads_workflows() {
+        let workflow = WorkflowSchema::from_yaml(
+            r#"
+nodes:
+  - node_id: start
+    action_type: Start
+    name: start
+    position: { x: 0, y: 0 }
+connections: []
+"#,
+        )
+        .unwrap();
+        assert_eq!(workflow.nodes.len(), 1);
+        assert!(WorkflowSchema::from_yaml("- stage: 1").is_err());
+    }
+}
@@ -1,17 +1,110 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
 
+use crate::node::end::node::{EndNode, NODE_TYPE as END_NODE_TYPE};
+use crate::node::start::node::{NODE_TYPE as START_NODE_TYPE, parse_inputs};
 use crate::schema::node::NodeSchema;
+use crate::schema::pipeline;
+use crate::types::Pipeline;
+use crate::types::conditions::Conditions;
+use crate::types::field::SchemaField;
+use crate::types::input::InputField;
+use crate::types::node::NodeDefine;
 
 #[derive(Deserialize, Serialize, Clone, Debug, Default)]
 pub struct WorkflowSchema {
     pub nodes: Vec<NodeSchema>,
     pub connections: Vec<Connection>,
+    /// Deadline for the whole run, in milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    /// Values shared by every node as `${var.<name>}`, with their defaults.
+    /// See [`crate::workflow::variables`] for how a run overrides them.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub variables: HashMap<String, serde_json::Value>,
+}
+
+impl WorkflowSchema {
+    /// Reads a workflow from a YAML file.
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read workflow {}: {}", path.display(), e))?;
+        Self::from_yaml(&content)
+            .map_err(|e| format!("Failed to parse workflow {}: {}", path.display(), e))
+    }
+
+    /// Parses a workflow from YAML. A legacy pipeline, a list of stages, is
+    /// converted with [`WorkflowSchema::from_pipeline`].
+    pub fn from_yaml(content: &str) -> Result<Self, String> {
+        let value: serde_yaml::Value = serde_yaml::from_str(content).map_err(|e| e.to_string())?;
+        if value.is_sequence() {
+            let pipeline: Pipeline = serde_yaml::from_value(value).map_err(|e| e.to_string())?;
+            return Ok(Self::from_pipeline(&pipeline));
+        }
+        serde_yaml::from_value(value).map_err(|e| e.to_string())
+    }
+
+    /// Converts a legacy pipeline, see [`pipeline::to_workflow`].
+    pub fn from_pipeline(pipeline: &Pipeline) -> Self {
+        pipeline::to_workflow(pipeline)
+    }
+
+    /// The inputs a run of this workflow takes, as its Start nodes declare
+    /// them.
+    pub fn input_schema(&self) -> Result<Vec<InputField>, String> {
+        let mut fields: Vec<InputField> = vec![];
+        for node in self
+            .nodes
+            .iter()
+            .filter(|node| node.action_type == START_NODE_TYPE)
+        {
+            let input = node.input_data.clone().unwrap_or_default();
+            for input in parse_inputs(&input)? {
+                if !fields
+                    .iter()
+                    .any(|known| known.field.name == input.field.name)
+                {
+                    fields.push(input);
+                }
+            }
+        }
+        Ok(fields)
+    }
+
+    /// The values a run of this workflow returns, as its End nodes declare
+    /// them.
+    pub fn output_schema(&self) -> Vec<SchemaField> {
+        let mut fields: Vec<SchemaField> = vec![];
+        for node in self
+            .nodes
+            .iter()
+            .filter(|node| node.action_type == END_NODE_TYPE)
+        {
+            let input = node.input_data.clone().unwrap_or_default();
+            for field in EndNode::new().output_schema(input) {
+                if !fields.iter().any(|known| known.name == field.name) {
+                    fields.push(field);
+                }
+            }
+        }
+        fields
+    }
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug, Default)]
 pub struct Connection {
     pub from: String,
     pub to: String,
+    /// Output port of `from` this edge is attached to. An edge without a
+    /// port is followed whenever `from` succeeds, one on the `error` port
+    /// when it fails.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<String>,
+    /// Checked against the `Context` once `from` has finished; the edge is
+    /// only followed when it passes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<Conditions>,
 }
 
 #[cfg(test)]
@@ -42,4 +135,72 @@ connections:
 
         println!("node schema {:?}", node);
     }
+
+    #[test]
+    pub fn test_deserialize_conditional_connections() {
+        let yaml_str = r#"
+nodes: []
+connections:
+  - from: "branch"
+    to: "yes"
+    port: "true"
+  - from: "find"
+    to: "click"
+    condition:
+      condition: "${ctx.find.score} > 0.9"
+  - from: "click"
+    to: "done"
+            "#;
+        let workflow: WorkflowSchema = serde_yaml::from_str(yaml_str).unwrap();
+
+        assert_eq!(workflow.connections[0].port.as_deref(), Some("true"));
+        assert!(workflow.connections[0].condition.is_none());
+        let condition = workflow.connections[1].condition.as_ref().unwrap();
+        assert_eq!(
+            condition.condition.as_deref(),
+            Some("${ctx.find.score} > 0.9")
+        );
+        assert!(workflow.connections[2].port.is_none());
+
+        let yaml = serde_yaml::to_string(&workflow.connections[2]).unwrap();
+        assert!(!yaml.contains("port"), "unset port should not be written");
+    }
+
+    #[test]
+    pub fn test_input_and_output_schema() {
+        let yaml_str = r#"
+nodes:
+  - node_id: start
+    action_type: Start
+    name: start
+    input_data:
+      inputs:
+        - name: account
+          type: string
+          required: true
+  - node_id: done
+    action_type: End
+    name: done
+    input_data:
+      outputs:
+        token: "${ctx.login.token}"
+        page: "${ctx.nav.page}"
+connections:
+  - from: start
+    to: done
+            "#;
+        let workflow: WorkflowSchema = serde_yaml::from_str(yaml_str).unwrap();
+
+        let inputs = workflow.input_schema().unwrap();
+        assert_eq!(inputs.len(), 1);
+        assert_eq!(inputs[0].field.name, "account");
+        assert!(inputs[0].required);
+
+        let names: Vec<String> = workflow
+            .output_schema()
+            .into_iter()
+            .map(|field| field.name)
+            .collect();
+        assert_eq!(names, vec!["page".to_string(), "token".to_string()]);
+    }
 }
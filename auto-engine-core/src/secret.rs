@@ -0,0 +1,230 @@
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Nodes reference a secret as `${secret.<name>}`.
+pub const SECRET_PREFIX: &str = "secret.";
+
+/// `AUTO_ENGINE_SECRET_API_TOKEN` is loaded as the secret `api_token`.
+pub const ENV_PREFIX: &str = "AUTO_ENGINE_SECRET_";
+
+/// What a secret value is replaced with wherever it would be shown.
+pub const REDACTED: &str = "******";
+
+const KEY_ROUNDS: u32 = 100_000;
+
+/// Secret values by name. They are never stored in the `Context`, a
+/// `${secret.<name>}` reference is replaced with its value only when a node
+/// resolves its inputs.
+#[derive(Default, Clone)]
+pub struct SecretStore {
+    values: HashMap<String, String>,
+}
+
+impl std::fmt::Debug for SecretStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SecretStore")
+            .field("names", &self.names())
+            .finish()
+    }
+}
+
+/// An encrypted secrets file.
+#[derive(Serialize, Deserialize)]
+struct SecretFile {
+    salt: String,
+    nonce: String,
+    data: String,
+}
+
+impl SecretStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every environment variable starting with [`ENV_PREFIX`], by the rest
+    /// of its name in lower case.
+    pub fn from_env() -> Self {
+        let mut store = Self::new();
+        for (name, value) in std::env::vars() {
+            if let Some(name) = name.strip_prefix(ENV_PREFIX) {
+                store.insert(&name.to_lowercase(), value);
+            }
+        }
+        store
+    }
+
+    /// Reads a file written by [`Self::save`] with the same password.
+    pub fn load(path: &Path, password: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read secrets {}: {}", path.display(), e))?;
+        let file: SecretFile = serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid secrets file {}: {}", path.display(), e))?;
+        let decode = |text: &str| {
+            BASE64
+                .decode(text)
+                .map_err(|e| format!("Invalid secrets file {}: {}", path.display(), e))
+        };
+        let salt = decode(&file.salt)?;
+        let nonce = decode(&file.nonce)?;
+        if nonce.len() != 12 {
+            return Err(format!("Invalid secrets file {}", path.display()));
+        }
+        let plain = cipher(password, &salt)
+            .decrypt(Nonce::from_slice(&nonce), decode(&file.data)?.as_ref())
+            .map_err(|_| format!("Wrong password for secrets {}", path.display()))?;
+        let values = serde_json::from_slice(&plain)
+            .map_err(|e| format!("Invalid secrets file {}: {}", path.display(), e))?;
+        Ok(Self { values })
+    }
+
+    /// Writes every secret to `path`, encrypted with a key derived from
+    /// `password`.
+    pub fn save(&self, path: &Path, password: &str) -> Result<(), String> {
+        let salt: [u8; 16] = rand::random();
+        let nonce: [u8; 12] = rand::random();
+        let plain = serde_json::to_vec(&self.values).map_err(|e| e.to_string())?;
+        let data = cipher(password, &salt)
+            .encrypt(Nonce::from_slice(&nonce), plain.as_ref())
+            .map_err(|e| format!("Failed to encrypt secrets: {}", e))?;
+        let file = SecretFile {
+            salt: BASE64.encode(salt),
+            nonce: BASE64.encode(nonce),
+            data: BASE64.encode(data),
+        };
+        let content = serde_json::to_string_pretty(&file).map_err(|e| e.to_string())?;
+        std::fs::write(path, content)
+            .map_err(|e| format!("Failed to write secrets {}: {}", path.display(), e))
+    }
+
+    pub fn insert(&mut self, name: &str, value: String) {
+        self.values.insert(name.to_string(), value);
+    }
+
+    /// Adds every secret of `other`, replacing those with the same name.
+    pub fn extend(&mut self, other: SecretStore) {
+        self.values.extend(other.values);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.values.keys().map(String::as_str).collect();
+        names.sort();
+        names
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// `text` with every secret value in it replaced by [`REDACTED`].
+    pub fn redact(&self, text: &str) -> String {
+        let mut values: Vec<&String> = self
+            .values
+            .values()
+            .filter(|value| !value.is_empty())
+            .collect();
+        // A secret containing another one is replaced first.
+        values.sort_by_key(|value| std::cmp::Reverse(value.len()));
+        let mut text = text.to_string();
+        for value in values {
+            if text.contains(value.as_str()) {
+                text = text.replace(value.as_str(), REDACTED);
+            }
+        }
+        text
+    }
+
+    /// `value` with every secret redacted from its strings, keys included.
+    pub fn redact_value(&self, value: Value) -> Value {
+        if self.is_empty() {
+            return value;
+        }
+        match value {
+            Value::String(text) => Value::String(self.redact(&text)),
+            Value::Array(items) => Value::Array(
+                items
+                    .into_iter()
+                    .map(|item| self.redact_value(item))
+                    .collect(),
+            ),
+            Value::Object(fields) => Value::Object(
+                fields
+                    .into_iter()
+                    .map(|(key, value)| (self.redact(&key), self.redact_value(value)))
+                    .collect(),
+            ),
+            value => value,
+        }
+    }
+
+    /// `value` with every secret redacted, see [`Self::redact_value`].
+    pub fn redact_serialized<T: Serialize + serde::de::DeserializeOwned>(
+        &self,
+        value: T,
+    ) -> Result<T, String> {
+        if self.is_empty() {
+            return Ok(value);
+        }
+        let json = serde_json::to_value(value).map_err(|e| e.to_string())?;
+        serde_json::from_value(self.redact_value(json)).map_err(|e| e.to_string())
+    }
+}
+
+fn cipher(password: &str, salt: &[u8]) -> Aes256Gcm {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<sha2::Sha256>(password.as_bytes(), salt, KEY_ROUNDS, &mut key);
+    Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> SecretStore {
+        let mut store = SecretStore::new();
+        store.insert("token", "abc123".to_string());
+        store.insert("long_token", "abc123xyz".to_string());
+        store
+    }
+
+    #[test]
+    fn test_redact() {
+        let store = store();
+        assert_eq!(
+            store.redact("Bearer abc123xyz"),
+            format!("Bearer {}", REDACTED)
+        );
+        assert_eq!(
+            store.redact("token=abc123&a=1"),
+            format!("token={}&a=1", REDACTED)
+        );
+        assert_eq!(
+            store.redact_value(serde_json::json!({"auth": ["abc123"], "n": 1})),
+            serde_json::json!({"auth": [REDACTED], "n": 1})
+        );
+        assert_eq!(SecretStore::new().redact("abc123"), "abc123");
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join("auto-engine-secrets-test.json");
+        store().save(&path, "password").unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("abc123"));
+        let loaded = SecretStore::load(&path, "password").unwrap();
+        assert_eq!(loaded.get("token"), Some("abc123"));
+        assert_eq!(loaded.names(), vec!["long_token", "token"]);
+        assert!(SecretStore::load(&path, "wrong").is_err());
+        std::fs::remove_file(&path).unwrap_or_default();
+    }
+}
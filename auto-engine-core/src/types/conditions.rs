@@ -23,28 +23,24 @@ impl Conditions {
     pub async fn check(&self, ctx: &Context) -> Result<ConditionResult, String> {
         if let Some(key) = &self.exist
             && key != ""
+            && ctx.get_value(key).await.is_none()
         {
-            let values = ctx.string_value.read().await;
-            if !values.contains_key(key) {
-                log::info!("{} does not exist, {:?}", key, values);
-                return Ok(ConditionResult {
-                    pass: false,
-                    reason: Some(format!("{} does not exist", key)),
-                });
-            }
+            log::info!("{} does not exist", key);
+            return Ok(ConditionResult {
+                pass: false,
+                reason: Some(format!("{} does not exist", key)),
+            });
         }
 
         if let Some(key) = &self.not_exist
             && key != ""
+            && ctx.get_value(key).await.is_some()
         {
-            let values = ctx.string_value.read().await;
-            if values.contains_key(key) {
-                log::info!("{} does not exist, {:?}", key, values);
-                return Ok(ConditionResult {
-                    pass: false,
-                    reason: Some(format!("{} does not exist", key)),
-                });
-            }
+            log::info!("{} exists", key);
+            return Ok(ConditionResult {
+                pass: false,
+                reason: Some(format!("{} exists", key)),
+            });
         }
 
         if let Some(condition) = &self.condition
@@ -78,7 +74,7 @@ impl Conditions {
             }
         }
         Ok(ConditionResult {
-            pass: false,
+            pass: true,
             reason: None,
         })
     }
@@ -148,4 +144,23 @@ mod tests {
             );
         }
     }
+
+    #[tokio::test]
+    async fn not_exist_reports_the_existing_key() {
+        #[cfg(feature = "tauri")]
+        let context = Context::new(PathBuf::new(), None);
+
+        #[cfg(not(feature = "tauri"))]
+        let context = Context::new(PathBuf::new());
+
+        context.set_string_value("image.x", "2").await.unwrap();
+        let conditions = Conditions {
+            exist: None,
+            condition: None,
+            not_exist: s("image.x"),
+        };
+        let result = conditions.check(&context).await.unwrap();
+        assert!(!result.pass);
+        assert_eq!(result.reason.as_deref(), Some("image.x exists"));
+    }
 }
@@ -110,6 +110,8 @@ pub enum FieldType {
     Object,
     Image,
     File,
+    /// Any JSON value, used when the type is only known at run time.
+    Any,
 }
 #[derive(Clone, Default, Serialize, Debug, Deserialize)]
 pub struct SchemaField {
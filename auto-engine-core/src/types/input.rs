@@ -0,0 +1,189 @@
+use crate::types::field::{FieldType, SchemaField};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// A value a workflow is launched with, declared on its Start node.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct InputField {
+    #[serde(flatten)]
+    pub field: SchemaField,
+    /// A run without this input is refused, unless the field has a default.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub required: bool,
+}
+
+/// Checks `supplied` against `fields` before a run starts. Declared inputs
+/// get their default when missing and are converted to their type, values
+/// nobody declared are passed on unchanged. Every problem is reported at
+/// once.
+pub fn validate_inputs(
+    fields: &[InputField],
+    supplied: &HashMap<String, Value>,
+) -> Result<HashMap<String, Value>, String> {
+    let mut inputs = supplied.clone();
+    let mut errors = vec![];
+    for input in fields.iter() {
+        let name = &input.field.name;
+        let value = match supplied.get(name) {
+            Some(value) if !value.is_null() => value.clone(),
+            _ => match &input.field.default {
+                Some(default) => Value::String(default.clone()),
+                None if input.required => {
+                    errors.push(format!("input `{}` is required", name));
+                    continue;
+                }
+                None => continue,
+            },
+        };
+
+        match coerce(&input.field, value) {
+            Ok(value) => {
+                inputs.insert(name.clone(), value);
+            }
+            Err(e) => errors.push(format!("input `{}` {}", name, e)),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(inputs)
+    } else {
+        Err(format!("Invalid workflow inputs: {}", errors.join("; ")))
+    }
+}
+
+fn coerce(field: &SchemaField, value: Value) -> Result<Value, String> {
+    let value = match (&field.field_type, value) {
+        (FieldType::String | FieldType::Image | FieldType::File, value) => match value {
+            Value::String(_) => value,
+            Value::Number(_) | Value::Bool(_) => Value::String(value.to_string()),
+            other => {
+                return Err(format!(
+                    "must be {}, got: {}",
+                    type_name(&field.field_type),
+                    other
+                ));
+            }
+        },
+        (FieldType::Any, value) => value,
+        (FieldType::Number, Value::Number(number)) => Value::Number(number),
+        (FieldType::Number, Value::String(s)) => {
+            let s = s.trim();
+            if let Ok(number) = s.parse::<i64>() {
+                Value::Number(number.into())
+            } else {
+                s.parse::<f64>()
+                    .ok()
+                    .and_then(serde_json::Number::from_f64)
+                    .map(Value::Number)
+                    .ok_or_else(|| format!("must be a number, got: {}", s))?
+            }
+        }
+        (FieldType::Boolean, Value::Bool(value)) => Value::Bool(value),
+        (FieldType::Boolean, Value::String(s)) => match s.trim().to_lowercase().as_str() {
+            "true" | "1" => Value::Bool(true),
+            "false" | "0" => Value::Bool(false),
+            _ => return Err(format!("must be a boolean, got: {}", s)),
+        },
+        (FieldType::Array, value @ Value::Array(_)) => value,
+        (FieldType::Object, value @ Value::Object(_)) => value,
+        (field_type @ (FieldType::Array | FieldType::Object), Value::String(s)) => {
+            match (field_type, serde_json::from_str::<Value>(&s)) {
+                (FieldType::Array, Ok(value @ Value::Array(_)))
+                | (FieldType::Object, Ok(value @ Value::Object(_))) => value,
+                _ => return Err(format!("must be {}, got: {}", type_name(field_type), s)),
+            }
+        }
+        (field_type, other) => {
+            return Err(format!("must be {}, got: {}", type_name(field_type), other));
+        }
+    };
+
+    if !field.enums.is_empty() {
+        let text = match &value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        if !field.enums.contains(&text) {
+            return Err(format!(
+                "must be one of {}, got: {}",
+                field.enums.join(", "),
+                text
+            ));
+        }
+    }
+    Ok(value)
+}
+
+fn type_name(field_type: &FieldType) -> &'static str {
+    match field_type {
+        FieldType::String => "a string",
+        FieldType::Number => "a number",
+        FieldType::Boolean => "a boolean",
+        FieldType::Array => "an array",
+        FieldType::Object => "an object",
+        FieldType::Image => "an image",
+        FieldType::File => "a file",
+        FieldType::Any => "any value",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields() -> Vec<InputField> {
+        serde_yaml::from_str(
+            r#"
+- name: account
+  type: string
+  required: true
+- name: count
+  type: number
+  default: "3"
+- name: headless
+  type: boolean
+- name: mode
+  type: string
+  enums: [fast, safe]
+  default: safe
+- name: tags
+  type: array
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_validate_inputs() {
+        let supplied = HashMap::from([
+            ("account".to_string(), Value::from("alice")),
+            ("headless".to_string(), Value::from("true")),
+            ("tags".to_string(), Value::from(r#"["a", "b"]"#)),
+            ("extra".to_string(), Value::from(1)),
+        ]);
+        let inputs = validate_inputs(&fields(), &supplied).unwrap();
+
+        assert_eq!(inputs["account"], Value::from("alice"));
+        assert_eq!(inputs["count"], Value::from(3));
+        assert_eq!(inputs["headless"], Value::from(true));
+        assert_eq!(inputs["mode"], Value::from("safe"));
+        assert_eq!(inputs["tags"], serde_json::json!(["a", "b"]));
+        assert_eq!(inputs["extra"], Value::from(1));
+    }
+
+    #[test]
+    fn test_validate_inputs_errors() {
+        let supplied = HashMap::from([
+            ("count".to_string(), Value::from("many")),
+            ("mode".to_string(), Value::from("slow")),
+            ("tags".to_string(), Value::from(r#"{"a": 1}"#)),
+        ]);
+        let err = validate_inputs(&fields(), &supplied).unwrap_err();
+
+        assert!(err.contains("`account` is required"), "{err}");
+        assert!(err.contains("`count` must be a number"), "{err}");
+        assert!(err.contains("`mode` must be one of fast, safe"), "{err}");
+        assert!(err.contains("`tags` must be an array"), "{err}");
+    }
+}
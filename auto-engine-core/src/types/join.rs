@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+/// When a node with several incoming connections runs.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JoinMode {
+    /// Once every incoming branch finished or was skipped.
+    #[default]
+    All,
+    /// As soon as the first incoming branch arrives.
+    Any,
+    /// As soon as this many incoming branches arrived.
+    AtLeast(usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::MetaData;
+
+    #[test]
+    fn test_deserialize() {
+        let metadata: MetaData = serde_yaml::from_str("name: a\njoin: any").unwrap();
+        assert_eq!(metadata.join, Some(JoinMode::Any));
+
+        let metadata: MetaData = serde_yaml::from_str("name: a\njoin: !at_least 2").unwrap();
+        assert_eq!(metadata.join, Some(JoinMode::AtLeast(2)));
+
+        let metadata: MetaData = serde_yaml::from_str("name: a").unwrap();
+        assert_eq!(metadata.join, None);
+    }
+}
@@ -8,4 +8,7 @@ pub use typs::*;
 pub use keyboard::*;
 
 pub mod field;
+pub mod input;
+pub mod join;
 pub mod node;
+pub mod retry;
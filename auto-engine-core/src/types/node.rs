@@ -5,6 +5,18 @@ use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// Output key a runner sets to pick which outgoing port of its node is
+/// followed, see [`NodeDefine::output_ports`].
+pub const OUTPUT_PORT: &str = "port";
+
+/// Port every node has implicitly, its connections are followed when the
+/// node fails instead of aborting the run.
+pub const ERROR_PORT: &str = "error";
+
+/// Output key the error message of a failed node is kept under, as
+/// `ctx.<name>.error`, when its failure does not abort the run.
+pub const ERROR_OUTPUT: &str = "error";
+
 #[derive(Clone, Default, Serialize, Deserialize, Debug)]
 pub struct I18nValue {
     pub zh: String,
@@ -22,6 +34,8 @@ pub struct NodeType {
     pub output_schema: Vec<SchemaField>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub input_schema: Vec<SchemaField>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub output_ports: Vec<String>,
 }
 
 pub trait NodeDefine: Send + Sync {
@@ -38,6 +52,18 @@ pub trait NodeDefine: Send + Sync {
     fn output_schema(&self, input: HashMap<String, serde_json::Value>) -> Vec<SchemaField>;
 
     fn input_schema(&self) -> Vec<SchemaField>;
+
+    /// Named outcomes connections can attach to. The runner reports the
+    /// chosen one through the [`OUTPUT_PORT`] output.
+    fn output_ports(&self) -> Vec<String> {
+        vec![]
+    }
+
+    /// Whether running the node reaches outside the workflow, e.g. the
+    /// mouse, the screen or the network. Dry runs simulate such nodes.
+    fn has_side_effects(&self) -> bool {
+        true
+    }
 }
 
 #[async_trait::async_trait]
@@ -48,9 +74,93 @@ pub trait NodeRunnerControl: Send + Sync {
         node_name: &str,
         params: HashMap<String, serde_json::Value>,
         schema_field: Vec<SchemaField>,
+    ) -> Result<Option<HashMap<String, serde_json::Value>>, String> {
+        let params = resolve_params(ctx, params, &schema_field).await?;
+        self.run_resolved(ctx, node_name, params).await
+    }
+
+    /// Runs the node with `params` as [`resolve_params`] returned them.
+    async fn run_resolved(
+        &mut self,
+        ctx: &Context,
+        node_name: &str,
+        params: HashMap<String, serde_json::Value>,
     ) -> Result<Option<HashMap<String, serde_json::Value>>, String>;
 }
 
+/// Resolves the `${}` references in `params` and converts every value to
+/// the type its field in `schema` declares, as runners receive them.
+pub async fn resolve_params(
+    ctx: &Context,
+    mut params: HashMap<String, serde_json::Value>,
+    schema: &[SchemaField],
+) -> Result<HashMap<String, serde_json::Value>, String> {
+    log::info!("params: {:?}, size: {}", params, params.len());
+    for field in schema.iter() {
+        log::info!("field: {:?}", field);
+        let default = field.default.clone().unwrap_or_default();
+        let mut val = params
+            .get(&field.name)
+            .unwrap_or(&serde_json::Value::String(default))
+            .clone();
+
+        if let serde_json::Value::String(s) = &val {
+            let res = utils::parse_variables(ctx, s).await;
+            val = match field.field_type {
+                FieldType::String | FieldType::Image | FieldType::File => {
+                    serde_json::Value::String(res.clone())
+                }
+                FieldType::Number => match res.trim() {
+                    "" => serde_json::Value::Number(0.into()),
+
+                    s if s.parse::<i64>().is_ok() => {
+                        serde_json::Value::Number(s.parse::<i64>().unwrap().into())
+                    }
+
+                    s if s.parse::<f64>().is_ok() => {
+                        serde_json::Number::from_f64(s.parse::<f64>().unwrap())
+                            .map(serde_json::Value::Number)
+                            .unwrap_or(serde_json::Value::Null)
+                    }
+
+                    s => {
+                        return Err(format!(
+                            "Field '{}' cannot be parsed as a number: {}",
+                            field.name, s
+                        ));
+                    }
+                },
+                FieldType::Boolean => match res.to_lowercase().as_str() {
+                    "true" | "1" => serde_json::Value::Bool(true),
+                    "false" | "0" => serde_json::Value::Bool(false),
+                    _ => {
+                        return Err(format!(
+                            "Field '{}' cannot be parsed as a boolean: {}",
+                            field.name, res
+                        ));
+                    }
+                },
+                FieldType::Array => match serde_json::from_str::<serde_json::Value>(&res) {
+                    Ok(array @ serde_json::Value::Array(_)) => array,
+                    _ => {
+                        return Err(format!(
+                            "Field '{}' cannot be parsed as an array: {}",
+                            field.name, res
+                        ));
+                    }
+                },
+                FieldType::Object => Default::default(),
+                FieldType::Any => serde_json::from_str::<serde_json::Value>(&res)
+                    .unwrap_or(serde_json::Value::String(res)),
+            };
+        }
+
+        params.insert(field.name.clone(), val);
+    }
+
+    Ok(params)
+}
+
 pub struct NodeRunnerController<T: NodeRunner> {
     runner: T,
 }
@@ -66,72 +176,12 @@ impl<T> NodeRunnerControl for NodeRunnerController<T>
 where
     T: NodeRunner,
 {
-    async fn run(
+    async fn run_resolved(
         &mut self,
         ctx: &Context,
         node_name: &str,
         params: HashMap<String, serde_json::Value>,
-        schema_field: Vec<SchemaField>,
     ) -> Result<Option<HashMap<String, serde_json::Value>>, String> {
-        let mut params = params;
-        log::info!("params: {:?}, size: {}", params, params.len());
-        for field in schema_field.iter() {
-            log::info!("field: {:?}", field);
-            let default = field.default.clone().unwrap_or_default();
-            let mut val = params
-                .get(&field.name)
-                .unwrap_or(&serde_json::Value::String(default))
-                .clone();
-
-            if let serde_json::Value::String(s) = &val {
-                let res = utils::parse_variables(ctx, s).await;
-                val = match field.field_type {
-                    FieldType::String | FieldType::Image | FieldType::File => {
-                        serde_json::Value::String(res.clone())
-                    }
-                    FieldType::Number => match res.trim() {
-                        "" => serde_json::Value::Number(0.into()),
-
-                        s if s.parse::<i64>().is_ok() => {
-                            serde_json::Value::Number(s.parse::<i64>().unwrap().into())
-                        }
-
-                        s if s.parse::<f64>().is_ok() => {
-                            serde_json::Number::from_f64(s.parse::<f64>().unwrap())
-                                .map(serde_json::Value::Number)
-                                .unwrap_or(serde_json::Value::Null)
-                        }
-
-                        s => {
-                            return Err(format!(
-                                "Field '{}' cannot be parsed as a number: {}",
-                                field.name, s
-                            ));
-                        }
-                    },
-                    FieldType::Boolean => match res.to_lowercase().as_str() {
-                        "true" | "1" => serde_json::Value::Bool(true),
-                        "false" | "0" => serde_json::Value::Bool(false),
-                        _ => {
-                            return Err(format!(
-                                "Field '{}' cannot be parsed as a boolean: {}",
-                                field.name, res
-                            ));
-                        }
-                    },
-                    FieldType::Array => {
-                        return Err(format!(
-                            "Field '{}' cannot be parsed as an array: {}",
-                            field.name, res
-                        ));
-                    }
-                    FieldType::Object => Default::default(),
-                };
-            }
-
-            params.insert(field.name.clone(), val);
-        }
-
         let params: T::ParamType = serde_json::from_value(serde_json::Value::Object(
             serde_json::map::Map::from_iter(params),
         ))
@@ -0,0 +1,252 @@
+use crate::types::MetaData;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Infinite retries never run faster than this, whatever the policy says.
+pub const MIN_INFINITE_RETRY_DELAY_MS: u64 = 200;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Backoff {
+    /// Waits `delay_ms` before every retry.
+    #[default]
+    Fixed,
+    /// Multiplies the delay by `factor` after every retry.
+    Exponential,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt, `-1` retries until the node succeeds.
+    #[serde(default)]
+    pub max_retries: i32,
+    #[serde(default)]
+    pub backoff: Backoff,
+    /// Delay before the first retry, in milliseconds.
+    #[serde(default)]
+    pub delay_ms: u64,
+    /// Growth of the delay for exponential backoff.
+    #[serde(default = "default_factor")]
+    pub factor: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_delay_ms: Option<u64>,
+    /// Share of every delay, from 0 to 1, that is randomly cut off so
+    /// parallel nodes do not retry in lockstep.
+    #[serde(default)]
+    pub jitter: f64,
+    /// No retry starts once this many milliseconds passed since the first
+    /// attempt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_elapsed_ms: Option<u64>,
+    /// Only errors matching one of these regular expressions are retried,
+    /// every error is when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub retry_on: Vec<String>,
+    /// Errors matching one of these regular expressions are never retried.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub abort_on: Vec<String>,
+}
+
+fn default_factor() -> f64 {
+    2.0
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 0,
+            backoff: Backoff::Fixed,
+            delay_ms: 0,
+            factor: default_factor(),
+            max_delay_ms: None,
+            jitter: 0.0,
+            max_elapsed_ms: None,
+            retry_on: vec![],
+            abort_on: vec![],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The policy a node runs with. Nodes without `retry_policy` keep the
+    /// behaviour of the `retry`, `interval` and `duration` fields.
+    pub fn from_metadata(metadata: &MetaData) -> Self {
+        if let Some(policy) = &metadata.retry_policy {
+            return policy.clone();
+        }
+
+        let delay_ms = metadata
+            .interval
+            .or(metadata.duration.map(u64::from))
+            .unwrap_or(0);
+        Self {
+            max_retries: metadata.retry.unwrap_or(0),
+            delay_ms,
+            ..Default::default()
+        }
+    }
+
+    /// Whether another attempt should follow attempt number `attempt`
+    /// (starting at 1) that failed with `error`.
+    pub fn should_retry(&self, attempt: u32, elapsed: Duration, error: &str) -> bool {
+        if self.max_retries >= 0 && attempt > self.max_retries as u32 {
+            return false;
+        }
+        if let Some(max_elapsed) = self.max_elapsed_ms
+            && elapsed >= Duration::from_millis(max_elapsed)
+        {
+            return false;
+        }
+        if matches_any(&self.abort_on, error) {
+            return false;
+        }
+        self.retry_on.is_empty() || matches_any(&self.retry_on, error)
+    }
+
+    /// How long to wait after attempt number `attempt` failed.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let mut delay = self.delay_ms as f64;
+        if self.backoff == Backoff::Exponential {
+            delay *= self.factor.max(1.0).powi(attempt.saturating_sub(1) as i32);
+        }
+        if let Some(max_delay) = self.max_delay_ms {
+            delay = delay.min(max_delay as f64);
+        }
+
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter > 0.0 {
+            delay -= delay * jitter * rand::random::<f64>();
+        }
+
+        if self.max_retries < 0 {
+            delay = delay.max(MIN_INFINITE_RETRY_DELAY_MS as f64);
+        }
+        Duration::from_millis(delay as u64)
+    }
+}
+
+fn matches_any(patterns: &[String], error: &str) -> bool {
+    patterns.iter().any(|pattern| match Regex::new(pattern) {
+        Ok(regex) => regex.is_match(error),
+        Err(e) => {
+            log::warn!("invalid retry pattern {}: {}", pattern, e);
+            error.contains(pattern.as_str())
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay() {
+        let fixed = RetryPolicy {
+            max_retries: 3,
+            delay_ms: 100,
+            ..Default::default()
+        };
+        assert_eq!(fixed.delay(1), Duration::from_millis(100));
+        assert_eq!(fixed.delay(3), Duration::from_millis(100));
+
+        let exponential = RetryPolicy {
+            max_retries: 5,
+            backoff: Backoff::Exponential,
+            delay_ms: 100,
+            max_delay_ms: Some(500),
+            ..Default::default()
+        };
+        assert_eq!(exponential.delay(1), Duration::from_millis(100));
+        assert_eq!(exponential.delay(2), Duration::from_millis(200));
+        assert_eq!(exponential.delay(3), Duration::from_millis(400));
+        assert_eq!(exponential.delay(4), Duration::from_millis(500));
+
+        let jittered = RetryPolicy {
+            delay_ms: 100,
+            jitter: 0.5,
+            ..Default::default()
+        };
+        for _ in 0..20 {
+            let delay = jittered.delay(1);
+            assert!(delay >= Duration::from_millis(50) && delay <= Duration::from_millis(100));
+        }
+
+        let infinite = RetryPolicy {
+            max_retries: -1,
+            ..Default::default()
+        };
+        assert_eq!(
+            infinite.delay(1),
+            Duration::from_millis(MIN_INFINITE_RETRY_DELAY_MS)
+        );
+    }
+
+    #[test]
+    fn test_should_retry() {
+        let policy = RetryPolicy {
+            max_retries: 2,
+            max_elapsed_ms: Some(1000),
+            retry_on: vec!["timed out".to_string(), "^not found".to_string()],
+            abort_on: vec!["permission".to_string()],
+            ..Default::default()
+        };
+        let now = Duration::ZERO;
+        assert!(policy.should_retry(1, now, "Node a timed out after 10 ms"));
+        assert!(policy.should_retry(2, now, "not found: login.png"));
+        assert!(!policy.should_retry(3, now, "not found: login.png"));
+        assert!(!policy.should_retry(1, now, "image not found"));
+        assert!(!policy.should_retry(1, now, "timed out, permission denied"));
+        assert!(!policy.should_retry(1, Duration::from_secs(2), "timed out"));
+
+        let infinite = RetryPolicy {
+            max_retries: -1,
+            ..Default::default()
+        };
+        assert!(infinite.should_retry(1000, now, "any error"));
+    }
+
+    #[test]
+    fn test_from_metadata() {
+        let metadata = MetaData {
+            retry: Some(2),
+            interval: Some(300),
+            duration: Some(50),
+            ..Default::default()
+        };
+        let policy = RetryPolicy::from_metadata(&metadata);
+        assert_eq!(policy.max_retries, 2);
+        assert_eq!(policy.delay_ms, 300);
+
+        let metadata = MetaData {
+            retry: Some(2),
+            retry_policy: Some(RetryPolicy {
+                max_retries: 5,
+                backoff: Backoff::Exponential,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let policy = RetryPolicy::from_metadata(&metadata);
+        assert_eq!(policy.max_retries, 5);
+        assert_eq!(policy.backoff, Backoff::Exponential);
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let policy: RetryPolicy = serde_yaml::from_str(
+            r#"
+max_retries: 3
+backoff: exponential
+delay_ms: 100
+jitter: 0.2
+retry_on:
+  - timed out
+"#,
+        )
+        .unwrap();
+        assert_eq!(policy.backoff, Backoff::Exponential);
+        assert_eq!(policy.factor, 2.0);
+        assert_eq!(policy.retry_on, vec!["timed out".to_string()]);
+    }
+}
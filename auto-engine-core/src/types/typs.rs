@@ -1,6 +1,8 @@
 use crate::context::Context;
 use crate::types::KeyBoardParams;
 use crate::types::conditions::Conditions;
+use crate::types::join::JoinMode;
+use crate::types::retry::RetryPolicy;
 use auto_engine_macro::with_metadata;
 use opencv::imgcodecs;
 use serde::{Deserialize, Serialize};
@@ -76,6 +78,21 @@ pub struct MetaData {
     pub conditions: Option<Conditions>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub err_return: Option<bool>,
+    /// Upper bound for a single attempt of the node, in milliseconds. A
+    /// timed out attempt fails like any other error.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    /// Replaces `retry`, `interval` and `duration` when set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_policy: Option<RetryPolicy>,
+    /// How a node with several incoming connections waits for them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub join: Option<JoinMode>,
+    /// A disabled node is passed over, the nodes after it run as if it had
+    /// succeeded without output. Connections on a port are not followed,
+    /// except the `done` port of a loop.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub disabled: bool,
 }
 
 #[with_metadata]
@@ -97,6 +114,10 @@ impl Node {
         &metadata.name
     }
 
+    pub fn disabled(&self) -> bool {
+        self.metadata().disabled
+    }
+
     pub fn conditions(&self) -> Option<Conditions> {
         let metadata = self.metadata();
         metadata.conditions.clone()
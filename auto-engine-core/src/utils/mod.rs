@@ -1,4 +1,5 @@
-use crate::context::Context;
+use crate::context::{Context, ScopeValues};
+use crate::secret::SECRET_PREFIX;
 use once_cell::sync::Lazy;
 use regex::Regex;
 
@@ -8,36 +9,27 @@ pub static REGEX_PARSE_VARIABLES: Lazy<Regex> =
 // String: the value name or key
 // bool: if need get value from Context
 pub async fn parse_variables(context: &Context, input: &str) -> String {
-    let ctx = context.string_value.read().await;
+    let ctx = context.read_values().await;
 
     REGEX_PARSE_VARIABLES
         .replace_all(input, |caps: &regex::Captures| {
             let var_name = &caps[1];
             let default = caps.get(2).map(|m| m.as_str()).unwrap_or("");
 
-            if let Some(value) = ctx.get(var_name) {
-                return serde_json::to_string(&value)
-                    .unwrap_or_default()
-                    .trim_matches('"')
-                    .to_string();
-            }
-            default.to_string()
+            variable_text(context, &ctx, var_name).unwrap_or_else(|| default.to_string())
         })
         .into_owned()
 }
 
 pub async fn try_parse_variables(context: &Context, input: &str) -> Result<String, String> {
-    let ctx = context.string_value.read().await;
+    let ctx = context.read_values().await;
     let mut err: Option<String> = None;
 
     let result = REGEX_PARSE_VARIABLES.replace_all(input, |caps: &regex::Captures| {
         let var_name = &caps[1];
 
-        let variable = match ctx.get(var_name) {
-            Some(value) => serde_json::to_string(value)
-                .unwrap_or_default()
-                .trim_matches('"')
-                .to_string(),
+        let variable = match variable_text(context, &ctx, var_name) {
+            Some(variable) => variable,
             None => {
                 err = Some(format!("variable `{}` not found", var_name));
                 String::new()
@@ -56,6 +48,20 @@ pub async fn try_parse_variables(context: &Context, input: &str) -> Result<Strin
     }
 }
 
+/// The text `${name}` is replaced with. `secret.` names are looked up in the
+/// secrets of `context`, everything else in `values`.
+fn variable_text(context: &Context, values: &ScopeValues<'_>, name: &str) -> Option<String> {
+    if let Some(secret) = name.strip_prefix(SECRET_PREFIX) {
+        return context.secrets().get(secret).map(str::to_string);
+    }
+    values.get(name).map(|value| {
+        serde_json::to_string(value)
+            .unwrap_or_default()
+            .trim_matches('"')
+            .to_string()
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -89,6 +95,18 @@ mod tests {
                 content: "${image-rec.x:0} > 2".to_string(),
                 expected: "0 > 2".to_string(),
             },
+            TestCase {
+                content: "${http.body.users.1.name}".to_string(),
+                expected: "bob".to_string(),
+            },
+            TestCase {
+                content: "Bearer ${secret.token}".to_string(),
+                expected: "Bearer a\"b".to_string(),
+            },
+            TestCase {
+                content: "${secret.missing:none}".to_string(),
+                expected: "none".to_string(),
+            },
         ];
 
         #[cfg(feature = "tauri")]
@@ -97,11 +115,21 @@ mod tests {
         #[cfg(not(feature = "tauri"))]
         let context = Context::new(PathBuf::new());
 
+        let mut secrets = crate::secret::SecretStore::new();
+        secrets.insert("token", "a\"b".to_string());
+        let context = context.with_secrets(secrets);
         context
             .set_string_value("test", "test_value")
             .await
             .unwrap();
         context.set_string_value("none.a", "a").await.unwrap();
+        context
+            .set_value(
+                "http.body",
+                serde_json::json!({"users": [{"name": "alice"}, {"name": "bob"}]}),
+            )
+            .await
+            .unwrap();
 
         for t in tests {
             let result = parse_variables(&context, &t.content).await;
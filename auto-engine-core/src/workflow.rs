@@ -1,7 +1,21 @@
 use std::pin::Pin;
 
+pub mod analysis;
 pub mod builder;
+pub mod checkpoint;
+pub mod clock;
+pub mod completion;
+pub mod control;
+pub mod cron;
+pub mod dry_run;
 pub mod graph;
+mod join;
+pub mod manager;
+pub mod report;
 pub mod runner;
+pub mod schedule;
+pub mod scheduler;
+pub mod validate;
+pub mod variables;
 
 pub type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send + 'static>>;
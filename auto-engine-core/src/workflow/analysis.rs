@@ -0,0 +1,315 @@
+use crate::node::time_wait::node::NODE_TYPE as TIME_WAIT_NODE_TYPE;
+use crate::workflow::graph::{Graph, NodeIndex};
+use crate::workflow::report::RunReport;
+use std::collections::{HashMap, HashSet};
+
+/// The longest chain of nodes from a Start node and how long it takes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CriticalPath {
+    pub nodes: Vec<NodeIndex>,
+    pub duration_ms: u64,
+}
+
+impl Graph {
+    /// Nodes grouped by the longest chain of connections leading to them. The
+    /// first layer holds the nodes nothing connects to, every other node sits
+    /// one layer below its deepest predecessor.
+    pub fn layers(&self) -> Vec<Vec<NodeIndex>> {
+        let mut depth = vec![0usize; self.nodes().len()];
+        let mut layers: Vec<Vec<NodeIndex>> = vec![];
+        for index in self.topological_order().iter().copied() {
+            let layer = self
+                .node(index)
+                .prev
+                .iter()
+                .map(|prev| depth[*prev] + 1)
+                .max()
+                .unwrap_or(0);
+            depth[index] = layer;
+            if layers.len() <= layer {
+                layers.resize_with(layer + 1, Vec::new);
+            }
+            layers[layer].push(index);
+        }
+        for layer in layers.iter_mut() {
+            layer.sort_unstable();
+        }
+        layers
+    }
+
+    /// Every node `index` has a path to, depth first.
+    pub fn descendants(&self, index: NodeIndex) -> Vec<NodeIndex> {
+        // `reachable_from` lists `index` itself first.
+        self.reachable_from(&[index]).into_iter().skip(1).collect()
+    }
+
+    /// Nodes no Start node leads to, in the order of the workflow. A run
+    /// never gets to them.
+    pub fn unreachable(&self) -> Vec<NodeIndex> {
+        let reachable: HashSet<NodeIndex> =
+            self.reachable_from(self.starts()).into_iter().collect();
+        (0..self.nodes().len())
+            .filter(|index| !reachable.contains(index))
+            .collect()
+    }
+
+    /// How long each node is expected to take in milliseconds, by index.
+    /// Nodes that ran in `history` take their average time, `TimeWait` nodes
+    /// that did not take their fixed duration and every other node 0.
+    pub fn estimated_durations(&self, history: &[RunReport]) -> Vec<u64> {
+        let mut timings: HashMap<&str, (u64, u64)> = HashMap::new();
+        for report in history.iter().flat_map(|run| run.nodes.iter()) {
+            if let Some(duration) = report.duration_ms {
+                let (total, count) = timings.entry(report.node_id.as_str()).or_default();
+                *total += duration;
+                *count += 1;
+            }
+        }
+        self.nodes()
+            .iter()
+            .map(|node| match timings.get(node.node_id.as_str()) {
+                Some((total, count)) => total / count,
+                None if node.node_context.action_type == TIME_WAIT_NODE_TYPE => {
+                    time_wait_ms(&node.node_context.input_data)
+                }
+                None => 0,
+            })
+            .collect()
+    }
+
+    /// The path from a Start node taking the longest when every node takes
+    /// `durations[index]` milliseconds, see `estimated_durations`. Empty when
+    /// `durations` does not hold a value for every node.
+    pub fn critical_path(&self, durations: &[u64]) -> CriticalPath {
+        if durations.len() != self.nodes().len() {
+            return CriticalPath::default();
+        }
+        let reachable: HashSet<NodeIndex> =
+            self.reachable_from(self.starts()).into_iter().collect();
+        // Time until a node finished on its longest path, and where that path
+        // came from.
+        let mut finished: Vec<Option<(u64, Option<NodeIndex>)>> = vec![None; durations.len()];
+        for index in self.topological_order().iter().copied() {
+            if !reachable.contains(&index) {
+                continue;
+            }
+            let longest = self
+                .node(index)
+                .prev
+                .iter()
+                .filter_map(|prev| finished[*prev].map(|(time, _)| (time, *prev)))
+                .max_by_key(|(time, _)| *time);
+            let (before, from) = match longest {
+                Some((time, prev)) => (time, Some(prev)),
+                None => (0, None),
+            };
+            finished[index] = Some((before + durations[index], from));
+        }
+
+        let Some((mut at, duration_ms)) = finished
+            .iter()
+            .enumerate()
+            .filter_map(|(index, finished)| finished.map(|(time, _)| (index, time)))
+            .max_by_key(|(_, time)| *time)
+        else {
+            return CriticalPath::default();
+        };
+        let mut nodes = vec![at];
+        while let Some((_, Some(prev))) = finished[at] {
+            nodes.push(prev);
+            at = prev;
+        }
+        nodes.reverse();
+        CriticalPath { nodes, duration_ms }
+    }
+
+    /// Whether `a` and `b` can run at the same time: a run reaches both and
+    /// neither has a path to the other. Conditions and ports are not taken
+    /// into account, they may still keep one of them from running.
+    pub fn can_run_in_parallel(&self, a: NodeIndex, b: NodeIndex) -> bool {
+        if a == b {
+            return false;
+        }
+        let reachable = self.reachable_from(self.starts());
+        reachable.contains(&a)
+            && reachable.contains(&b)
+            && !self.reachable_from(&[a]).contains(&b)
+            && !self.reachable_from(&[b]).contains(&a)
+    }
+
+    /// Every node that can run at the same time as `index`, in the order of
+    /// the workflow. See `can_run_in_parallel`.
+    pub fn parallel_with(&self, index: NodeIndex) -> Vec<NodeIndex> {
+        let mut reachable: HashSet<NodeIndex> =
+            self.reachable_from(self.starts()).into_iter().collect();
+        if !reachable.remove(&index) {
+            return vec![];
+        }
+        for related in self
+            .ancestors(index)
+            .into_iter()
+            .chain(self.descendants(index))
+        {
+            reachable.remove(&related);
+        }
+        let mut parallel: Vec<NodeIndex> = reachable.into_iter().collect();
+        parallel.sort_unstable();
+        parallel
+    }
+}
+
+/// The duration of a `TimeWait` node in milliseconds, 0 when it is only known
+/// at run time.
+fn time_wait_ms(input_data: &Option<HashMap<String, serde_json::Value>>) -> u64 {
+    let seconds = match input_data.as_ref().and_then(|input| input.get("duration")) {
+        Some(serde_json::Value::Number(number)) => number.as_f64(),
+        Some(serde_json::Value::String(text)) => text.trim().parse::<f64>().ok(),
+        _ => None,
+    };
+    seconds
+        .filter(|seconds| seconds.is_finite() && *seconds > 0.0)
+        .map(|seconds| (seconds * 1000.0).round() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+#[allow(deprecated)]
+mod tests {
+    use super::*;
+    use crate::schema::node::{NodeSchema, Position};
+    use crate::schema::workflow::{Connection, WorkflowSchema};
+    use crate::types::MetaData;
+    use crate::workflow::report::{NodeReport, NodeStatus, RunStatus};
+
+    fn node_schema(node_id: &str, action_type: &str, duration: Option<&str>) -> NodeSchema {
+        NodeSchema {
+            node_id: node_id.to_string(),
+            action_type: action_type.to_string(),
+            metadata: MetaData {
+                name: node_id.to_string(),
+                description: None,
+                duration: None,
+                retry: None,
+                interval: None,
+                conditions: None,
+                err_return: None,
+                timeout_ms: None,
+                retry_policy: None,
+                join: None,
+                disabled: false,
+            },
+            params: None,
+            input_data: duration.map(|duration| {
+                HashMap::from([("duration".to_string(), serde_json::json!(duration))])
+            }),
+            position: Position::default(),
+            icon: None,
+            type_define: None,
+        }
+    }
+
+    /// start -> a -> c -> end, start -> wait -> end, and `x` -> c on its own.
+    fn graph() -> Graph {
+        let connections = [
+            ("start", "a"),
+            ("start", "wait"),
+            ("a", "c"),
+            ("c", "end"),
+            ("wait", "end"),
+            ("x", "c"),
+        ];
+        Graph::compile(WorkflowSchema {
+            nodes: vec![
+                node_schema("start", "Start", None),
+                node_schema("a", "Custom", None),
+                node_schema("wait", TIME_WAIT_NODE_TYPE, Some("1.5")),
+                node_schema("c", "Custom", None),
+                node_schema("end", "End", None),
+                node_schema("x", "Custom", None),
+            ],
+            connections: connections
+                .iter()
+                .map(|(from, to)| Connection {
+                    from: from.to_string(),
+                    to: to.to_string(),
+                    port: None,
+                    condition: None,
+                })
+                .collect(),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn ids(graph: &Graph, indices: &[NodeIndex]) -> Vec<String> {
+        indices
+            .iter()
+            .map(|index| graph.node(*index).node_id.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_layers_and_reachability() {
+        let graph = graph();
+        let layers: Vec<Vec<String>> = graph
+            .layers()
+            .iter()
+            .map(|layer| ids(&graph, layer))
+            .collect();
+        assert_eq!(
+            layers,
+            vec![
+                vec!["start", "x"],
+                vec!["a", "wait"],
+                vec!["c"],
+                vec!["end"],
+            ]
+        );
+
+        let a = graph.index_of("a").unwrap();
+        assert_eq!(ids(&graph, &graph.descendants(a)), vec!["c", "end"]);
+        assert_eq!(ids(&graph, &graph.unreachable()), vec!["x"]);
+    }
+
+    #[test]
+    fn test_critical_path() {
+        let graph = graph();
+        let durations = graph.estimated_durations(&[]);
+        assert_eq!(durations[graph.index_of("wait").unwrap()], 1500);
+        let path = graph.critical_path(&durations);
+        assert_eq!(ids(&graph, &path.nodes), vec!["start", "wait", "end"]);
+        assert_eq!(path.duration_ms, 1500);
+
+        let mut slow = NodeReport::new("c", "c", "Custom", NodeStatus::Succeeded);
+        slow.duration_ms = Some(2000);
+        let history = RunReport {
+            status: RunStatus::Succeeded,
+            started_at: 0,
+            finished_at: 0,
+            duration_ms: 0,
+            nodes: vec![slow],
+            outputs: Default::default(),
+            dry_run: None,
+            context: Default::default(),
+            error: None,
+        };
+        let path = graph.critical_path(&graph.estimated_durations(&[history]));
+        assert_eq!(ids(&graph, &path.nodes), vec!["start", "a", "c", "end"]);
+        assert_eq!(path.duration_ms, 2000);
+    }
+
+    #[test]
+    fn test_parallel_branches() {
+        let graph = graph();
+        let index = |id: &str| graph.index_of(id).unwrap();
+        assert!(graph.can_run_in_parallel(index("a"), index("wait")));
+        assert!(graph.can_run_in_parallel(index("c"), index("wait")));
+        assert!(!graph.can_run_in_parallel(index("a"), index("c")));
+        assert!(!graph.can_run_in_parallel(index("a"), index("x")));
+        assert_eq!(
+            ids(&graph, &graph.parallel_with(index("wait"))),
+            vec!["a", "c"]
+        );
+        assert!(graph.parallel_with(index("end")).is_empty());
+    }
+}
@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Progress of a run, written after every node that succeeds so the run
+/// can be resumed once the process is gone.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Checkpoint {
+    /// Outputs of every node that succeeded, by node id. A resumed run
+    /// replays these nodes without running them again.
+    pub completed: HashMap<String, Option<HashMap<String, Value>>>,
+    /// Validated inputs of every Start node, by node id.
+    pub inputs: HashMap<String, HashMap<String, Value>>,
+    /// The iteration every unfinished ForEach node was in, by node id. A
+    /// resumed loop continues with that iteration instead of the first.
+    #[serde(default)]
+    pub loops: HashMap<String, usize>,
+    /// The `Context` values when the checkpoint was written.
+    pub context: HashMap<String, Value>,
+}
+
+impl Checkpoint {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read checkpoint {}: {}", path.display(), e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse checkpoint {}: {}", path.display(), e))
+    }
+
+    /// Writes the checkpoint next to `path` first and then moves it in
+    /// place, so a crash while writing keeps the previous one.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let content = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".tmp");
+        std::fs::write(&temp, content)
+            .map_err(|e| format!("Failed to write checkpoint {}: {}", path.display(), e))?;
+        std::fs::rename(&temp, path)
+            .map_err(|e| format!("Failed to write checkpoint {}: {}", path.display(), e))
+    }
+}
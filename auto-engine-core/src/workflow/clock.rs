@@ -0,0 +1,69 @@
+use crate::workflow::report::now_ms;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// Source of time for the scheduler, in milliseconds since the Unix epoch.
+#[async_trait::async_trait]
+pub trait Clock: Send + Sync {
+    fn now_ms(&self) -> u64;
+
+    /// Returns once `now_ms` has reached `at`.
+    async fn sleep_until(&self, at: u64);
+}
+
+#[derive(Default, Clone, Copy, Debug)]
+pub struct SystemClock;
+
+#[async_trait::async_trait]
+impl Clock for SystemClock {
+    fn now_ms(&self) -> u64 {
+        now_ms()
+    }
+
+    async fn sleep_until(&self, at: u64) {
+        // Checks the wall clock again, the process may have been suspended.
+        loop {
+            let now = now_ms();
+            if now >= at {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(at - now)).await;
+        }
+    }
+}
+
+/// A clock that only moves when told to, for tests.
+#[derive(Clone, Debug)]
+pub struct FakeClock {
+    now: Arc<watch::Sender<u64>>,
+}
+
+impl FakeClock {
+    pub fn new(now: u64) -> Self {
+        let (now, _) = watch::channel(now);
+        Self { now: Arc::new(now) }
+    }
+
+    /// Moves the clock to `now`, waking every sleeper that is due.
+    pub fn set(&self, now: u64) {
+        self.now.send_replace(now);
+    }
+
+    pub fn advance(&self, ms: u64) {
+        self.now.send_modify(|now| *now += ms);
+    }
+}
+
+#[async_trait::async_trait]
+impl Clock for FakeClock {
+    fn now_ms(&self) -> u64 {
+        *self.now.borrow()
+    }
+
+    async fn sleep_until(&self, at: u64) {
+        let mut receiver = self.now.subscribe();
+        // The sender lives as long as `self`, waiting cannot fail.
+        let _ = receiver.wait_for(|now| *now >= at).await;
+    }
+}
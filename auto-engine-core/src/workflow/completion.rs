@@ -0,0 +1,434 @@
+use crate::node::for_each;
+use crate::node::start::node::NODE_TYPE as START_NODE_TYPE;
+use crate::register::bus::NodeRegisterBus;
+use crate::schema::node::NodeSchema;
+use crate::types::field::{FieldType, SchemaField};
+use crate::types::node::{ERROR_OUTPUT, ERROR_PORT, I18nValue};
+use crate::workflow::graph::Graph;
+use crate::workflow::variables::VARIABLE_PREFIX;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// A value a node can reference as `${path}`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Variable {
+    pub path: String,
+    #[serde(rename = "type")]
+    pub field_type: FieldType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item_type: Option<FieldType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<I18nValue>,
+    /// The node that sets it, none for the variables of the workflow.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node_id: Option<String>,
+}
+
+impl Graph {
+    /// Every variable set before `node_id` runs, nearest node first: the
+    /// outputs of the upstream nodes as `ctx.<name>.<field>`, the fields
+    /// inside object values whose shape is known, `ctx.<name>.error` of
+    /// upstream nodes whose failure does not stop the run, the loop
+    /// variables inside a ForEach body and last the workflow variables.
+    /// Upstream nodes directly connected to `node_id` must be registered,
+    /// others that are not are left out.
+    pub async fn variables_at(
+        &self,
+        node_id: &str,
+        bus: Arc<RwLock<NodeRegisterBus>>,
+    ) -> Result<Vec<Variable>, String> {
+        let Some(index) = self.index_of(node_id) else {
+            return Ok(vec![]);
+        };
+
+        let bus = bus.read().await;
+        let mut variables = Variables::default();
+        let mut loops = vec![];
+        for ancestor in self.ancestors(index) {
+            let graph_node = self.node(ancestor);
+            let node = &graph_node.node_context;
+            let Some(define) = bus.load_node(&node.action_type) else {
+                if self.node(index).prev.contains(&ancestor) {
+                    return Err(format!("Node {} not found", node.action_type));
+                }
+                continue;
+            };
+            let input = node.input_data.clone().unwrap_or_default();
+            for field in define.output_schema(input) {
+                let sample = sample_value(node, &field);
+                variables.push_field(
+                    format!("ctx.{}.{}", node.metadata.name, field.name),
+                    &field,
+                    sample.as_ref(),
+                    Some(&node.node_id),
+                );
+            }
+
+            let handles_errors = node.metadata.err_return == Some(false)
+                || graph_node
+                    .next
+                    .iter()
+                    .any(|edge| edge.port.as_deref() == Some(ERROR_PORT));
+            if handles_errors {
+                variables.push(Variable {
+                    path: format!("ctx.{}.{}", node.metadata.name, ERROR_OUTPUT),
+                    field_type: FieldType::String,
+                    item_type: None,
+                    description: Some(I18nValue {
+                        zh: "节点失败时的错误信息".to_string(),
+                        en: "Error message when the node failed".to_string(),
+                    }),
+                    node_id: Some(node.node_id.clone()),
+                });
+            }
+
+            if node.action_type == for_each::node::NODE_TYPE {
+                loops.push(node);
+            }
+            if node.action_type == for_each::node::NODE_TYPE
+                && self.loop_body(ancestor).contains(&index)
+            {
+                let outputs = define.output_schema(node.input_data.clone().unwrap_or_default());
+                for (path, name) in [
+                    (for_each::node::LOOP_INDEX, "index"),
+                    (for_each::node::LOOP_ITEM, "item"),
+                ] {
+                    if let Some(field) = outputs.iter().find(|field| field.name == name) {
+                        variables.push_field(path.to_string(), field, None, Some(&node.node_id));
+                    }
+                }
+            }
+        }
+
+        let mut names: Vec<&String> = self.variables().keys().collect();
+        names.sort();
+        for name in names {
+            let value = &self.variables()[name];
+            let field = SchemaField {
+                name: name.clone(),
+                field_type: type_of(value),
+                ..Default::default()
+            };
+            variables.push_field(
+                format!("{}{}", VARIABLE_PREFIX, name),
+                &field,
+                Some(value),
+                None,
+            );
+        }
+
+        // The sources of the loop items come after the loops, so the item
+        // types are only known once every variable is in.
+        for node in loops {
+            let Some(item_type) = loop_item_type(node, &variables) else {
+                continue;
+            };
+            let output = format!("ctx.{}.item", node.metadata.name);
+            for variable in variables.list.iter_mut() {
+                if variable.node_id.as_deref() == Some(node.node_id.as_str())
+                    && (variable.path == output || variable.path == for_each::node::LOOP_ITEM)
+                {
+                    variable.field_type = item_type.clone();
+                }
+            }
+        }
+        Ok(variables.list)
+    }
+}
+
+/// Variables in the order they were found, each path listed once.
+#[derive(Default)]
+struct Variables {
+    list: Vec<Variable>,
+    paths: HashSet<String>,
+}
+
+impl Variables {
+    fn push(&mut self, variable: Variable) {
+        if self.paths.insert(variable.path.clone()) {
+            self.list.push(variable);
+        }
+    }
+
+    /// Adds `field` as `path`, followed by the fields inside `sample`.
+    fn push_field(
+        &mut self,
+        path: String,
+        field: &SchemaField,
+        sample: Option<&Value>,
+        node_id: Option<&str>,
+    ) {
+        self.push(Variable {
+            path: path.clone(),
+            field_type: field.field_type.clone(),
+            item_type: field
+                .item_type
+                .clone()
+                .or_else(|| sample.and_then(item_type_of)),
+            description: field.description.clone(),
+            node_id: node_id.map(str::to_string),
+        });
+        if let Some(sample) = sample {
+            self.push_nested(&path, sample, node_id);
+        }
+    }
+
+    fn push_nested(&mut self, path: &str, value: &Value, node_id: Option<&str>) {
+        let Value::Object(fields) = value else {
+            return;
+        };
+        for (name, value) in fields.iter() {
+            let path = format!("{}.{}", path, name);
+            self.push(Variable {
+                path: path.clone(),
+                field_type: type_of(value),
+                item_type: item_type_of(value),
+                description: None,
+                node_id: node_id.map(str::to_string),
+            });
+            self.push_nested(&path, value, node_id);
+        }
+    }
+}
+
+/// A value `field` of `node` is known to look like: a Start parameter, or
+/// the JSON default of an object or array field.
+fn sample_value(node: &NodeSchema, field: &SchemaField) -> Option<Value> {
+    if node.action_type == START_NODE_TYPE {
+        let param = node
+            .input_data
+            .as_ref()
+            .and_then(|input| input.get("params"))
+            .and_then(|params| params.get(&field.name));
+        if let Some(param) = param {
+            return Some(param.clone());
+        }
+    }
+    match field.field_type {
+        FieldType::Object | FieldType::Array => field
+            .default
+            .as_deref()
+            .and_then(|default| serde_json::from_str(default).ok()),
+        _ => None,
+    }
+}
+
+/// The type of the items a ForEach node loops over: those of a literal
+/// array, or the item type of the variable its items refer to.
+fn loop_item_type(node: &NodeSchema, variables: &Variables) -> Option<FieldType> {
+    let items = node.input_data.as_ref()?.get("items")?;
+    let items = match items {
+        Value::String(items) => items.trim(),
+        other => return item_type_of(other),
+    };
+    if let Ok(value) = serde_json::from_str::<Value>(items) {
+        return item_type_of(&value);
+    }
+    let path = items
+        .strip_prefix("${")
+        .and_then(|path| path.strip_suffix('}'))
+        .unwrap_or(items);
+    variables
+        .list
+        .iter()
+        .find(|variable| variable.path == path)
+        .and_then(|variable| variable.item_type.clone())
+}
+
+fn type_of(value: &Value) -> FieldType {
+    match value {
+        Value::Number(_) => FieldType::Number,
+        Value::Bool(_) => FieldType::Boolean,
+        Value::Array(_) => FieldType::Array,
+        Value::Object(_) => FieldType::Object,
+        _ => FieldType::String,
+    }
+}
+
+/// The type of the items of an array, going by its first one.
+fn item_type_of(value: &Value) -> Option<FieldType> {
+    match value {
+        Value::Array(items) => items.first().map(type_of),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::workflow::WorkflowSchema;
+
+    fn paths(variables: &[Variable]) -> Vec<(&str, &str)> {
+        variables
+            .iter()
+            .map(|variable| {
+                (
+                    variable.path.as_str(),
+                    variable.node_id.as_deref().unwrap_or_default(),
+                )
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn variables_at_lists_typed_nested_paths() {
+        let workflow: WorkflowSchema = serde_yaml::from_str(
+            r#"
+nodes:
+  - node_id: start
+    action_type: Start
+    name: begin
+    input_data:
+      params:
+        user:
+          name: alice
+          tags: [admin]
+      inputs:
+        - name: retries
+          type: number
+  - node_id: merge
+    action_type: DataAggregator
+    name: merge
+    err_return: false
+    input_data:
+      mode: object
+      sources: ["${ctx.begin.user.name}"]
+  - node_id: loop
+    action_type: ForEach
+    name: loop
+    input_data:
+      items: "[1, 2]"
+  - node_id: wait
+    action_type: TimeWait
+    name: wait
+    input_data:
+      duration: 1
+variables:
+  base_url: https://example.com
+  offset: {x: 10}
+connections:
+  - from: start
+    to: merge
+  - from: merge
+    to: loop
+  - from: loop
+    to: wait
+    port: body
+"#,
+        )
+        .unwrap();
+        let graph = Graph::compile(workflow).unwrap();
+        let bus = Arc::new(RwLock::new(NodeRegisterBus::new().with_internal_nodes()));
+
+        let variables = graph.variables_at("wait", bus.clone()).await.unwrap();
+        let found = paths(&variables);
+        for expected in [
+            ("ctx.begin.user", "start"),
+            ("ctx.begin.user.name", "start"),
+            ("ctx.begin.user.tags", "start"),
+            ("ctx.begin.retries", "start"),
+            ("ctx.merge.result", "merge"),
+            ("ctx.merge.error", "merge"),
+            ("ctx.loop.item", "loop"),
+            ("loop.index", "loop"),
+            ("loop.item", "loop"),
+            ("var.base_url", ""),
+            ("var.offset.x", ""),
+        ] {
+            assert!(found.contains(&expected), "{expected:?} in {found:?}");
+        }
+        // The nearest node comes first.
+        assert_eq!(found[0].1, "loop");
+
+        let tags = variables
+            .iter()
+            .find(|variable| variable.path == "ctx.begin.user.tags")
+            .unwrap();
+        assert!(matches!(tags.field_type, FieldType::Array));
+        assert!(matches!(tags.item_type, Some(FieldType::String)));
+        let retries = variables
+            .iter()
+            .find(|variable| variable.path == "ctx.begin.retries")
+            .unwrap();
+        assert!(matches!(retries.field_type, FieldType::Number));
+        let item = variables
+            .iter()
+            .find(|variable| variable.path == "loop.item")
+            .unwrap();
+        assert!(matches!(item.field_type, FieldType::Number));
+
+        // Loop variables only exist inside the body.
+        let variables = graph.variables_at("loop", bus.clone()).await.unwrap();
+        assert!(
+            !paths(&variables)
+                .iter()
+                .any(|(path, _)| path.starts_with("loop."))
+        );
+        let variables = graph.variables_at("start", bus).await.unwrap();
+        assert!(
+            paths(&variables)
+                .iter()
+                .all(|(path, _)| path.starts_with("var."))
+        );
+    }
+
+    #[tokio::test]
+    async fn variables_at_types_loop_items_by_their_source() {
+        let workflow: WorkflowSchema = serde_yaml::from_str(
+            r#"
+nodes:
+  - node_id: start
+    action_type: Start
+    name: begin
+    input_data:
+      params:
+        tags: [admin]
+  - node_id: tags
+    action_type: ForEach
+    name: tags
+    input_data:
+      items: "${ctx.begin.tags}"
+  - node_id: rows
+    action_type: ForEach
+    name: rows
+    input_data:
+      items: "${var.rows}"
+  - node_id: wait
+    action_type: TimeWait
+    name: wait
+    input_data:
+      duration: 1
+variables:
+  rows: "[]"
+connections:
+  - from: start
+    to: tags
+  - from: tags
+    to: rows
+    port: body
+  - from: rows
+    to: wait
+    port: body
+"#,
+        )
+        .unwrap();
+        let graph = Graph::compile(workflow).unwrap();
+        let bus = Arc::new(RwLock::new(NodeRegisterBus::new().with_internal_nodes()));
+
+        let variables = graph.variables_at("wait", bus).await.unwrap();
+        let field_type = |path: &str| {
+            variables
+                .iter()
+                .find(|variable| variable.path == path)
+                .map(|variable| variable.field_type.clone())
+                .unwrap()
+        };
+        assert!(matches!(field_type("ctx.tags.item"), FieldType::String));
+        // Nothing is known about the items of `var.rows`.
+        assert!(matches!(field_type("ctx.rows.item"), FieldType::Any));
+        assert!(matches!(field_type("loop.item"), FieldType::Any));
+    }
+}
@@ -0,0 +1,338 @@
+use crate::context::Context;
+use crate::event::{WORKFLOW_EVENT, WorkflowEventPayload, WorkflowStatus};
+use crate::notification::emitter::NotificationEmitter;
+use crate::types::conditions::Conditions;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ControlState {
+    Running,
+    /// Paused, with the number of nodes still allowed to start before the
+    /// run blocks again.
+    Paused {
+        steps: usize,
+    },
+}
+
+impl ControlState {
+    fn is_paused(&self) -> bool {
+        matches!(self, ControlState::Paused { .. })
+    }
+}
+
+/// What keeps nodes from starting: the pause set by `pause`/`step`, and
+/// apart from it whether a node is halted at a breakpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Gate {
+    state: ControlState,
+    halted: bool,
+}
+
+impl Gate {
+    fn is_paused(&self) -> bool {
+        self.halted || self.state.is_paused()
+    }
+}
+
+/// Halts a run before `node_id` starts, only when `condition` passes if
+/// one is set.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Breakpoint {
+    pub node_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<Conditions>,
+}
+
+/// How a run halted before a node goes on.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DebugAction {
+    /// Runs until the next breakpoint.
+    Continue,
+    /// Runs the node and halts again before the next one starts.
+    StepOver,
+    /// Goes on without running the node, as if it succeeded without outputs.
+    Skip,
+}
+
+#[derive(Debug)]
+pub(crate) enum DebugCommand {
+    SetValue { key: String, value: Value },
+    Resume(DebugAction),
+}
+
+#[derive(Debug, Default)]
+struct Debugger {
+    breakpoints: HashMap<String, Option<Conditions>>,
+    /// Halt before the next node that starts, set by stepping over.
+    stepping: bool,
+    /// Nodes the run is halted before, with the channel reaching each.
+    halted: HashMap<String, mpsc::UnboundedSender<DebugCommand>>,
+}
+
+/// Handle used to cancel, pause, resume or single-step a workflow run, and
+/// to debug it with breakpoints.
+///
+/// The handle is cheap to clone; every clone drives the same run. Pausing
+/// never interrupts a node that is already running, the run stops before
+/// the next node starts and keeps its `Context` intact.
+#[derive(Debug, Clone)]
+pub struct RunControl {
+    token: CancellationToken,
+    gate: Arc<watch::Sender<Gate>>,
+    debugger: Arc<Mutex<Debugger>>,
+}
+
+impl Default for RunControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RunControl {
+    pub fn new() -> Self {
+        Self::from_token(CancellationToken::new())
+    }
+
+    pub fn from_token(token: CancellationToken) -> Self {
+        let (gate, _) = watch::channel(Gate {
+            state: ControlState::Running,
+            halted: false,
+        });
+        Self {
+            token,
+            gate: Arc::new(gate),
+            debugger: Default::default(),
+        }
+    }
+
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.gate.borrow().is_paused()
+    }
+
+    /// Stops the run before the next node starts.
+    pub fn pause(&self) {
+        self.gate.send_if_modified(|gate| {
+            if gate.state.is_paused() {
+                return false;
+            }
+            gate.state = ControlState::Paused { steps: 0 };
+            true
+        });
+    }
+
+    /// Lifts a pause. A run halted at a breakpoint stays halted until the
+    /// node is released.
+    pub fn resume(&self) {
+        self.gate.send_if_modified(|gate| {
+            if gate.state == ControlState::Running {
+                return false;
+            }
+            gate.state = ControlState::Running;
+            true
+        });
+    }
+
+    /// Lets exactly one more node start, then stays paused. Pauses the run
+    /// first if it is currently running.
+    pub fn step(&self) {
+        self.gate.send_modify(|gate| {
+            gate.state = match gate.state {
+                ControlState::Running => ControlState::Paused { steps: 1 },
+                ControlState::Paused { steps } => ControlState::Paused { steps: steps + 1 },
+            }
+        });
+    }
+
+    pub fn set_breakpoint(&self, breakpoint: Breakpoint) {
+        self.debugger()
+            .breakpoints
+            .insert(breakpoint.node_id, breakpoint.condition);
+    }
+
+    pub fn remove_breakpoint(&self, node_id: &str) {
+        self.debugger().breakpoints.remove(node_id);
+    }
+
+    pub fn breakpoints(&self) -> Vec<Breakpoint> {
+        self.debugger()
+            .breakpoints
+            .iter()
+            .map(|(node_id, condition)| Breakpoint {
+                node_id: node_id.clone(),
+                condition: condition.clone(),
+            })
+            .collect()
+    }
+
+    /// Nodes the run is halted before.
+    pub fn halted(&self) -> Vec<String> {
+        self.debugger().halted.keys().cloned().collect()
+    }
+
+    /// Changes a `Context` value while the run is halted before `node_id`.
+    pub fn set_value(&self, node_id: &str, key: &str, value: Value) -> Result<(), String> {
+        self.send(
+            node_id,
+            DebugCommand::SetValue {
+                key: key.to_string(),
+                value,
+            },
+        )
+    }
+
+    /// Lets the run halted before `node_id` go on.
+    pub fn debug(&self, node_id: &str, action: DebugAction) -> Result<(), String> {
+        self.send(node_id, DebugCommand::Resume(action))
+    }
+
+    fn send(&self, node_id: &str, command: DebugCommand) -> Result<(), String> {
+        let debugger = self.debugger();
+        let sender = debugger
+            .halted
+            .get(node_id)
+            .ok_or_else(|| format!("The run is not halted before node {}", node_id))?;
+        sender.send(command).map_err(|e| e.to_string())
+    }
+
+    fn debugger(&self) -> MutexGuard<'_, Debugger> {
+        self.debugger.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Whether the run should halt before `node_id`, because of a
+    /// breakpoint or a step.
+    pub(crate) async fn should_halt(&self, node_id: &str, ctx: &Context) -> Result<bool, String> {
+        let (stepping, breakpoint) = {
+            let debugger = self.debugger();
+            (
+                debugger.stepping,
+                debugger.breakpoints.get(node_id).cloned(),
+            )
+        };
+        match breakpoint {
+            _ if stepping => Ok(true),
+            Some(Some(condition)) => Ok(condition.check(ctx).await?.pass),
+            Some(None) => Ok(true),
+            None => Ok(false),
+        }
+    }
+
+    /// Halts the run before `node_id`. No other node starts until every
+    /// halted node was released.
+    pub(crate) fn halt(&self, node_id: &str) -> mpsc::UnboundedReceiver<DebugCommand> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        {
+            let mut debugger = self.debugger();
+            debugger.stepping = false;
+            debugger.halted.insert(node_id.to_string(), sender);
+        }
+        self.set_halted(true);
+        receiver
+    }
+
+    pub(crate) fn release(&self, node_id: &str, action: DebugAction) {
+        let released = {
+            let mut debugger = self.debugger();
+            debugger.halted.remove(node_id);
+            match action {
+                DebugAction::StepOver => debugger.stepping = true,
+                DebugAction::Continue => debugger.stepping = false,
+                DebugAction::Skip => {}
+            }
+            debugger.halted.is_empty()
+        };
+        if released {
+            self.set_halted(false);
+        }
+    }
+
+    fn set_halted(&self, halted: bool) {
+        self.gate.send_if_modified(|gate| {
+            if gate.halted == halted {
+                return false;
+            }
+            gate.halted = halted;
+            true
+        });
+    }
+
+    /// Waits until the run may start another node. Returns `false` when the
+    /// run was cancelled while waiting.
+    pub(crate) async fn wait_turn(&self) -> bool {
+        let mut receiver = self.gate.subscribe();
+        loop {
+            let mut acquired = false;
+            self.gate.send_if_modified(|gate| match &mut gate.state {
+                _ if gate.halted => false,
+                ControlState::Running => {
+                    acquired = true;
+                    false
+                }
+                ControlState::Paused { steps } if *steps > 0 => {
+                    *steps -= 1;
+                    acquired = true;
+                    true
+                }
+                ControlState::Paused { .. } => false,
+            });
+            if acquired {
+                return true;
+            }
+
+            tokio::select! {
+                _ = self.token.cancelled() => return false,
+                changed = receiver.changed() => {
+                    if changed.is_err() {
+                        return false;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Emits `Paused`/`Running` workflow events whenever the pause state
+    /// flips. The returned task must be aborted once the run is over.
+    pub(crate) fn forward_events(&self, emitter: Arc<NotificationEmitter>) -> JoinHandle<()> {
+        let mut receiver = self.gate.subscribe();
+        tokio::spawn(async move {
+            // The run always announces itself as running, so only report a
+            // pause that is already in place.
+            let mut paused = false;
+            loop {
+                let now_paused = receiver.borrow_and_update().is_paused();
+                if now_paused != paused {
+                    paused = now_paused;
+                    let status = if paused {
+                        WorkflowStatus::Paused
+                    } else {
+                        WorkflowStatus::Running
+                    };
+                    emitter
+                        .emit(WORKFLOW_EVENT, WorkflowEventPayload { status })
+                        .unwrap_or_default();
+                }
+                if receiver.changed().await.is_err() {
+                    break;
+                }
+            }
+        })
+    }
+}
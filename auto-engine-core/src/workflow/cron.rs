@@ -0,0 +1,267 @@
+use serde::{Deserialize, Serialize};
+
+pub(crate) const MINUTE_MS: i64 = 60_000;
+pub(crate) const DAY_MINUTES: i64 = 24 * 60;
+
+const MONTHS: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+const WEEKDAYS: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+/// A standard five field cron expression: minute, hour, day of month, month
+/// and day of week. Fields take `*`, lists, ranges and steps, months and
+/// weekdays also their three letter names. `@hourly`, `@daily`, `@weekly`,
+/// `@monthly` and `@yearly` are accepted as well.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct CronExpr {
+    source: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl CronExpr {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let expanded = match source.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!(
+                "Invalid cron expression `{}`: expected 5 fields, got {}",
+                source,
+                fields.len()
+            ));
+        };
+        let invalid = |e: String| format!("Invalid cron expression `{}`: {}", source, e);
+
+        let mut weekdays = parse_field(weekday, 0, 7, &WEEKDAYS).map_err(invalid)?;
+        // Both 0 and 7 are Sunday.
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            source: source.to_string(),
+            minutes: parse_field(minute, 0, 59, &[]).map_err(invalid)?,
+            hours: parse_field(hour, 0, 23, &[]).map_err(invalid)?,
+            days: parse_field(day, 1, 31, &[]).map_err(invalid)?,
+            months: parse_field(month, 1, 12, &MONTHS).map_err(invalid)?,
+            weekdays,
+            any_day: day == "*",
+            any_weekday: weekday == "*",
+        })
+    }
+
+    /// The first time after `after_ms` the expression matches, read in a
+    /// timezone `utc_offset_minutes` away from UTC. `None` when it never
+    /// matches, e.g. on February 30th.
+    pub fn next_after(&self, after_ms: u64, utc_offset_minutes: i32) -> Option<u64> {
+        let offset = utc_offset_minutes as i64;
+        let mut minute = (after_ms as i64).div_euclid(MINUTE_MS) + 1 + offset;
+        // Long enough for any date that exists, including February 29th.
+        let limit = minute + 8 * 366 * DAY_MINUTES;
+        while minute < limit {
+            let day = minute.div_euclid(DAY_MINUTES);
+            if self.matches_day(day) {
+                let of_day = minute.rem_euclid(DAY_MINUTES);
+                if let Some(time) = self.first_time_from(of_day / 60, of_day % 60) {
+                    let local = day * DAY_MINUTES + time;
+                    return u64::try_from((local - offset) * MINUTE_MS).ok();
+                }
+            }
+            minute = (day + 1) * DAY_MINUTES;
+        }
+        None
+    }
+
+    fn matches_day(&self, day: i64) -> bool {
+        let (_, month, day_of_month) = civil_from_days(day);
+        if !has(self.months, month) {
+            return false;
+        }
+        let by_day = has(self.days, day_of_month);
+        let by_weekday = has(self.weekdays, weekday(day));
+        // Like cron, a day matches either field when both are restricted.
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => by_weekday,
+            (false, true) => by_day,
+            (false, false) => by_day || by_weekday,
+        }
+    }
+
+    /// Minutes into the day of the first matching time at or after
+    /// `hour:minute`.
+    fn first_time_from(&self, hour: i64, minute: i64) -> Option<i64> {
+        for h in hour..24 {
+            if !has(self.hours, h as u32) {
+                continue;
+            }
+            let from = if h == hour { minute } else { 0 };
+            if let Some(m) = (from..60).find(|m| has(self.minutes, *m as u32)) {
+                return Some(h * 60 + m);
+            }
+        }
+        None
+    }
+}
+
+impl TryFrom<String> for CronExpr {
+    type Error = String;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        Self::parse(&source)
+    }
+}
+
+impl From<CronExpr> for String {
+    fn from(expr: CronExpr) -> Self {
+        expr.source
+    }
+}
+
+fn has(set: u64, value: u32) -> bool {
+    set & (1 << value) != 0
+}
+
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, String> {
+    let mut set = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step = step
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|step| *step > 0)
+                    .ok_or_else(|| format!("invalid step in `{}`", part))?;
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (from, to) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((from, to)) => (
+                parse_value(from, min, max, names)?,
+                parse_value(to, min, max, names)?,
+            ),
+            // `5/15` runs from 5 to the end of the range.
+            None if step > 1 => (parse_value(range, min, max, names)?, max),
+            None => {
+                let value = parse_value(range, min, max, names)?;
+                (value, value)
+            }
+        };
+        if from > to {
+            return Err(format!("invalid range `{}`", part));
+        }
+        for value in (from..=to).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
+
+fn parse_value(text: &str, min: u32, max: u32, names: &[&str]) -> Result<u32, String> {
+    if let Some(index) = names
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case(text))
+    {
+        // Months are numbered from 1, weekdays from 0.
+        return Ok(index as u32 + min);
+    }
+    text.parse::<u32>()
+        .ok()
+        .filter(|value| (min..=max).contains(value))
+        .ok_or_else(|| format!("`{}` is not between {} and {}", text, min, max))
+}
+
+/// Year, month and day of a day counted from 1970-01-01.
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Day of the week of a day counted from 1970-01-01, Sunday is 0.
+pub(crate) fn weekday(days: i64) -> u32 {
+    (days + 4).rem_euclid(7) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2024-01-01T00:00:00Z, a Monday.
+    const MONDAY: u64 = 1_704_067_200_000;
+    const HOUR: u64 = 3_600_000;
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19_723), (2024, 1, 1));
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+        assert_eq!(weekday(19_723), 1);
+    }
+
+    #[test]
+    fn test_next_after() {
+        let every_15 = CronExpr::parse("*/15 * * * *").unwrap();
+        assert_eq!(every_15.next_after(MONDAY, 0), Some(MONDAY + 15 * 60_000));
+        assert_eq!(
+            every_15.next_after(MONDAY + 14 * 60_000 + 1, 0),
+            Some(MONDAY + 15 * 60_000)
+        );
+
+        let workdays = CronExpr::parse("30 9 * * MON-FRI").unwrap();
+        let friday = MONDAY + 4 * 24 * HOUR;
+        assert_eq!(
+            workdays.next_after(friday + 10 * HOUR, 0),
+            Some(friday + 3 * 24 * HOUR + 9 * HOUR + 30 * 60_000)
+        );
+
+        // 09:00 at UTC+2 is 07:00 UTC.
+        let nine = CronExpr::parse("0 9 * * *").unwrap();
+        assert_eq!(nine.next_after(MONDAY, 120), Some(MONDAY + 7 * HOUR));
+
+        // Midnight at UTC+2 is 22:00 UTC the day before.
+        let daily = CronExpr::parse("@daily").unwrap();
+        assert_eq!(daily.next_after(MONDAY, 120), Some(MONDAY + 22 * HOUR));
+
+        let leap_day = CronExpr::parse("0 0 29 FEB *").unwrap();
+        assert_eq!(
+            leap_day.next_after(MONDAY, 0),
+            Some(MONDAY + 59 * 24 * HOUR)
+        );
+        assert_eq!(
+            CronExpr::parse("0 0 30 2 *").unwrap().next_after(0, 0),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(CronExpr::parse("* * * *").is_err());
+        assert!(CronExpr::parse("60 * * * *").is_err());
+        assert!(CronExpr::parse("*/0 * * * *").is_err());
+        assert!(CronExpr::parse("5-1 * * * *").is_err());
+        assert!(CronExpr::parse("0 0 * * 7").is_ok());
+    }
+}
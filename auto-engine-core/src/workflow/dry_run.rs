@@ -0,0 +1,185 @@
+use crate::context::Context;
+use crate::secret::SECRET_PREFIX;
+use crate::types::field::{FieldType, SchemaField};
+use crate::types::node::{NodeDefine, OUTPUT_PORT};
+use crate::utils::REGEX_PARSE_VARIABLES;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Settings of a run that exercises the control flow only. Nodes with side
+/// effects are not run, they return outputs shaped like their
+/// `output_schema` or the fixture given for them.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct DryRun {
+    /// Outputs to return instead, by node id or node name.
+    #[serde(default)]
+    pub fixtures: HashMap<String, HashMap<String, Value>>,
+}
+
+impl DryRun {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads fixtures from a YAML or JSON file that maps node ids or names
+    /// to their outputs.
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read fixtures {}: {}", path.display(), e))?;
+        let fixtures = serde_yaml::from_str(&content)
+            .map_err(|e| format!("Failed to parse fixtures {}: {}", path.display(), e))?;
+        Ok(Self { fixtures })
+    }
+
+    pub fn with_fixture(mut self, node: &str, outputs: HashMap<String, Value>) -> Self {
+        self.fixtures.insert(node.to_string(), outputs);
+        self
+    }
+
+    /// The outputs a node returns in this dry run, `None` when it runs for
+    /// real.
+    pub(crate) fn outputs(
+        &self,
+        node_id: &str,
+        node_name: &str,
+        node: &dyn NodeDefine,
+        input: HashMap<String, Value>,
+    ) -> Option<HashMap<String, Value>> {
+        if let Some(fixture) = self
+            .fixtures
+            .get(node_id)
+            .or_else(|| self.fixtures.get(node_name))
+        {
+            return Some(fixture.clone());
+        }
+        if !node.has_side_effects() {
+            return None;
+        }
+
+        let mut outputs: HashMap<String, Value> = node
+            .output_schema(input)
+            .iter()
+            .map(|field| (field.name.clone(), synthesize(field)))
+            .collect();
+        // Takes the first outcome, a fixture can pick another one.
+        if let Some(port) = node.output_ports().first() {
+            outputs.insert(OUTPUT_PORT.to_string(), Value::from(port.as_str()));
+        }
+        Some(outputs)
+    }
+}
+
+/// A value of the type `field` declares, its default or first allowed value
+/// when it has one.
+fn synthesize(field: &SchemaField) -> Value {
+    let preset = field.enums.first().or(field.default.as_ref());
+    match field.field_type {
+        FieldType::Number => preset
+            .and_then(|preset| serde_json::from_str::<serde_json::Number>(preset).ok())
+            .map(Value::Number)
+            .unwrap_or_else(|| Value::from(0)),
+        FieldType::Boolean => Value::Bool(preset.is_some_and(|preset| preset == "true")),
+        FieldType::Array => Value::Array(vec![]),
+        FieldType::Object => Value::Object(Default::default()),
+        FieldType::String | FieldType::Image | FieldType::File => {
+            Value::String(preset.cloned().unwrap_or_default())
+        }
+        FieldType::Any => preset.cloned().map(Value::String).unwrap_or(Value::Null),
+    }
+}
+
+/// What a dry run found out about the control flow.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct DryRunReport {
+    /// Every node condition that was checked.
+    pub conditions: Vec<ConditionCheck>,
+    /// Every outgoing connection of a node that ran.
+    pub branches: Vec<BranchCheck>,
+    /// Every `${...}` reference to a variable that did not exist.
+    pub defaults: Vec<DefaultReference>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ConditionCheck {
+    pub node_id: String,
+    pub passed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BranchCheck {
+    pub from: String,
+    pub to: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<String>,
+    pub taken: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DefaultReference {
+    pub node_id: String,
+    /// Input the reference is in, nested values joined with `.`.
+    pub field: String,
+    pub variable: String,
+    /// What the reference resolved to instead.
+    pub default: String,
+}
+
+/// The references in `input` to variables `ctx` does not have.
+pub(crate) async fn default_references(
+    ctx: &Context,
+    node_id: &str,
+    input: &HashMap<String, Value>,
+) -> Vec<DefaultReference> {
+    let mut strings = vec![];
+    for (name, value) in input.iter() {
+        collect_strings(name.clone(), value, &mut strings);
+    }
+    strings.sort();
+
+    let values = ctx.read_values().await;
+    let mut references = vec![];
+    for (field, text) in strings {
+        for caps in REGEX_PARSE_VARIABLES.captures_iter(text) {
+            let variable = &caps[1];
+            let secret = variable
+                .strip_prefix(SECRET_PREFIX)
+                .and_then(|name| ctx.secrets().get(name));
+            if secret.is_some() || values.get(variable).is_some() {
+                continue;
+            }
+            references.push(DefaultReference {
+                node_id: node_id.to_string(),
+                field: field.clone(),
+                variable: variable.to_string(),
+                default: caps.get(2).map(|m| m.as_str()).unwrap_or("").to_string(),
+            });
+        }
+    }
+    references
+}
+
+/// Every string in `value` with where it is, nested values joined with `.`.
+pub(crate) fn collect_strings<'a>(
+    path: String,
+    value: &'a Value,
+    strings: &mut Vec<(String, &'a str)>,
+) {
+    match value {
+        Value::String(text) => strings.push((path, text)),
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                collect_strings(format!("{}.{}", path, index), item, strings);
+            }
+        }
+        Value::Object(map) => {
+            for (key, item) in map.iter() {
+                collect_strings(format!("{}.{}", path, key), item, strings);
+            }
+        }
+        _ => {}
+    }
+}
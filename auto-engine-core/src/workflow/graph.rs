@@ -1,104 +1,264 @@
+use crate::node::for_each;
+use crate::node::start::node::NODE_TYPE as START_NODE_TYPE;
 use crate::register::bus::NodeRegisterBus;
 use crate::schema::node::NodeSchema;
+use crate::schema::workflow::WorkflowSchema;
+use crate::types::conditions::Conditions;
 use crate::types::field::SchemaField;
-use crate::workflow::BoxFuture;
-use std::collections::HashMap;
+use crate::workflow::variables;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
-use std::sync::atomic::AtomicUsize;
 use tokio::sync::RwLock;
 
+/// Position of a node in its workflow.
+pub type NodeIndex = usize;
+
+#[derive(Debug, Clone)]
+pub struct GraphEdge {
+    pub to: NodeIndex,
+    pub port: Option<String>,
+    pub condition: Option<Conditions>,
+}
+
 #[derive(Debug, Clone)]
 pub struct GraphNode {
     pub node_id: String,
     pub node_context: NodeSchema,
-    pub next: Vec<Arc<RwLock<GraphNode>>>,
-    pub prev: Vec<String>,
-    pub wait_count: Arc<AtomicUsize>,
+    /// Outgoing connections, in the order of the workflow.
+    pub next: Vec<GraphEdge>,
+    /// Nodes with a connection to this one, each listed once.
+    pub prev: Vec<NodeIndex>,
+    /// Connections to this node from nodes a run can reach, the number of
+    /// branches a join waits for.
+    pub incoming: usize,
 }
+
+/// A workflow compiled into an immutable graph. Nodes are addressed by their
+/// index in the workflow; whatever a run changes is kept by the run.
+#[derive(Debug, Clone, Default)]
 pub struct Graph {
-    pub nodes: HashMap<String, Arc<RwLock<GraphNode>>>,
-    pub starts: Vec<Arc<RwLock<GraphNode>>>,
+    nodes: Vec<GraphNode>,
+    index: HashMap<String, NodeIndex>,
+    starts: Vec<NodeIndex>,
+    order: Vec<NodeIndex>,
+    variables: HashMap<String, serde_json::Value>,
 }
 
 impl Graph {
-    pub fn new(
-        nodes: HashMap<String, Arc<RwLock<GraphNode>>>,
-        starts: Vec<Arc<RwLock<GraphNode>>>,
-    ) -> Graph {
-        Graph { nodes, starts }
+    /// Checks that every connection joins two nodes, that nothing leads back
+    /// to a Start node and that there are no cycles.
+    pub fn compile(workflow: WorkflowSchema) -> Result<Self, String> {
+        for name in workflow.variables.keys() {
+            variables::check_name(name)?;
+        }
+        let mut index = HashMap::with_capacity(workflow.nodes.len());
+        let mut starts = vec![];
+        let mut nodes = Vec::with_capacity(workflow.nodes.len());
+        for node_context in workflow.nodes.into_iter() {
+            let position = nodes.len();
+            if index
+                .insert(node_context.node_id.clone(), position)
+                .is_some()
+            {
+                return Err(format!(
+                    "node id '{}' is used more than once",
+                    node_context.node_id
+                ));
+            }
+            if node_context.action_type == START_NODE_TYPE {
+                starts.push(position);
+            }
+            nodes.push(GraphNode {
+                node_id: node_context.node_id.clone(),
+                node_context,
+                next: vec![],
+                prev: vec![],
+                incoming: 0,
+            });
+        }
+
+        for edge in workflow.connections.into_iter() {
+            if index.get(&edge.to).is_some_and(|to| starts.contains(to)) {
+                return Err(
+                    "The [Start] node cannot be used as the next node in the connection."
+                        .to_string(),
+                );
+            }
+            let from = *index
+                .get(&edge.from)
+                .ok_or_else(|| format!("connection references missing node '{}'", edge.from))?;
+            let to = *index
+                .get(&edge.to)
+                .ok_or_else(|| format!("connection references missing node '{}'", edge.to))?;
+            nodes[from].next.push(GraphEdge {
+                to,
+                port: edge.port,
+                condition: edge.condition,
+            });
+            if !nodes[to].prev.contains(&from) {
+                nodes[to].prev.push(from);
+            }
+        }
+
+        if starts.is_empty() {
+            return Err("workflow missing Start node".to_string());
+        }
+
+        let mut graph = Self {
+            order: topological_order(&nodes)?,
+            nodes,
+            index,
+            starts,
+            variables: workflow.variables,
+        };
+        for from in graph.reachable_from(&graph.starts) {
+            for edge in graph.nodes[from].next.clone() {
+                graph.nodes[edge.to].incoming += 1;
+            }
+        }
+        Ok(graph)
+    }
+
+    pub fn nodes(&self) -> &[GraphNode] {
+        &self.nodes
+    }
+
+    pub fn node(&self, index: NodeIndex) -> &GraphNode {
+        &self.nodes[index]
+    }
+
+    pub fn index_of(&self, node_id: &str) -> Option<NodeIndex> {
+        self.index.get(node_id).copied()
+    }
+
+    /// The Start nodes, in the order of the workflow.
+    pub fn starts(&self) -> &[NodeIndex] {
+        &self.starts
     }
 
+    /// The variables of the workflow with their declared values.
+    pub fn variables(&self) -> &HashMap<String, serde_json::Value> {
+        &self.variables
+    }
+
+    /// Every node, each one after all nodes with a connection to it.
+    pub fn topological_order(&self) -> &[NodeIndex] {
+        &self.order
+    }
+
+    /// `from` and every node reachable from it, depth first in the order of
+    /// the connections.
+    pub fn reachable_from(&self, from: &[NodeIndex]) -> Vec<NodeIndex> {
+        let mut visited = vec![false; self.nodes.len()];
+        let mut reached = vec![];
+        let mut stack: Vec<NodeIndex> = from.iter().rev().copied().collect();
+        while let Some(index) = stack.pop() {
+            if std::mem::replace(&mut visited[index], true) {
+                continue;
+            }
+            reached.push(index);
+            stack.extend(self.nodes[index].next.iter().rev().map(|edge| edge.to));
+        }
+        reached
+    }
+
+    /// Every node with a path to `index`, nearest first.
+    pub fn ancestors(&self, index: NodeIndex) -> Vec<NodeIndex> {
+        let mut visited = HashSet::from([index]);
+        let mut ancestors = vec![];
+        let mut queue: VecDeque<NodeIndex> = self.nodes[index].prev.iter().copied().collect();
+        while let Some(prev) = queue.pop_front() {
+            if visited.insert(prev) {
+                ancestors.push(prev);
+                queue.extend(self.nodes[prev].prev.iter().copied());
+            }
+        }
+        ancestors
+    }
+
+    /// The nodes reachable from the `body` port of the ForEach node `index`.
+    pub(crate) fn loop_body(&self, index: NodeIndex) -> Vec<NodeIndex> {
+        let body: Vec<NodeIndex> = self.nodes[index]
+            .next
+            .iter()
+            .filter(|edge| edge.port.as_deref() == Some(for_each::node::PORT_BODY))
+            .map(|edge| edge.to)
+            .collect();
+        self.reachable_from(&body)
+    }
+
+    /// Outputs of every upstream node of `id`, by node name. Nodes directly
+    /// connected to `id` must be registered, others that are not are left out.
     pub async fn node_params_from_ctx(
         &self,
         id: String,
         bus: Arc<RwLock<NodeRegisterBus>>,
     ) -> Result<HashMap<String, Vec<SchemaField>>, String> {
-        let graph_node = {
-            let option = self.nodes.get(&id);
-            match option {
-                None => return Ok(Default::default()),
-                Some(n) => n.clone(),
-            }
+        let Some(index) = self.index_of(&id) else {
+            return Ok(Default::default());
         };
 
-        let prev_nodes = graph_node.read().await.prev.clone();
-        get_prev_node_outputs(self.nodes.clone(), bus, prev_nodes).await
-    }
-}
-
-fn get_prev_node_outputs(
-    nodes: HashMap<String, Arc<RwLock<GraphNode>>>,
-    bus: Arc<RwLock<NodeRegisterBus>>,
-    prev_nodes: Vec<String>,
-) -> BoxFuture<Result<HashMap<String, Vec<SchemaField>>, String>> {
-    Box::pin(async move {
+        let bus = bus.read().await;
         let mut params = HashMap::new();
-        for node_id in prev_nodes {
-            let node = {
-                let node = nodes.get(&node_id);
-                match node {
-                    None => return Err(format!("connection references missing node '{node_id}'")),
-                    Some(n) => n.clone(),
+        for ancestor in self.ancestors(index) {
+            let node = &self.nodes[ancestor].node_context;
+            let Some(node_define) = bus.load_node(&node.action_type) else {
+                if self.nodes[index].prev.contains(&ancestor) {
+                    return Err(format!("Node {} not found", node.action_type));
                 }
+                continue;
             };
+            let input_data = node.input_data.clone().unwrap_or_default();
+            params.insert(
+                node.metadata.name.clone(),
+                node_define.output_schema(input_data),
+            );
+        }
+        Ok(params)
+    }
+}
 
-            let (action, name, input_data, prev_nodes) = {
-                let node_reader = node.read().await;
-                (
-                    node_reader.node_context.action_type.clone(),
-                    node_reader.node_context.metadata.name.clone(),
-                    node_reader
-                        .node_context
-                        .input_data
-                        .clone()
-                        .unwrap_or_default(),
-                    node_reader.prev.clone(),
-                )
-            };
-
-            let node_define = {
-                let bus = bus.read().await;
-                let node_define = bus.load_node(&action);
-                match node_define {
-                    None => {
-                        return Err(format!("Node {} not found", action));
-                    }
-                    Some(define) => define,
-                }
-            };
-            params.insert(name.clone(), node_define.output_schema(input_data).clone());
-
-            log::info!("params: {:?}", params);
-
-            let result = get_prev_node_outputs(nodes.clone(), bus.clone(), prev_nodes).await;
-
-            if let Ok(res) = result {
-                params.extend(res);
+/// Kahn's algorithm, failing with a node on a cycle if there is one.
+fn topological_order(nodes: &[GraphNode]) -> Result<Vec<NodeIndex>, String> {
+    let mut in_degree = vec![0usize; nodes.len()];
+    for node in nodes.iter() {
+        for edge in node.next.iter() {
+            in_degree[edge.to] += 1;
+        }
+    }
+    let mut queue: VecDeque<NodeIndex> = (0..nodes.len())
+        .filter(|index| in_degree[*index] == 0)
+        .collect();
+    let mut order = Vec::with_capacity(nodes.len());
+    while let Some(index) = queue.pop_front() {
+        order.push(index);
+        for edge in nodes[index].next.iter() {
+            in_degree[edge.to] -= 1;
+            if in_degree[edge.to] == 0 {
+                queue.push_back(edge.to);
             }
         }
+    }
+    if order.len() == nodes.len() {
+        return Ok(order);
+    }
 
-        Ok(params)
-    })
+    // Every node left has a predecessor left, walking back from any of them
+    // ends up going around a cycle.
+    let Some(mut at) = (0..nodes.len()).find(|index| in_degree[*index] > 0) else {
+        return Ok(order);
+    };
+    let mut seen = HashSet::new();
+    while seen.insert(at) {
+        match nodes[at].prev.iter().find(|prev| in_degree[**prev] > 0) {
+            Some(prev) => at = *prev,
+            None => break,
+        }
+    }
+    Err(format!(
+        "workflow contains a cycle at node '{}'",
+        nodes[at].node_id
+    ))
 }
 
 #[cfg(test)]
@@ -106,6 +266,7 @@ fn get_prev_node_outputs(
 mod tests {
     use super::*;
     use crate::schema::node::Position;
+    use crate::schema::workflow::Connection;
     use crate::types::MetaData;
     use crate::types::field::{FieldType, SchemaField};
     use crate::types::node::{I18nValue, NodeDefine};
@@ -119,6 +280,10 @@ mod tests {
             interval: None,
             conditions: None,
             err_return: None,
+            timeout_ms: None,
+            retry_policy: None,
+            join: None,
+            disabled: false,
         }
     }
 
@@ -172,11 +337,136 @@ mod tests {
         }
     }
 
+    /// A graph of a Start node and `nodes` as `(id, action type, name)`.
+    fn graph(nodes: &[(&str, &str, &str)], connections: &[(&str, &str)]) -> Graph {
+        let mut schemas = vec![node_schema("start", START_NODE_TYPE, "start")];
+        schemas.extend(
+            nodes
+                .iter()
+                .map(|(id, action, name)| node_schema(id, action, name)),
+        );
+        Graph::compile(WorkflowSchema {
+            nodes: schemas,
+            connections: connections
+                .iter()
+                .map(|(from, to)| Connection {
+                    from: from.to_string(),
+                    to: to.to_string(),
+                    port: None,
+                    condition: None,
+                })
+                .collect(),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn node_schema(node_id: &str, action_type: &str, name: &str) -> NodeSchema {
+        NodeSchema {
+            node_id: node_id.to_string(),
+            action_type: action_type.to_string(),
+            metadata: create_test_metadata(name),
+            params: None,
+            input_data: None,
+            position: Position::default(),
+            icon: None,
+            type_define: None,
+        }
+    }
+
+    fn register(bus: &mut NodeRegisterBus, action_type: &str, output_schema: Vec<SchemaField>) {
+        bus.register_node(
+            action_type.to_string(),
+            Box::new(MockNodeDefine {
+                action_type: action_type.to_string(),
+                output_schema,
+            }),
+        );
+    }
+
+    #[test]
+    fn test_compile_orders_and_counts() {
+        let graph = graph(
+            &[
+                ("a", "A", "a"),
+                ("b", "B", "b"),
+                ("c", "C", "c"),
+                ("x", "X", "x"),
+            ],
+            &[
+                ("start", "b"),
+                ("start", "a"),
+                ("a", "c"),
+                ("b", "c"),
+                ("x", "c"),
+            ],
+        );
+
+        let order: Vec<&str> = graph
+            .topological_order()
+            .iter()
+            .map(|index| graph.node(*index).node_id.as_str())
+            .collect();
+        let position = |id: &str| order.iter().position(|node| *node == id).unwrap();
+        assert!(position("start") < position("a"));
+        assert!(position("a") < position("c"));
+        assert!(position("x") < position("c"));
+
+        let c = graph.index_of("c").unwrap();
+        assert_eq!(graph.node(c).prev.len(), 3);
+        // `x` cannot be reached, a join at `c` does not wait for it.
+        assert_eq!(graph.node(c).incoming, 2);
+
+        let reached: Vec<&str> = graph
+            .reachable_from(graph.starts())
+            .into_iter()
+            .map(|index| graph.node(index).node_id.as_str())
+            .collect();
+        assert_eq!(reached, vec!["start", "b", "c", "a"]);
+    }
+
+    #[test]
+    fn test_compile_rejects_invalid_graphs() {
+        let compile = |nodes: Vec<NodeSchema>, connections: &[(&str, &str)]| {
+            Graph::compile(WorkflowSchema {
+                nodes,
+                connections: connections
+                    .iter()
+                    .map(|(from, to)| Connection {
+                        from: from.to_string(),
+                        to: to.to_string(),
+                        port: None,
+                        condition: None,
+                    })
+                    .collect(),
+                ..Default::default()
+            })
+            .unwrap_err()
+        };
+        let start = || node_schema("start", START_NODE_TYPE, "start");
+
+        let err = compile(
+            vec![
+                start(),
+                node_schema("a", "A", "a"),
+                node_schema("b", "B", "b"),
+                node_schema("c", "C", "c"),
+            ],
+            &[("start", "a"), ("a", "b"), ("b", "c"), ("c", "b")],
+        );
+        assert!(
+            err.contains("cycle at node 'b'") || err.contains("cycle at node 'c'"),
+            "{err}"
+        );
+        assert!(compile(vec![start(), start()], &[]).contains("more than once"));
+        assert!(compile(vec![start()], &[("start", "missing")]).contains("missing node"));
+        assert!(compile(vec![node_schema("a", "A", "a")], &[]).contains("missing Start"));
+    }
+
     #[tokio::test]
     async fn test_prev_parameters_with_nonexistent_node() {
-        let nodes: HashMap<String, Arc<RwLock<GraphNode>>> = HashMap::new();
         let bus = Arc::new(RwLock::new(NodeRegisterBus::new()));
-        let graph = Graph::new(nodes, vec![]);
+        let graph = Graph::default();
 
         let result = graph
             .node_params_from_ctx("nonexistent".to_string(), bus)
@@ -193,35 +483,8 @@ mod tests {
             create_test_schema_field("result", FieldType::String),
             create_test_schema_field("count", FieldType::Number),
         ];
-
-        bus.register_node(
-            "TestAction".to_string(),
-            Box::new(MockNodeDefine {
-                action_type: "TestAction".to_string(),
-                output_schema: output_fields.clone(),
-            }),
-        );
-
-        let node = GraphNode {
-            node_id: "node-1".to_string(),
-            node_context: NodeSchema {
-                node_id: "node-1".to_string(),
-                action_type: "TestAction".to_string(),
-                metadata: create_test_metadata("test_node"),
-                params: None,
-                input_data: None,
-                position: Position::default(),
-                icon: None,
-                type_define: None,
-            },
-            next: vec![],
-            prev: vec![],
-            wait_count: Arc::new(AtomicUsize::new(0)),
-        };
-
-        let mut nodes = HashMap::new();
-        nodes.insert("node-1".to_string(), Arc::new(RwLock::new(node)));
-        let graph = Graph::new(nodes, vec![]);
+        register(&mut bus, "TestAction", output_fields);
+        let graph = graph(&[("node-1", "TestAction", "test_node")], &[]);
 
         let result = graph
             .node_params_from_ctx("node-1".to_string(), Arc::new(RwLock::new(bus)))
@@ -234,45 +497,13 @@ mod tests {
     #[tokio::test]
     async fn test_prev_parameters_with_node_not_registered() {
         let bus = NodeRegisterBus::new();
-
-        let prev_node = GraphNode {
-            node_id: "node-1".to_string(),
-            node_context: NodeSchema {
-                node_id: "node-1".to_string(),
-                action_type: "UnknownAction".to_string(),
-                metadata: create_test_metadata("test_node"),
-                params: None,
-                input_data: None,
-                position: Position::default(),
-                icon: None,
-                type_define: None,
-            },
-            next: vec![],
-            prev: vec![],
-            wait_count: Arc::new(AtomicUsize::new(0)),
-        };
-
-        let node = GraphNode {
-            node_id: "node-2".to_string(),
-            node_context: NodeSchema {
-                node_id: "node-2".to_string(),
-                action_type: "SecondAction".to_string(),
-                metadata: create_test_metadata("consumer_node"),
-                params: None,
-                input_data: None,
-                position: Position::default(),
-                icon: None,
-                type_define: None,
-            },
-            next: vec![],
-            prev: vec!["node-1".to_string()],
-            wait_count: Arc::new(AtomicUsize::new(0)),
-        };
-
-        let mut nodes = HashMap::new();
-        nodes.insert("node-1".to_string(), Arc::new(RwLock::new(prev_node)));
-        nodes.insert("node-2".to_string(), Arc::new(RwLock::new(node)));
-        let graph = Graph::new(nodes, vec![]);
+        let graph = graph(
+            &[
+                ("node-1", "UnknownAction", "test_node"),
+                ("node-2", "SecondAction", "consumer_node"),
+            ],
+            &[("node-1", "node-2")],
+        );
 
         let result = graph
             .node_params_from_ctx("node-2".to_string(), Arc::new(RwLock::new(bus)))
@@ -285,65 +516,23 @@ mod tests {
     #[tokio::test]
     async fn test_prev_parameters_with_prev_nodes() {
         let mut bus = NodeRegisterBus::new();
-
-        // Register first node type
-        bus.register_node(
-            "FirstAction".to_string(),
-            Box::new(MockNodeDefine {
-                action_type: "FirstAction".to_string(),
-                output_schema: vec![create_test_schema_field("output1", FieldType::String)],
-            }),
+        register(
+            &mut bus,
+            "FirstAction",
+            vec![create_test_schema_field("output1", FieldType::String)],
         );
-
-        // Register second node type
-        bus.register_node(
-            "SecondAction".to_string(),
-            Box::new(MockNodeDefine {
-                action_type: "SecondAction".to_string(),
-                output_schema: vec![create_test_schema_field("output2", FieldType::Number)],
-            }),
+        register(
+            &mut bus,
+            "SecondAction",
+            vec![create_test_schema_field("output2", FieldType::Number)],
+        );
+        let graph = graph(
+            &[
+                ("node-1", "FirstAction", "first_node"),
+                ("node-2", "SecondAction", "second_node"),
+            ],
+            &[("node-1", "node-2")],
         );
-
-        // Create first node (predecessor)
-        let node1 = Arc::new(RwLock::new(GraphNode {
-            node_id: "node-1".to_string(),
-            node_context: NodeSchema {
-                node_id: "node-1".to_string(),
-                action_type: "FirstAction".to_string(),
-                metadata: create_test_metadata("first_node"),
-                params: None,
-                input_data: None,
-                position: Position::default(),
-                icon: None,
-                type_define: None,
-            },
-            next: vec![],
-            prev: vec![],
-            wait_count: Arc::new(AtomicUsize::new(0)),
-        }));
-
-        // Create second node with node1 as predecessor
-        let node2 = GraphNode {
-            node_id: "node-2".to_string(),
-            node_context: NodeSchema {
-                node_id: "node-2".to_string(),
-                action_type: "SecondAction".to_string(),
-                metadata: create_test_metadata("second_node"),
-                params: None,
-                input_data: None,
-                position: Position::default(),
-                icon: None,
-                type_define: None,
-            },
-            next: vec![],
-            prev: vec!["node-1".to_string()],
-            wait_count: Arc::new(AtomicUsize::new(0)),
-        };
-
-        let mut nodes = HashMap::new();
-        nodes.insert("node-1".to_string(), node1);
-        nodes.insert("node-2".to_string(), Arc::new(RwLock::new(node2)));
-        let graph = Graph::new(nodes, vec![]);
 
         let result = graph
             .node_params_from_ctx("node-2".to_string(), Arc::new(RwLock::new(bus)))
@@ -359,87 +548,29 @@ mod tests {
     #[tokio::test]
     async fn test_prev_parameters_with_multiple_prev_nodes() {
         let mut bus = NodeRegisterBus::new();
-
-        bus.register_node(
-            "Action1".to_string(),
-            Box::new(MockNodeDefine {
-                action_type: "Action1".to_string(),
-                output_schema: vec![create_test_schema_field("out1", FieldType::String)],
-            }),
+        register(
+            &mut bus,
+            "Action1",
+            vec![create_test_schema_field("out1", FieldType::String)],
         );
-
-        bus.register_node(
-            "Action2".to_string(),
-            Box::new(MockNodeDefine {
-                action_type: "Action2".to_string(),
-                output_schema: vec![create_test_schema_field("out2", FieldType::Number)],
-            }),
+        register(
+            &mut bus,
+            "Action2",
+            vec![create_test_schema_field("out2", FieldType::Number)],
         );
-
-        bus.register_node(
-            "Action3".to_string(),
-            Box::new(MockNodeDefine {
-                action_type: "Action3".to_string(),
-                output_schema: vec![create_test_schema_field("out3", FieldType::Boolean)],
-            }),
+        register(
+            &mut bus,
+            "Action3",
+            vec![create_test_schema_field("out3", FieldType::Boolean)],
+        );
+        let graph = graph(
+            &[
+                ("node-1", "Action1", "node1"),
+                ("node-2", "Action2", "node2"),
+                ("node-3", "Action3", "node3"),
+            ],
+            &[("node-1", "node-3"), ("node-2", "node-3")],
         );
-
-        let node1 = Arc::new(RwLock::new(GraphNode {
-            node_id: "node-1".to_string(),
-            node_context: NodeSchema {
-                node_id: "node-1".to_string(),
-                action_type: "Action1".to_string(),
-                metadata: create_test_metadata("node1"),
-                params: None,
-                input_data: None,
-                position: Position::default(),
-                icon: None,
-                type_define: None,
-            },
-            next: vec![],
-            prev: vec![],
-            wait_count: Arc::new(AtomicUsize::new(0)),
-        }));
-
-        let node2 = Arc::new(RwLock::new(GraphNode {
-            node_id: "node-2".to_string(),
-            node_context: NodeSchema {
-                node_id: "node-2".to_string(),
-                action_type: "Action2".to_string(),
-                metadata: create_test_metadata("node2"),
-                params: None,
-                input_data: None,
-                position: Position::default(),
-                icon: None,
-                type_define: None,
-            },
-            next: vec![],
-            prev: vec![],
-            wait_count: Arc::new(AtomicUsize::new(0)),
-        }));
-
-        let node3 = GraphNode {
-            node_id: "node-3".to_string(),
-            node_context: NodeSchema {
-                node_id: "node-3".to_string(),
-                action_type: "Action3".to_string(),
-                metadata: create_test_metadata("node3"),
-                params: None,
-                input_data: None,
-                position: Position::default(),
-                icon: None,
-                type_define: None,
-            },
-            next: vec![],
-            prev: vec!["node-1".to_string(), "node-2".to_string()],
-            wait_count: Arc::new(AtomicUsize::new(0)),
-        };
-
-        let mut nodes = HashMap::new();
-        nodes.insert("node-1".to_string(), node1);
-        nodes.insert("node-2".to_string(), node2);
-        nodes.insert("node-3".to_string(), Arc::new(RwLock::new(node3)));
-        let graph = Graph::new(nodes, vec![]);
 
         let result = graph
             .node_params_from_ctx("node-3".to_string(), Arc::new(RwLock::new(bus)))
@@ -455,76 +586,24 @@ mod tests {
     #[tokio::test]
     async fn test_prev_parameters_with_chain() {
         let mut bus = NodeRegisterBus::new();
-
         for i in 1..=3 {
-            bus.register_node(
-                format!("Action{}", i),
-                Box::new(MockNodeDefine {
-                    action_type: format!("Action{}", i),
-                    output_schema: vec![create_test_schema_field(
-                        &format!("out{}", i),
-                        FieldType::String,
-                    )],
-                }),
+            register(
+                &mut bus,
+                &format!("Action{}", i),
+                vec![create_test_schema_field(
+                    &format!("out{}", i),
+                    FieldType::String,
+                )],
             );
         }
-
-        let node1 = Arc::new(RwLock::new(GraphNode {
-            node_id: "node-1".to_string(),
-            node_context: NodeSchema {
-                node_id: "node-1".to_string(),
-                action_type: "Action1".to_string(),
-                metadata: create_test_metadata("node1"),
-                params: None,
-                input_data: None,
-                position: Position::default(),
-                icon: None,
-                type_define: None,
-            },
-            next: vec![],
-            prev: vec![],
-            wait_count: Arc::new(AtomicUsize::new(0)),
-        }));
-
-        let node2 = Arc::new(RwLock::new(GraphNode {
-            node_id: "node-2".to_string(),
-            node_context: NodeSchema {
-                node_id: "node-2".to_string(),
-                action_type: "Action2".to_string(),
-                metadata: create_test_metadata("node2"),
-                params: None,
-                input_data: None,
-                position: Position::default(),
-                icon: None,
-                type_define: None,
-            },
-            next: vec![],
-            prev: vec!["node-1".to_string()],
-            wait_count: Arc::new(AtomicUsize::new(0)),
-        }));
-
-        let node3 = GraphNode {
-            node_id: "node-3".to_string(),
-            node_context: NodeSchema {
-                node_id: "node-3".to_string(),
-                action_type: "Action3".to_string(),
-                metadata: create_test_metadata("node3"),
-                params: None,
-                input_data: None,
-                position: Position::default(),
-                icon: None,
-                type_define: None,
-            },
-            next: vec![],
-            prev: vec!["node-2".to_string()],
-            wait_count: Arc::new(AtomicUsize::new(0)),
-        };
-
-        let mut nodes = HashMap::new();
-        nodes.insert("node-1".to_string(), node1);
-        nodes.insert("node-2".to_string(), node2);
-        nodes.insert("node-3".to_string(), Arc::new(RwLock::new(node3)));
-        let graph = Graph::new(nodes, vec![]);
+        let graph = graph(
+            &[
+                ("node-1", "Action1", "node1"),
+                ("node-2", "Action2", "node2"),
+                ("node-3", "Action3", "node3"),
+            ],
+            &[("node-1", "node-2"), ("node-2", "node-3")],
+        );
 
         let result = graph
             .node_params_from_ctx("node-3".to_string(), Arc::new(RwLock::new(bus)))
@@ -540,61 +619,20 @@ mod tests {
     #[tokio::test]
     async fn test_prev_parameters_output_schema_content() {
         let mut bus = NodeRegisterBus::new();
-
         let output_fields = vec![
             create_test_schema_field("field1", FieldType::String),
             create_test_schema_field("field2", FieldType::Number),
             create_test_schema_field("field3", FieldType::Boolean),
         ];
-
-        bus.register_node(
-            "TestAction".to_string(),
-            Box::new(MockNodeDefine {
-                action_type: "TestAction".to_string(),
-                output_schema: output_fields.clone(),
-            }),
+        register(&mut bus, "TestAction", output_fields);
+        let graph = graph(
+            &[
+                ("node-1", "TestAction", "test_node"),
+                ("node-2", "ConsumerAction", "consumer"),
+            ],
+            &[("node-1", "node-2")],
         );
 
-        let node = GraphNode {
-            node_id: "node-1".to_string(),
-            node_context: NodeSchema {
-                node_id: "node-1".to_string(),
-                action_type: "TestAction".to_string(),
-                metadata: create_test_metadata("test_node"),
-                params: None,
-                input_data: None,
-                position: Position::default(),
-                icon: None,
-                type_define: None,
-            },
-            next: vec![],
-            prev: vec![],
-            wait_count: Arc::new(AtomicUsize::new(0)),
-        };
-
-        let mut nodes = HashMap::new();
-        nodes.insert("node-1".to_string(), Arc::new(RwLock::new(node)));
-
-        let consumer = GraphNode {
-            node_id: "node-2".to_string(),
-            node_context: NodeSchema {
-                node_id: "node-2".to_string(),
-                action_type: "ConsumerAction".to_string(),
-                metadata: create_test_metadata("consumer"),
-                params: None,
-                input_data: None,
-                position: Position::default(),
-                icon: None,
-                type_define: None,
-            },
-            next: vec![],
-            prev: vec!["node-1".to_string()],
-            wait_count: Arc::new(AtomicUsize::new(0)),
-        };
-
-        nodes.insert("node-2".to_string(), Arc::new(RwLock::new(consumer)));
-        let graph = Graph::new(nodes, vec![]);
-
         let result = graph
             .node_params_from_ctx("node-2".to_string(), Arc::new(RwLock::new(bus)))
             .await;
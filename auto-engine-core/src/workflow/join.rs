@@ -0,0 +1,166 @@
+use crate::types::join::JoinMode;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum JoinDecision {
+    /// More incoming branches have to report first.
+    Wait,
+    Run,
+    /// Not enough branches can arrive any more, the node is skipped and so
+    /// is everything only reachable through it.
+    Skip,
+    /// The node was decided before this branch arrived, nothing is left to
+    /// do for it.
+    Decided,
+}
+
+/// A resumed run rebuilds the counters while it replays the completed
+/// nodes, so they are not part of a checkpoint.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct JoinCounter {
+    live: usize,
+    dead: usize,
+    decided: bool,
+}
+
+/// Incoming branch counters of one run. Every taken edge reports a live
+/// arrival, every edge that is not taken a dead one, so a join never waits
+/// for a branch that will not come.
+#[derive(Debug, Default)]
+pub(crate) struct JoinState {
+    counters: Mutex<HashMap<String, JoinCounter>>,
+}
+
+impl JoinState {
+    /// Records one incoming edge of `node_id` and decides whether the node
+    /// runs. A node is decided once; later arrivals return `Decided`.
+    pub(crate) fn arrive(
+        &self,
+        node_id: &str,
+        incoming: usize,
+        mode: JoinMode,
+        live: bool,
+    ) -> Result<JoinDecision, String> {
+        let mut counters = self.counters.lock().map_err(|e| e.to_string())?;
+        let counter = counters.entry(node_id.to_string()).or_default();
+        if counter.decided {
+            return Ok(JoinDecision::Decided);
+        }
+        if live {
+            counter.live += 1;
+        } else {
+            counter.dead += 1;
+        }
+
+        let incoming = incoming.max(1);
+        let pending = incoming.saturating_sub(counter.live + counter.dead);
+        let decision = match mode {
+            JoinMode::All if pending > 0 => JoinDecision::Wait,
+            JoinMode::All if counter.live > 0 => JoinDecision::Run,
+            JoinMode::All => JoinDecision::Skip,
+            JoinMode::Any | JoinMode::AtLeast(_) => {
+                let needed = match mode {
+                    JoinMode::AtLeast(count) => count.clamp(1, incoming),
+                    _ => 1,
+                };
+                if counter.live >= needed {
+                    JoinDecision::Run
+                } else if counter.live + pending < needed {
+                    JoinDecision::Skip
+                } else {
+                    JoinDecision::Wait
+                }
+            }
+        };
+
+        if decision != JoinDecision::Wait {
+            counter.decided = true;
+        }
+        Ok(decision)
+    }
+
+    /// Forgets the counters of `node_ids` so they can run again, as the
+    /// body of a loop does.
+    pub(crate) fn reset<'a>(
+        &self,
+        node_ids: impl IntoIterator<Item = &'a String>,
+    ) -> Result<(), String> {
+        let mut counters = self.counters.lock().map_err(|e| e.to_string())?;
+        for node_id in node_ids {
+            counters.remove(node_id);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decide(mode: JoinMode, incoming: usize, arrivals: &[bool]) -> Vec<JoinDecision> {
+        let state = JoinState::default();
+        arrivals
+            .iter()
+            .map(|live| state.arrive("join", incoming, mode, *live).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_all() {
+        use JoinDecision::*;
+        assert_eq!(decide(JoinMode::All, 1, &[true]), vec![Run]);
+        assert_eq!(
+            decide(JoinMode::All, 3, &[true, true, true]),
+            vec![Wait, Wait, Run]
+        );
+        assert_eq!(decide(JoinMode::All, 2, &[false, true]), vec![Wait, Run]);
+        assert_eq!(decide(JoinMode::All, 2, &[false, false]), vec![Wait, Skip]);
+    }
+
+    #[test]
+    fn test_any() {
+        use JoinDecision::*;
+        assert_eq!(
+            decide(JoinMode::Any, 3, &[true, true, true]),
+            vec![Run, Decided, Decided]
+        );
+        assert_eq!(decide(JoinMode::Any, 2, &[false, true]), vec![Wait, Run]);
+        assert_eq!(decide(JoinMode::Any, 2, &[false, false]), vec![Wait, Skip]);
+    }
+
+    #[test]
+    fn test_at_least() {
+        use JoinDecision::*;
+        assert_eq!(
+            decide(JoinMode::AtLeast(2), 3, &[true, false, true]),
+            vec![Wait, Wait, Run]
+        );
+        assert_eq!(
+            decide(JoinMode::AtLeast(2), 3, &[false, false, true]),
+            vec![Wait, Skip, Decided]
+        );
+        assert_eq!(
+            decide(JoinMode::AtLeast(5), 2, &[true, true]),
+            vec![Wait, Run]
+        );
+    }
+
+    #[test]
+    fn test_reset() {
+        let state = JoinState::default();
+        assert_eq!(
+            state.arrive("join", 1, JoinMode::All, true).unwrap(),
+            JoinDecision::Run
+        );
+        assert_eq!(
+            state.arrive("join", 1, JoinMode::All, true).unwrap(),
+            JoinDecision::Decided
+        );
+        state.reset([&"join".to_string()]).unwrap();
+        assert_eq!(
+            state.arrive("join", 1, JoinMode::All, true).unwrap(),
+            JoinDecision::Run
+        );
+    }
+}
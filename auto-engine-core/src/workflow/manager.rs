@@ -0,0 +1,420 @@
+use crate::context::Context;
+use crate::notification::emitter::{NotificationEmitter, RunIdEmitter};
+use crate::register::bus::NodeRegisterBus;
+use crate::workflow::control::RunControl;
+use crate::workflow::report::{RunReport, RunStatus, now_ms};
+use crate::workflow::runner::WorkflowRunner;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{RwLock, Semaphore, watch};
+
+/// Where a run the manager started is at.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RunPhase {
+    /// Waiting for another run to finish, as too many are running.
+    Queued,
+    Running,
+    Paused,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl RunPhase {
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            RunPhase::Succeeded | RunPhase::Failed | RunPhase::Cancelled
+        )
+    }
+}
+
+impl From<RunStatus> for RunPhase {
+    fn from(status: RunStatus) -> Self {
+        match status {
+            RunStatus::Succeeded => RunPhase::Succeeded,
+            RunStatus::Failed => RunPhase::Failed,
+            RunStatus::Cancelled => RunPhase::Cancelled,
+        }
+    }
+}
+
+/// A run the manager started, as `WorkflowManager::runs` lists it.
+#[derive(Serialize, Clone, Debug)]
+pub struct RunInfo {
+    pub run_id: String,
+    pub status: RunPhase,
+    /// Unix time in milliseconds.
+    pub queued_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<u64>,
+    /// Set once the run finished.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report: Option<RunReport>,
+    /// Why the run failed, or why it never started.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+struct ManagedRun {
+    info: RunInfo,
+    control: RunControl,
+    finished: watch::Receiver<bool>,
+}
+
+/// Starts workflow runs in the background and keeps track of them by run
+/// ID. Every event a run emits carries its `run_id`.
+pub struct WorkflowManager {
+    bus: Arc<RwLock<NodeRegisterBus>>,
+    emitter: Arc<NotificationEmitter>,
+    /// Caps how many runs go on at once, the others are queued.
+    limit: Option<Arc<Semaphore>>,
+    /// In the order they were started.
+    runs: Arc<Mutex<Vec<ManagedRun>>>,
+}
+
+impl WorkflowManager {
+    pub fn new(bus: Arc<RwLock<NodeRegisterBus>>, emitter: Arc<NotificationEmitter>) -> Self {
+        Self {
+            bus,
+            emitter,
+            limit: None,
+            runs: Default::default(),
+        }
+    }
+
+    /// Runs at most `max` workflows at once, later runs wait in the queue.
+    pub fn with_max_concurrent(mut self, max: usize) -> Self {
+        self.limit = Some(Arc::new(Semaphore::new(max.max(1))));
+        self
+    }
+
+    /// Starts running `runner` with `inputs` and returns the ID of the run.
+    pub fn start(
+        &self,
+        runner: Arc<WorkflowRunner>,
+        ctx: Arc<Context>,
+        inputs: HashMap<String, Value>,
+    ) -> Result<String, String> {
+        self.start_with_control(runner, ctx, inputs, RunControl::new())
+    }
+
+    /// [`Self::start`] with a `control` prepared beforehand, e.g. with
+    /// breakpoints set before the first node runs.
+    pub fn start_with_control(
+        &self,
+        runner: Arc<WorkflowRunner>,
+        ctx: Arc<Context>,
+        inputs: HashMap<String, Value>,
+        control: RunControl,
+    ) -> Result<String, String> {
+        let run_id = format!("run-{:016x}", rand::random::<u64>());
+        let (finished, receiver) = watch::channel(false);
+        self.runs
+            .lock()
+            .map_err(|e| e.to_string())?
+            .push(ManagedRun {
+                info: RunInfo {
+                    run_id: run_id.clone(),
+                    status: RunPhase::Queued,
+                    queued_at: now_ms(),
+                    started_at: None,
+                    finished_at: None,
+                    report: None,
+                    error: None,
+                },
+                control: control.clone(),
+                finished: receiver,
+            });
+
+        let runs = self.runs.clone();
+        let limit = self.limit.clone();
+        let bus = self.bus.clone();
+        let emitter = Arc::new(NotificationEmitter::new().with_emitter(Box::new(
+            RunIdEmitter::new(run_id.clone(), self.emitter.clone()),
+        )));
+        let id = run_id.clone();
+        tokio::spawn(async move {
+            let token = control.token();
+            let permit = match limit {
+                Some(limit) => tokio::select! {
+                    _ = token.cancelled() => None,
+                    permit = limit.acquire_owned() => permit.ok(),
+                },
+                None => None,
+            };
+
+            let result = if control.is_cancelled() {
+                None
+            } else {
+                update(&runs, &id, |info| {
+                    info.status = RunPhase::Running;
+                    info.started_at = Some(now_ms());
+                });
+                Some(
+                    runner
+                        .run_with_control(ctx, inputs, control, bus, emitter)
+                        .await,
+                )
+            };
+            drop(permit);
+
+            update(&runs, &id, |info| {
+                info.finished_at = Some(now_ms());
+                match result {
+                    None => info.status = RunPhase::Cancelled,
+                    Some(Ok(report)) => {
+                        info.status = report.status.into();
+                        info.error = report.error.clone();
+                        info.report = Some(report);
+                    }
+                    Some(Err(e)) => {
+                        info.status = RunPhase::Failed;
+                        info.error = Some(e);
+                    }
+                }
+            });
+            finished.send_replace(true);
+        });
+        Ok(run_id)
+    }
+
+    /// Every run the manager knows of, in the order they were started.
+    pub fn runs(&self) -> Vec<RunInfo> {
+        let runs = self.runs.lock().unwrap_or_else(|e| e.into_inner());
+        runs.iter().map(ManagedRun::info).collect()
+    }
+
+    /// Runs that are queued, running or paused.
+    pub fn active(&self) -> Vec<RunInfo> {
+        let mut runs = self.runs();
+        runs.retain(|run| !run.status.is_finished());
+        runs
+    }
+
+    pub fn get(&self, run_id: &str) -> Option<RunInfo> {
+        let runs = self.runs.lock().unwrap_or_else(|e| e.into_inner());
+        runs.iter()
+            .find(|run| run.info.run_id == run_id)
+            .map(ManagedRun::info)
+    }
+
+    /// The handle that pauses, resumes, steps or cancels the run.
+    pub fn control(&self, run_id: &str) -> Result<RunControl, String> {
+        let runs = self.runs.lock().map_err(|e| e.to_string())?;
+        runs.iter()
+            .find(|run| run.info.run_id == run_id)
+            .map(|run| run.control.clone())
+            .ok_or_else(|| format!("Unknown run {}", run_id))
+    }
+
+    pub fn cancel(&self, run_id: &str) -> Result<(), String> {
+        self.control(run_id)?.cancel();
+        Ok(())
+    }
+
+    pub fn pause(&self, run_id: &str) -> Result<(), String> {
+        self.control(run_id)?.pause();
+        Ok(())
+    }
+
+    pub fn resume(&self, run_id: &str) -> Result<(), String> {
+        self.control(run_id)?.resume();
+        Ok(())
+    }
+
+    /// Waits until the run finished and returns how it went.
+    pub async fn wait(&self, run_id: &str) -> Result<RunInfo, String> {
+        let mut finished = {
+            let runs = self.runs.lock().map_err(|e| e.to_string())?;
+            runs.iter()
+                .find(|run| run.info.run_id == run_id)
+                .map(|run| run.finished.clone())
+                .ok_or_else(|| format!("Unknown run {}", run_id))?
+        };
+        finished
+            .wait_for(|finished| *finished)
+            .await
+            .map_err(|e| e.to_string())?;
+        self.get(run_id)
+            .ok_or_else(|| format!("Unknown run {}", run_id))
+    }
+
+    /// Forgets the runs that finished.
+    pub fn clear_finished(&self) {
+        let mut runs = self.runs.lock().unwrap_or_else(|e| e.into_inner());
+        runs.retain(|run| !run.info.status.is_finished());
+    }
+}
+
+impl ManagedRun {
+    fn info(&self) -> RunInfo {
+        let mut info = self.info.clone();
+        if info.status == RunPhase::Running && self.control.is_paused() {
+            info.status = RunPhase::Paused;
+        }
+        info
+    }
+}
+
+fn update(runs: &Mutex<Vec<ManagedRun>>, run_id: &str, update: impl FnOnce(&mut RunInfo)) {
+    let mut runs = runs.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(run) = runs.iter_mut().find(|run| run.info.run_id == run_id) {
+        update(&mut run.info);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::start::{node::StartNode, runner::StartRunnerFactory};
+    use crate::node::time_wait::{node::TimeWaitNode, runner::TimeWaitRunnerFactory};
+    use crate::notification::emitter::Emitter;
+    use crate::schema::workflow::WorkflowSchema;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    struct RecordingEmitter {
+        events: Arc<Mutex<Vec<Value>>>,
+    }
+
+    impl Emitter for RecordingEmitter {
+        fn emit(&self, _event: &str, payload: Value) -> Result<(), String> {
+            self.events.lock().map_err(|e| e.to_string())?.push(payload);
+            Ok(())
+        }
+    }
+
+    fn manager(events: Arc<Mutex<Vec<Value>>>) -> WorkflowManager {
+        let mut bus = NodeRegisterBus::new();
+        bus.register(
+            Box::new(StartNode::new()),
+            Box::new(StartRunnerFactory::new()),
+        );
+        bus.register(
+            Box::new(TimeWaitNode::new()),
+            Box::new(TimeWaitRunnerFactory::new()),
+        );
+        let emitter =
+            NotificationEmitter::new().with_emitter(Box::new(RecordingEmitter { events }));
+        WorkflowManager::new(Arc::new(RwLock::new(bus)), Arc::new(emitter))
+    }
+
+    fn wait_runner(seconds: f64) -> Arc<WorkflowRunner> {
+        let workflow = WorkflowSchema::from_yaml(&format!(
+            r#"
+nodes:
+  - node_id: start
+    action_type: Start
+    name: start
+    position: {{ x: 0, y: 0 }}
+  - node_id: wait
+    action_type: TimeWait
+    name: wait
+    position: {{ x: 0, y: 0 }}
+    input_data:
+      duration: {}
+connections:
+  - from: start
+    to: wait
+"#,
+            seconds
+        ))
+        .unwrap();
+        Arc::new(WorkflowRunner::create(workflow).unwrap())
+    }
+
+    fn context() -> Arc<Context> {
+        #[cfg(feature = "tauri")]
+        let context = Context::new(PathBuf::new(), None);
+
+        #[cfg(not(feature = "tauri"))]
+        let context = Context::new(PathBuf::new());
+
+        Arc::new(context)
+    }
+
+    /// Waits until the run `run_id` took its slot and started.
+    async fn wait_started(manager: &WorkflowManager, run_id: &str) {
+        for _ in 0..200 {
+            if manager.get(run_id).unwrap().started_at.is_some() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("run {run_id} never started");
+    }
+
+    #[tokio::test]
+    async fn manager_queues_runs_over_the_limit() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let manager = manager(events.clone()).with_max_concurrent(1);
+        let runner = wait_runner(0.2);
+
+        let first = manager
+            .start(runner.clone(), context(), HashMap::new())
+            .unwrap();
+        let second = manager.start(runner, context(), HashMap::new()).unwrap();
+        assert_ne!(first, second);
+        wait_started(&manager, &first).await;
+        assert_eq!(manager.get(&first).unwrap().status, RunPhase::Running);
+        assert_eq!(manager.get(&second).unwrap().status, RunPhase::Queued);
+
+        assert_eq!(
+            manager.wait(&first).await.unwrap().status,
+            RunPhase::Succeeded
+        );
+        let second_info = manager.wait(&second).await.unwrap();
+        assert_eq!(second_info.status, RunPhase::Succeeded);
+        assert!(second_info.report.unwrap().is_success());
+        assert!(manager.active().is_empty());
+        assert_eq!(manager.runs().len(), 2);
+
+        // Every event names the run it belongs to.
+        let events = events.lock().unwrap().clone();
+        for run_id in [&first, &second] {
+            let tagged: Vec<&Value> = events
+                .iter()
+                .filter(|event| event["run_id"] == run_id.as_str())
+                .collect();
+            assert!(tagged.iter().any(|event| event["name"] == "wait"));
+        }
+        assert!(events.iter().all(|event| event["run_id"].is_string()));
+
+        manager.clear_finished();
+        assert!(manager.runs().is_empty());
+    }
+
+    #[tokio::test]
+    async fn manager_pauses_and_cancels_runs() {
+        let manager = manager(Default::default()).with_max_concurrent(1);
+        let runner = wait_runner(10.0);
+
+        let running = manager
+            .start(runner.clone(), context(), HashMap::new())
+            .unwrap();
+        let queued = manager.start(runner, context(), HashMap::new()).unwrap();
+        wait_started(&manager, &running).await;
+        manager.pause(&running).unwrap();
+        assert_eq!(manager.get(&running).unwrap().status, RunPhase::Paused);
+        manager.resume(&running).unwrap();
+
+        manager.cancel(&queued).unwrap();
+        let info = manager.wait(&queued).await.unwrap();
+        assert_eq!(info.status, RunPhase::Cancelled);
+        assert!(info.started_at.is_none());
+
+        manager.cancel(&running).unwrap();
+        let info = tokio::time::timeout(Duration::from_secs(2), manager.wait(&running))
+            .await
+            .expect("the run should stop once cancelled")
+            .unwrap();
+        assert_eq!(info.status, RunPhase::Cancelled);
+        assert!(manager.cancel("run-unknown").is_err());
+    }
+}
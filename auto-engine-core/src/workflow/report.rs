@@ -0,0 +1,126 @@
+use crate::workflow::dry_run::DryRunReport;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeStatus {
+    Succeeded,
+    /// Failed after its last attempt. The run may still have gone on over
+    /// an error connection or `err_return: false`.
+    Failed,
+    Skipped,
+    /// Passed over because the node is disabled.
+    Disabled,
+    /// Never started, because the run stopped before reaching it.
+    NotRun,
+}
+
+/// What happened to one node. Nodes inside a loop body get one report per
+/// iteration.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NodeReport {
+    pub node_id: String,
+    pub name: String,
+    pub action_type: String,
+    pub status: NodeStatus,
+    /// Unix time in milliseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    pub attempts: u32,
+    /// Inputs after `${}` references were resolved.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inputs: Option<HashMap<String, Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outputs: Option<HashMap<String, Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skip_reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl NodeReport {
+    pub fn new(node_id: &str, name: &str, action_type: &str, status: NodeStatus) -> Self {
+        Self {
+            node_id: node_id.to_string(),
+            name: name.to_string(),
+            action_type: action_type.to_string(),
+            status,
+            started_at: None,
+            finished_at: None,
+            duration_ms: None,
+            attempts: 0,
+            inputs: None,
+            outputs: None,
+            skip_reason: None,
+            error: None,
+        }
+    }
+
+    pub(crate) fn start(&mut self) {
+        self.started_at = Some(now_ms());
+    }
+
+    pub(crate) fn finish(&mut self, status: NodeStatus) {
+        let finished_at = now_ms();
+        self.status = status;
+        self.finished_at = Some(finished_at);
+        self.duration_ms = self
+            .started_at
+            .map(|started_at| finished_at.saturating_sub(started_at));
+    }
+}
+
+/// Everything a finished run did, in the order nodes finished.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RunReport {
+    pub status: RunStatus,
+    pub started_at: u64,
+    pub finished_at: u64,
+    pub duration_ms: u64,
+    pub nodes: Vec<NodeReport>,
+    /// What the End node returned, empty when the run never reached one.
+    #[serde(default)]
+    pub outputs: HashMap<String, Value>,
+    /// Only set for dry runs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dry_run: Option<DryRunReport>,
+    /// The `Context` values once the run ended.
+    pub context: HashMap<String, Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl RunReport {
+    pub fn is_success(&self) -> bool {
+        self.status == RunStatus::Succeeded
+    }
+
+    /// Reports of `node_id`, one per time it was reached.
+    pub fn node(&self, node_id: &str) -> impl Iterator<Item = &NodeReport> {
+        self.nodes
+            .iter()
+            .filter(move |node| node.node_id == node_id)
+    }
+}
+
+pub(crate) fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default()
+}
@@ -1,4 +1,4 @@
-use std::sync::atomic::AtomicUsize;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use std::{
     collections::{HashMap, HashSet},
@@ -10,148 +10,176 @@ use tokio::time::{Instant, sleep_until};
 use tokio_util::sync::CancellationToken;
 
 use crate::workflow::BoxFuture;
+use crate::workflow::checkpoint::Checkpoint;
+use crate::workflow::control::{DebugAction, DebugCommand, RunControl};
+use crate::workflow::dry_run::{
+    BranchCheck, ConditionCheck, DryRun, DryRunReport, default_references,
+};
+use crate::workflow::graph::{Graph, GraphEdge, NodeIndex};
+use crate::workflow::join::{JoinDecision, JoinState};
+use crate::workflow::report::{NodeReport, NodeStatus, RunReport, RunStatus, now_ms};
+use crate::workflow::variables;
 use crate::{
-    context::Context,
-    event::{NODE_EVENT, NodeEventPayload, WORKFLOW_EVENT, WorkflowEventPayload, WorkflowStatus},
-    notification::emitter::NotificationEmitter,
+    context::{Context, Scope},
+    event::{
+        DEBUG_EVENT, DebugEventPayload, DebugStatus, NODE_EVENT, NodeEventPayload, WORKFLOW_EVENT,
+        WorkflowEventPayload, WorkflowStatus,
+    },
+    node::{end, for_each, start, sub_workflow},
+    notification::emitter::{NotificationEmitter, RedactingEmitter},
     register::bus::NodeRegisterBus,
-    schema::{node::NodeSchema, workflow::WorkflowSchema},
+    schema::{
+        node::NodeSchema,
+        workflow::{Connection, WorkflowSchema},
+    },
+    types::{
+        conditions::{ConditionResult, Conditions},
+        input::validate_inputs,
+        node::{ERROR_OUTPUT, ERROR_PORT, OUTPUT_PORT, resolve_params},
+        retry::RetryPolicy,
+    },
 };
 
-#[derive(Debug, Clone)]
-struct GraphNode {
-    pub node_id: String,
-    pub node_context: NodeSchema,
-    pub next: Vec<Arc<std::sync::RwLock<GraphNode>>>,
-    pub wait_count: Arc<AtomicUsize>,
-}
-
-fn build_graph(
-    node_id: &str,
-    graph_nodes: &HashMap<String, Arc<std::sync::RwLock<GraphNode>>>,
-    edges: &HashMap<String, Vec<String>>,
-    visited: &mut HashSet<String>,
-    visiting: &mut HashSet<String>,
-) -> Result<(), String> {
-    if visited.contains(node_id) {
-        return Ok(());
-    }
-
-    if !visiting.insert(node_id.to_string()) {
-        return Err(format!("workflow contains a cycle at node '{node_id}'"));
-    }
-
-    let Some(next_edges) = edges.get(node_id) else {
-        visited.insert(node_id.to_string());
-        visiting.remove(node_id);
-        return Ok(());
-    };
-
-    let mut next_nodes = vec![];
-    for next_node_id in next_edges.iter() {
-        let rc_node = graph_nodes
-            .get(next_node_id)
-            .ok_or_else(|| format!("connection references missing node '{next_node_id}'"))?
-            .clone();
-
-        {
-            let node_writer = rc_node.write().map_err(|e| e.to_string())?;
-            node_writer
-                .wait_count
-                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+impl GraphEdge {
+    /// Whether a run should continue over this edge once its source node
+    /// finished and picked `port`.
+    async fn accepts(&self, ctx: &Context, port: Option<&str>) -> Result<bool, String> {
+        match self.port.as_deref() {
+            Some(edge_port) if Some(edge_port) != port => return Ok(false),
+            // Edges without a port follow success only.
+            None if port == Some(ERROR_PORT) => return Ok(false),
+            _ => {}
         }
-
-        build_graph(next_node_id, graph_nodes, edges, visited, visiting)?;
-
-        next_nodes.push(rc_node);
-    }
-
-    if let Some(node) = graph_nodes.get(node_id) {
-        let mut node = node.write().unwrap();
-        node.next = next_nodes;
+        if let Some(condition) = &self.condition {
+            return Ok(condition.check(ctx).await?.pass);
+        }
+        Ok(true)
     }
-
-    visiting.remove(node_id);
-    visited.insert(node_id.to_string());
-    Ok(())
 }
 
 #[derive(Debug)]
 pub struct WorkflowRunner {
-    graph: Vec<Arc<std::sync::RwLock<GraphNode>>>,
+    graph: Arc<Graph>,
+    timeout: Option<Duration>,
+    checkpoint: Option<PathBuf>,
+    dry_run: Option<Arc<DryRun>>,
 }
 
 impl WorkflowRunner {
     pub fn create(workflow: WorkflowSchema) -> Result<Self, String> {
-        let mut graph_nodes: HashMap<String, Arc<std::sync::RwLock<GraphNode>>> = HashMap::new();
-        let mut edges: HashMap<String, Vec<String>> = HashMap::new();
-        let mut start_nodes = vec![];
-
-        for node_context in workflow.nodes.into_iter() {
-            let key = node_context.node_id.clone();
-            if node_context.action_type == "Start" {
-                start_nodes.push(key.clone());
-            }
-
-            graph_nodes.insert(
-                key.clone(),
-                Arc::new(std::sync::RwLock::new(GraphNode {
-                    node_id: key,
-                    node_context,
-                    next: vec![],
-                    wait_count: Arc::new(AtomicUsize::new(0)),
-                })),
-            );
-        }
+        let timeout = workflow.timeout_ms.map(Duration::from_millis);
+        Ok(Self {
+            graph: Arc::new(Graph::compile(workflow)?),
+            timeout,
+            checkpoint: None,
+            dry_run: None,
+        })
+    }
 
-        for edge in workflow.connections.into_iter() {
-            for start in start_nodes.iter() {
-                if start == &edge.to {
-                    return Err(
-                        "The [Start] node cannot be used as the next node in the connection."
-                            .to_string(),
-                    );
-                }
-            }
+    /// Runs only the control flow: nodes with side effects return simulated
+    /// outputs, see [`DryRun`]. Dry runs write no checkpoints and add a
+    /// [`DryRunReport`] to the run report.
+    pub fn with_dry_run(mut self, dry_run: DryRun) -> Self {
+        self.dry_run = Some(Arc::new(dry_run));
+        self
+    }
 
-            if !graph_nodes.contains_key(&edge.from) {
-                return Err(format!(
-                    "connection references missing node '{}'",
-                    edge.from
-                ));
-            }
-            if !graph_nodes.contains_key(&edge.to) {
-                return Err(format!("connection references missing node '{}'", edge.to));
-            }
+    /// Writes a [`Checkpoint`] to `path` after every node that succeeds. The
+    /// file is removed once a run succeeds, [`Self::resume`] continues a run
+    /// that did not.
+    pub fn with_checkpoint(mut self, path: PathBuf) -> Self {
+        self.checkpoint = Some(path);
+        self
+    }
 
-            let entry = edges.entry(edge.from).or_default();
-            entry.push(edge.to);
-        }
+    /// Runs the workflow with `inputs` for its Start node and reports what
+    /// every node did. A run that fails or is cancelled still returns its
+    /// report, `Err` means it never started, e.g. because of invalid inputs.
+    pub async fn run(
+        &self,
+        ctx: Arc<Context>,
+        inputs: HashMap<String, serde_json::Value>,
+        token: CancellationToken,
+        bus: Arc<RwLock<NodeRegisterBus>>,
+        emitter: Arc<NotificationEmitter>,
+    ) -> Result<RunReport, String> {
+        self.run_with_control(ctx, inputs, RunControl::from_token(token), bus, emitter)
+            .await
+    }
 
-        if start_nodes.is_empty() {
-            return Err("workflow missing Start node".to_string());
-        }
+    /// Runs the workflow while `control` can pause, resume, step or cancel it.
+    pub async fn run_with_control(
+        &self,
+        ctx: Arc<Context>,
+        inputs: HashMap<String, serde_json::Value>,
+        control: RunControl,
+        bus: Arc<RwLock<NodeRegisterBus>>,
+        emitter: Arc<NotificationEmitter>,
+    ) -> Result<RunReport, String> {
+        let values = variables::resolve(self.graph.variables(), &inputs, variables::env)?;
+        variables::seed(&ctx, &values).await?;
+        let progress = Checkpoint {
+            inputs: self.start_inputs(&inputs)?,
+            ..Default::default()
+        };
+        self.execute(ctx, progress, control, bus, emitter).await
+    }
 
-        let mut graph: Vec<Arc<std::sync::RwLock<GraphNode>>> = vec![];
-        let mut visited = HashSet::new();
-        let mut visiting = HashSet::new();
-        for key in start_nodes.iter() {
-            build_graph(key, &graph_nodes, &edges, &mut visited, &mut visiting)?;
-            if let Some(node) = graph_nodes.get(key) {
-                graph.push(node.clone());
-            }
-        }
+    /// Continues the run the checkpoint file set with
+    /// [`Self::with_checkpoint`] was written by. Nodes that already succeeded
+    /// are not run again, their outputs and the `Context` are restored.
+    pub async fn resume(
+        &self,
+        ctx: Arc<Context>,
+        token: CancellationToken,
+        bus: Arc<RwLock<NodeRegisterBus>>,
+        emitter: Arc<NotificationEmitter>,
+    ) -> Result<RunReport, String> {
+        self.resume_with_control(ctx, RunControl::from_token(token), bus, emitter)
+            .await
+    }
 
-        Ok(Self { graph })
+    /// [`Self::resume`] while `control` can pause, resume, step or cancel
+    /// the run.
+    pub async fn resume_with_control(
+        &self,
+        ctx: Arc<Context>,
+        control: RunControl,
+        bus: Arc<RwLock<NodeRegisterBus>>,
+        emitter: Arc<NotificationEmitter>,
+    ) -> Result<RunReport, String> {
+        let path = self
+            .checkpoint
+            .as_ref()
+            .ok_or_else(|| "No checkpoint file to resume from".to_string())?;
+        let mut checkpoint = Checkpoint::load(path)?;
+        log::info!(
+            "resuming workflow, {} nodes already succeeded",
+            checkpoint.completed.len()
+        );
+        *ctx.string_value.write().await = std::mem::take(&mut checkpoint.context);
+        self.execute(ctx, checkpoint, control, bus, emitter).await
     }
 
-    pub async fn run(
+    /// Runs the workflow, skipping what `progress` says already happened.
+    async fn execute(
         &self,
         ctx: Arc<Context>,
-        token: CancellationToken,
+        progress: Checkpoint,
+        control: RunControl,
         bus: Arc<RwLock<NodeRegisterBus>>,
         emitter: Arc<NotificationEmitter>,
-    ) -> Result<Option<HashMap<String, serde_json::Value>>, String> {
+    ) -> Result<RunReport, String> {
+        let emitter = if ctx.secrets().is_empty() {
+            emitter
+        } else {
+            Arc::new(
+                NotificationEmitter::new().with_emitter(Box::new(RedactingEmitter::new(
+                    ctx.secrets.clone(),
+                    emitter,
+                ))),
+            )
+        };
         emitter.clone().emit(
             WORKFLOW_EVENT,
             WorkflowEventPayload {
@@ -159,7 +187,28 @@ impl WorkflowRunner {
             },
         )?;
 
-        let result = handle_nod(self.graph.clone(), ctx, token, bus, emitter.clone()).await;
+        let started_at = now_ms();
+        let control_events = control.forward_events(emitter.clone());
+        let state = Arc::new(RunState {
+            graph: self.graph.clone(),
+            ctx: ctx.clone(),
+            control: control.clone(),
+            bus,
+            emitter: emitter.clone(),
+            workflows: vec![],
+            inputs: progress.inputs,
+            joins: JoinState::default(),
+            reports: Default::default(),
+            outputs: Default::default(),
+            checkpoint: self.checkpoint.clone().filter(|_| self.dry_run.is_none()),
+            completed: std::sync::Mutex::new(progress.completed),
+            loops: std::sync::Mutex::new(progress.loops),
+            checkpoint_write: Default::default(),
+            dry_run: self.dry_run.clone(),
+            dry_run_report: Default::default(),
+        });
+        let result = run_graph(self.timeout, state.clone()).await;
+        control_events.abort();
 
         log::info!("workflow finished");
         emitter
@@ -174,46 +223,312 @@ impl WorkflowRunner {
             .emit(NODE_EVENT, NodeEventPayload::cancel())
             .unwrap_or_default();
 
-        log::info!("workflow finished, the result: {:?}", result);
+        log::info!(
+            "workflow finished, the result: {}",
+            ctx.secrets().redact(&format!("{:?}", result))
+        );
+
+        let mut nodes = std::mem::take(&mut *state.reports.lock().map_err(|e| e.to_string())?);
+        nodes.extend(not_run(&self.graph, &nodes));
+        let result = match unhandled_failure(&self.graph, &nodes) {
+            Some(failed) if result.is_ok() => Err(failed
+                .error
+                .clone()
+                .unwrap_or_else(|| format!("node {} failed", failed.node_id))),
+            _ => result,
+        };
+        let status = if control.is_cancelled() {
+            RunStatus::Cancelled
+        } else if result.is_err() {
+            RunStatus::Failed
+        } else {
+            RunStatus::Succeeded
+        };
+        if status == RunStatus::Succeeded
+            && let Some(path) = &state.checkpoint
+            && path.exists()
+        {
+            std::fs::remove_file(path).unwrap_or_else(|e| {
+                log::warn!("failed to remove checkpoint {}: {}", path.display(), e)
+            });
+        }
+        let finished_at = now_ms();
+        let report = RunReport {
+            status,
+            started_at,
+            finished_at,
+            duration_ms: finished_at.saturating_sub(started_at),
+            nodes,
+            outputs: state.take_outputs()?,
+            dry_run: match &state.dry_run {
+                Some(_) => Some(std::mem::take(
+                    &mut *state.dry_run_report.lock().map_err(|e| e.to_string())?,
+                )),
+                None => None,
+            },
+            context: ctx.string_value.read().await.clone(),
+            error: result.err(),
+        };
+        ctx.secrets().redact_serialized(report)
+    }
+
+    /// Validates `inputs` against what every Start node declares and returns
+    /// the values each of them starts with.
+    fn start_inputs(
+        &self,
+        inputs: &HashMap<String, serde_json::Value>,
+    ) -> Result<HashMap<String, HashMap<String, serde_json::Value>>, String> {
+        let mut start_inputs = HashMap::new();
+        for index in self.graph.starts() {
+            let node = self.graph.node(*index);
+            let input_data = node.node_context.input_data.clone().unwrap_or_default();
+            let declared = start::node::parse_inputs(&input_data)?;
+            start_inputs.insert(node.node_id.clone(), validate_inputs(&declared, inputs)?);
+        }
+        Ok(start_inputs)
+    }
+}
+
+/// Everything a single run shares between its branches.
+struct RunState {
+    graph: Arc<Graph>,
+    ctx: Arc<Context>,
+    control: RunControl,
+    bus: Arc<RwLock<NodeRegisterBus>>,
+    emitter: Arc<NotificationEmitter>,
+    /// Files of the sub workflows this run is nested in, outermost first.
+    workflows: Vec<PathBuf>,
+    /// Validated inputs of every Start node, by node id.
+    inputs: HashMap<String, HashMap<String, serde_json::Value>>,
+    joins: JoinState,
+    /// Nodes that finished or were skipped, in that order.
+    reports: std::sync::Mutex<Vec<NodeReport>>,
+    /// Set by the End node, the last one reached wins.
+    outputs: std::sync::Mutex<HashMap<String, serde_json::Value>>,
+    /// Where the progress of the run is written, if anywhere.
+    checkpoint: Option<PathBuf>,
+    /// Outputs of the nodes that succeeded, by node id. Nodes found here
+    /// when they are reached are not run again.
+    completed: std::sync::Mutex<HashMap<String, Option<HashMap<String, serde_json::Value>>>>,
+    /// The iteration every running ForEach node is in, by node id.
+    loops: std::sync::Mutex<HashMap<String, usize>>,
+    /// Keeps branches that finish together from writing the checkpoint at
+    /// the same time.
+    checkpoint_write: tokio::sync::Mutex<()>,
+    dry_run: Option<Arc<DryRun>>,
+    dry_run_report: std::sync::Mutex<DryRunReport>,
+}
+
+impl RunState {
+    fn record(&self, report: NodeReport) -> Result<(), String> {
+        self.reports.lock().map_err(|e| e.to_string())?.push(report);
+        Ok(())
+    }
+
+    fn restored(
+        &self,
+        node_id: &str,
+    ) -> Result<Option<Option<HashMap<String, serde_json::Value>>>, String> {
+        let completed = self.completed.lock().map_err(|e| e.to_string())?;
+        Ok(completed.get(node_id).cloned())
+    }
+
+    /// Marks `node_id` as succeeded and writes the checkpoint with the
+    /// values seen from `ctx`.
+    async fn complete(
+        &self,
+        ctx: &Context,
+        node_id: &str,
+        outputs: Option<HashMap<String, serde_json::Value>>,
+    ) -> Result<(), String> {
+        let Some(path) = &self.checkpoint else {
+            return Ok(());
+        };
+        let _write = self.checkpoint_write.lock().await;
+        let completed = {
+            let mut completed = self.completed.lock().map_err(|e| e.to_string())?;
+            completed.insert(node_id.to_string(), outputs);
+            completed.clone()
+        };
+        let loops = self.loops.lock().map_err(|e| e.to_string())?.clone();
+        let checkpoint = Checkpoint {
+            completed,
+            inputs: self.inputs.clone(),
+            loops,
+            // Values of the sibling branches still running are saved too.
+            context: ctx.snapshot().await,
+        };
+        // Secrets are never written to disk.
+        ctx.secrets().redact_serialized(checkpoint)?.save(path)
+    }
+
+    /// Forgets that `node_ids` succeeded, so they run again, as the body of
+    /// a loop does.
+    fn forget<'a>(&self, node_ids: impl IntoIterator<Item = &'a String>) -> Result<(), String> {
+        let mut completed = self.completed.lock().map_err(|e| e.to_string())?;
+        for node_id in node_ids {
+            completed.remove(node_id);
+        }
+        Ok(())
+    }
+
+    /// The iteration the ForEach node `node_id` is in, if it is running.
+    fn iteration(&self, node_id: &str) -> Result<Option<usize>, String> {
+        let loops = self.loops.lock().map_err(|e| e.to_string())?;
+        Ok(loops.get(node_id).copied())
+    }
+
+    fn set_iteration(&self, node_id: &str, index: Option<usize>) -> Result<(), String> {
+        let mut loops = self.loops.lock().map_err(|e| e.to_string())?;
+        match index {
+            Some(index) => loops.insert(node_id.to_string(), index),
+            None => loops.remove(node_id),
+        };
+        Ok(())
+    }
+
+    /// Adds to the dry run report, if this is a dry run.
+    fn observe(&self, update: impl FnOnce(&mut DryRunReport)) -> Result<(), String> {
+        if self.dry_run.is_some() {
+            update(&mut *self.dry_run_report.lock().map_err(|e| e.to_string())?);
+        }
+        Ok(())
+    }
+
+    fn take_outputs(&self) -> Result<HashMap<String, serde_json::Value>, String> {
+        let mut outputs = self.outputs.lock().map_err(|e| e.to_string())?;
+        Ok(std::mem::take(&mut *outputs))
+    }
+}
+
+/// A node reached over an edge that was either taken (`live`) or ruled out.
+struct Arrival {
+    node: NodeIndex,
+    live: bool,
+}
+
+impl Arrival {
+    fn live(node: NodeIndex) -> Self {
+        Self { node, live: true }
+    }
 
-        result
+    fn dead(node: NodeIndex) -> Self {
+        Self { node, live: false }
     }
 }
 
 type WorkflowResult = Result<Option<HashMap<String, serde_json::Value>>, String>;
 
+/// Runs the graph of `state` to completion, giving up once `timeout`
+/// elapsed. Dropping the graph future aborts every node that is still
+/// running.
+async fn run_graph(timeout: Option<Duration>, state: Arc<RunState>) -> WorkflowResult {
+    let starts = state
+        .graph
+        .starts()
+        .iter()
+        .copied()
+        .map(Arrival::live)
+        .collect();
+    let Some(timeout) = timeout else {
+        return handle_nod(starts, state.ctx.clone(), state).await;
+    };
+
+    let emitter = state.emitter.clone();
+    let ctx = state.ctx.clone();
+    match tokio::time::timeout(timeout, handle_nod(starts, ctx, state)).await {
+        Ok(result) => result,
+        Err(_) => {
+            emitter
+                .emit(NODE_EVENT, NodeEventPayload::timeout("*".to_string()))
+                .unwrap_or_default();
+            Err(format!(
+                "Workflow timed out after {} ms",
+                timeout.as_millis()
+            ))
+        }
+    }
+}
+
+/// Stands in for a node in a dry run: sets `outputs` as `ctx.<name>.*` as
+/// the node would have.
+async fn simulate(
+    ctx: &Context,
+    node_name: &str,
+    outputs: &HashMap<String, serde_json::Value>,
+) -> WorkflowResult {
+    for (name, value) in outputs.iter() {
+        ctx.set_value(format!("ctx.{}.{}", node_name, name).as_str(), value)
+            .await?;
+    }
+    Ok(Some(outputs.clone()))
+}
+
+/// Runs one attempt of a node. An attempt that outlives `timeout` is
+/// dropped, reported with a `timeout` node event and counts as a failure.
+async fn run_attempt(
+    future: impl Future<Output = WorkflowResult>,
+    timeout: Option<Duration>,
+    node_id: &str,
+    attempt: u32,
+    emitter: &NotificationEmitter,
+) -> WorkflowResult {
+    let Some(timeout) = timeout else {
+        return future.await;
+    };
+
+    match tokio::time::timeout(timeout, future).await {
+        Ok(result) => result,
+        Err(_) => {
+            emitter
+                .emit(
+                    NODE_EVENT,
+                    NodeEventPayload::timeout(node_id.to_string()).with_attempt(attempt),
+                )
+                .unwrap_or_default();
+            Err(format!(
+                "Node {} timed out after {} ms",
+                node_id,
+                timeout.as_millis()
+            ))
+        }
+    }
+}
+
+/// Runs the nodes of `arrivals` in the scope `ctx`. When there are several,
+/// each of them starts a branch with a scope of its own, merged into `ctx`
+/// once the branch is done.
 fn handle_nod(
-    graph: Vec<Arc<std::sync::RwLock<GraphNode>>>,
+    arrivals: Vec<Arrival>,
     ctx: Arc<Context>,
-    token: CancellationToken,
-    bus: Arc<RwLock<NodeRegisterBus>>,
-    emitter: Arc<NotificationEmitter>,
+    state: Arc<RunState>,
 ) -> BoxFuture<WorkflowResult> {
     Box::pin(async move {
         let mut tasks: JoinSet<Result<Option<HashMap<String, serde_json::Value>>, String>> =
             JoinSet::new();
-        for node in graph.iter() {
-            let token_clone = token.clone();
-            let token = token.clone();
-            let bus = bus.clone();
-            let ctx = ctx.clone();
-            let emitter_clone = emitter.clone();
-            let emitter = emitter.clone();
-
-            let (node_id, node_schema, next_node, wait_count) = {
-                let node_read = node.read().map_err(|e| e.to_string())?;
-                (
-                    node_read.node_id.clone(),
-                    node_read.node_context.clone(),
-                    node_read.next.clone(),
-                    node_read.wait_count.clone(),
-                )
+        let forked = arrivals.len() > 1;
+        for Arrival { node: index, live } in arrivals.into_iter() {
+            let token = state.control.token();
+            let state = state.clone();
+            let ctx = if forked {
+                Arc::new(ctx.scoped(Scope::Branch))
+            } else {
+                ctx.clone()
             };
+            let branch = forked.then(|| ctx.clone());
+            let emitter_clone = state.emitter.clone();
+            let emitter = state.emitter.clone();
+
+            let graph = state.graph.clone();
+            let graph_node = graph.node(index);
+            let node_id = graph_node.node_id.clone();
+            let node_schema = graph_node.node_context.clone();
+            let incoming = graph_node.incoming;
 
             let action = node_schema.action_type.clone();
 
             let (node, mut runner) = {
-                let locked_bus = bus.read().await;
+                let locked_bus = state.bus.read().await;
                 let node = match locked_bus.load_node(&action) {
                     None => {
                         return Err(format!("Can't find node : {}", action));
@@ -228,142 +543,356 @@ fn handle_nod(
                 };
                 (node, runner)
             };
-            let run_input = node_schema.input_data.clone().unwrap_or_default();
-            let retry = node_schema.metadata.retry.unwrap_or(0);
+            let mut run_input = node_schema.input_data.clone().unwrap_or_default();
+            if let Some(inputs) = state.inputs.get(&node_id) {
+                let params = run_input
+                    .entry("params".to_string())
+                    .or_insert_with(|| serde_json::Value::Object(Default::default()));
+                if !params.is_object() {
+                    *params = serde_json::Value::Object(Default::default());
+                }
+                if let Some(params) = params.as_object_mut() {
+                    params.extend(inputs.clone());
+                }
+            }
+            // Loops check this after every iteration, so it must not be
+            // resolved together with the other inputs.
+            let break_condition = run_input
+                .get("break_condition")
+                .and_then(|condition| condition.as_str())
+                .filter(|condition| !condition.trim().is_empty())
+                .map(str::to_string);
+            let retry_policy = RetryPolicy::from_metadata(&node_schema.metadata);
+            let stop_on_error = node_schema.metadata.err_return.unwrap_or(true);
+            let join_mode = node_schema.metadata.join.unwrap_or_default();
+            let disabled = node_schema.metadata.disabled;
             let node_name = node_schema.metadata.name;
-            let delay = node_schema.metadata.duration.unwrap_or(0) as u64;
+            let timeout = node_schema.metadata.timeout_ms.map(Duration::from_millis);
 
             let handle = async move {
-                emitter
-                    .emit(NODE_EVENT, NodeEventPayload::running(node_id.clone()))
-                    .unwrap_or_default();
-
-                log::info!(
-                    "wait_count {} {}",
-                    action,
-                    wait_count.load(std::sync::atomic::Ordering::SeqCst)
-                );
+                let next_node = &graph.node(index).next;
+                let mut report =
+                    NodeReport::new(&node_id, &node_name, &action, NodeStatus::Skipped);
+                // The branch that gets to run the join sees what the others
+                // set.
+                if incoming > 1 {
+                    ctx.publish_branches().await;
+                }
+                match state.joins.arrive(&node_id, incoming, join_mode, live)? {
+                    JoinDecision::Wait => {
+                        log::info!("node {} waits for its other branches", node_id);
+                        emitter
+                            .emit(NODE_EVENT, NodeEventPayload::waiting(node_id.clone()))
+                            .unwrap_or_default();
+                        return Ok(None);
+                    }
+                    JoinDecision::Decided => {
+                        log::info!("node {} was decided before this branch arrived", node_id);
+                        return Ok(None);
+                    }
+                    JoinDecision::Skip => {
+                        let reason = ConditionResult {
+                            pass: false,
+                            reason: Some("no upstream branch reached this node".to_string()),
+                        };
+                        report.skip_reason = reason.reason.clone();
+                        state.record(report)?;
+                        emitter
+                            .emit(
+                                NODE_EVENT,
+                                NodeEventPayload::skip(node_id.clone(), Some(reason)),
+                            )
+                            .unwrap_or_default();
+                        return skip_branches(next_node, ctx, state).await;
+                    }
+                    JoinDecision::Run => {}
+                }
 
-                if wait_count.load(std::sync::atomic::Ordering::SeqCst) > 1 {
-                    wait_count.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
-                    log::info!(
-                        "waiting for {}",
-                        wait_count.load(std::sync::atomic::Ordering::SeqCst)
-                    );
-                    emitter
-                        .emit(NODE_EVENT, NodeEventPayload::waiting(node_id.clone()))
-                        .unwrap_or_default();
+                // Pausing takes effect here, between nodes, so nothing is
+                // interrupted half way through an action.
+                if !state.control.wait_turn().await {
+                    return Ok(None);
+                }
+                // A disabled node does not stop at breakpoints either.
+                let skipped = !disabled
+                    && debug_break(&state, &ctx, &node_id).await? == Some(DebugAction::Skip);
+                if state.control.is_cancelled() {
                     return Ok(None);
                 }
+                report.start();
+
+                let node_event = if disabled {
+                    log::info!("node {} is disabled", node_id);
+                    NodeEventPayload::disabled(node_id.clone())
+                } else if skipped {
+                    let reason = ConditionResult {
+                        pass: false,
+                        reason: Some(DEBUG_SKIP_REASON.to_string()),
+                    };
+                    NodeEventPayload::skip(node_id.clone(), Some(reason))
+                } else {
+                    NodeEventPayload::running(node_id.clone()).with_attempt(1)
+                };
+                emitter.emit(NODE_EVENT, node_event).unwrap_or_default();
 
-                if let Some(condition) = node_schema.metadata.conditions {
+                let bypassed = skipped || disabled;
+                if let Some(condition) = node_schema.metadata.conditions.filter(|_| !bypassed) {
                     let result = condition.check(&ctx).await?;
+                    state.observe(|dry_run| {
+                        dry_run.conditions.push(ConditionCheck {
+                            node_id: node_id.clone(),
+                            passed: result.pass,
+                            reason: result.reason.clone(),
+                        })
+                    })?;
                     if !result.pass {
+                        report.skip_reason = Some(
+                            result
+                                .reason
+                                .clone()
+                                .unwrap_or_else(|| "condition not met".to_string()),
+                        );
+                        report.finish(NodeStatus::Skipped);
+                        state.record(report)?;
                         emitter
                             .emit(
                                 NODE_EVENT,
                                 NodeEventPayload::skip(node_id.clone(), Some(result)),
                             )
                             .unwrap_or_default();
-                        return Ok(None);
+                        return skip_branches(next_node, ctx, state).await;
                     }
                 }
 
-                let mut result: Result<Option<HashMap<String, serde_json::Value>>, String> =
-                    Ok(None);
-                if retry <= -1 {
-                    // Infinite retry with a minimum interval between attempts.
-                    let min_interval = Duration::from_millis(200);
-                    let mut next_tick = Instant::now();
-                    loop {
-                        match runner
-                            .run(
-                                &ctx,
-                                &node_name,
-                                run_input.clone(),
-                                node.input_schema().clone(),
-                            )
-                            .await
-                        {
+                let mut attempt = 0;
+                let outcome = if bypassed {
+                    Ok(None)
+                } else if let Some(outputs) = state.restored(&node_id)? {
+                    log::info!("node {} already succeeded before the checkpoint", node_id);
+                    for (name, value) in outputs.iter().flatten() {
+                        ctx.set_value(format!("ctx.{}.{}", node_name, name).as_str(), value)
+                            .await?;
+                    }
+                    emitter
+                        .emit(
+                            NODE_EVENT,
+                            NodeEventPayload::success(node_id.clone(), outputs.clone()),
+                        )
+                        .unwrap_or_default();
+                    Ok(outputs)
+                } else {
+                    let simulated = match &state.dry_run {
+                        Some(dry_run) => {
+                            let references = default_references(&ctx, &node_id, &run_input).await;
+                            state.observe(|report| report.defaults.extend(references))?;
+                            dry_run.outputs(&node_id, &node_name, &**node, run_input.clone())
+                        }
+                        None => None,
+                    };
+                    // Resolved once, every attempt runs with the same params.
+                    let resolved =
+                        resolve_params(&ctx, run_input.clone(), &node.input_schema()).await;
+                    report.inputs = Some(resolved.clone().unwrap_or_else(|_| run_input.clone()));
+                    let started = Instant::now();
+                    attempt = 1;
+                    let outcome: WorkflowResult = loop {
+                        if attempt > 1 {
+                            emitter
+                                .emit(
+                                    NODE_EVENT,
+                                    NodeEventPayload::running(node_id.clone())
+                                        .with_attempt(attempt),
+                                )
+                                .unwrap_or_default();
+                        }
+
+                        let result = match &simulated {
+                            Some(outputs) => simulate(&ctx, &node_name, outputs).await,
+                            None => match &resolved {
+                                Ok(params) => {
+                                    let future =
+                                        runner.run_resolved(&ctx, &node_name, params.clone());
+                                    run_attempt(future, timeout, &node_id, attempt, &emitter).await
+                                }
+                                Err(err) => Err(err.clone()),
+                            },
+                        };
+                        match result {
                             Ok(res) => {
                                 emitter
                                     .emit(
                                         NODE_EVENT,
-                                        NodeEventPayload::success(node_id.clone(), res.clone()),
+                                        NodeEventPayload::success(node_id.clone(), res.clone())
+                                            .with_attempt(attempt),
                                     )
                                     .unwrap_or_default();
-                                result = Ok(res);
-                                break;
+                                break Ok(res);
                             }
-                            Err(_) => {
-                                next_tick += min_interval;
-                                let now = Instant::now();
-                                if next_tick > now {
-                                    sleep_until(next_tick).await;
-                                } else {
-                                    next_tick = now;
+                            Err(err) => {
+                                let err = ctx.secrets().redact(&err);
+                                if !retry_policy.should_retry(attempt, started.elapsed(), &err) {
+                                    break Err(err);
                                 }
+                                log::warn!(
+                                    "node {} failed on attempt {}: {}",
+                                    node_id,
+                                    attempt,
+                                    err
+                                );
+                                tokio::time::sleep(retry_policy.delay(attempt)).await;
+                                attempt += 1;
                             }
-                        };
-                    }
-                } else {
-                    let mut last_err = None;
-                    // Total attempts = 1 (initial) + retry.
-                    for attempt in 0..=retry {
-                        match runner
-                            .run(
-                                &ctx,
+                        }
+                    };
+                    log::info!("handle finished {}", action);
+
+                    match outcome {
+                        Ok(outputs) if action == for_each::node::NODE_TYPE => {
+                            let items =
+                                match outputs.as_ref().and_then(|outputs| outputs.get("items")) {
+                                    Some(serde_json::Value::Array(items)) => items.clone(),
+                                    _ => vec![],
+                                };
+                            run_for_each(
+                                &node_id,
                                 &node_name,
-                                run_input.clone(),
-                                node.input_schema().clone(),
+                                items,
+                                break_condition,
+                                next_node,
+                                ctx.clone(),
+                                state.clone(),
                             )
                             .await
+                            .map(|_| outputs)
+                        }
+                        Ok(Some(resolved))
+                            if action == sub_workflow::node::NODE_TYPE && simulated.is_none() =>
                         {
-                            Ok(res) => {
-                                emitter
-                                    .emit(
-                                        NODE_EVENT,
-                                        NodeEventPayload::success(node_id.clone(), res.clone()),
-                                    )
-                                    .unwrap_or_default();
-                                last_err = None;
-                                result = Ok(res);
-                                break;
-                            }
-                            Err(e) => {
-                                last_err = Some(e);
-                                if attempt < retry {
-                                    tokio::time::sleep(Duration::from_millis(delay)).await;
-                                }
-                            }
+                            run_sub_workflow(&node_name, &resolved, &ctx, state.clone())
+                                .await
+                                .map(Some)
+                        }
+                        outcome => outcome,
+                    }
+                };
+
+                report.attempts = attempt;
+                match &outcome {
+                    Ok(_) if disabled => report.finish(NodeStatus::Disabled),
+                    Ok(_) if skipped => {
+                        report.skip_reason = Some(DEBUG_SKIP_REASON.to_string());
+                        report.finish(NodeStatus::Skipped);
+                    }
+                    Ok(outputs) => {
+                        if action == end::node::NODE_TYPE {
+                            *state.outputs.lock().map_err(|e| e.to_string())? =
+                                outputs.clone().unwrap_or_default();
                         }
+                        report.outputs = outputs.clone();
+                        report.finish(NodeStatus::Succeeded);
+                    }
+                    Err(err) => {
+                        report.error = Some(err.clone());
+                        report.finish(NodeStatus::Failed);
                     }
+                }
+                state.record(report)?;
+                if let Ok(outputs) = &outcome {
+                    state.complete(&ctx, &node_id, outputs.clone()).await?;
+                }
 
-                    if let Some(err) = last_err {
+                let (result, port) = match outcome {
+                    Ok(outputs) => {
+                        let port = outputs
+                            .as_ref()
+                            .and_then(|outputs| outputs.get(OUTPUT_PORT))
+                            .and_then(|port| port.as_str())
+                            .map(str::to_string);
+                        // A disabled loop goes on as if it had no items.
+                        let port = port.or_else(|| {
+                            (disabled && action == for_each::node::NODE_TYPE)
+                                .then(|| for_each::node::PORT_DONE.to_string())
+                        });
+                        (Ok(outputs), port)
+                    }
+                    Err(err) => {
                         emitter
                             .emit(
                                 NODE_EVENT,
-                                NodeEventPayload::error::<String>(node_id.clone(), None),
+                                NodeEventPayload::error(node_id.clone(), Some(err.clone()))
+                                    .with_attempt(attempt),
                             )
                             .unwrap_or_default();
-                        return Err(err);
+                        let error_key = format!("ctx.{}.{}", node_name, ERROR_OUTPUT);
+                        ctx.set_value(&error_key, &err).await?;
+
+                        // A failure with an error handler attached is handled
+                        // there, otherwise `err_return: false` keeps going as
+                        // if the node succeeded without output.
+                        let has_handler = next_node
+                            .iter()
+                            .any(|edge| edge.port.as_deref() == Some(ERROR_PORT));
+                        if has_handler {
+                            log::warn!(
+                                "node {} failed, running its error handler: {}",
+                                node_id,
+                                err
+                            );
+                            (Ok(None), Some(ERROR_PORT.to_string()))
+                        } else if !stop_on_error {
+                            log::warn!("node {} failed, continuing: {}", node_id, err);
+                            (Ok(None), None)
+                        } else {
+                            return Err(err);
+                        }
+                    }
+                };
+
+                let mut next_nodes = vec![];
+                for edge in next_node.iter() {
+                    // The loop body already ran, it is not a branch of its own.
+                    if action == for_each::node::NODE_TYPE
+                        && edge.port.as_deref() == Some(for_each::node::PORT_BODY)
+                    {
+                        continue;
+                    }
+                    let live = edge.accepts(&ctx, port.as_deref()).await?;
+                    if state.dry_run.is_some() {
+                        let to = graph.node(edge.to).node_id.clone();
+                        state.observe(|dry_run| {
+                            dry_run.branches.push(BranchCheck {
+                                from: node_id.clone(),
+                                to,
+                                port: edge.port.clone(),
+                                taken: live,
+                            })
+                        })?;
                     }
+                    next_nodes.push(Arrival {
+                        node: edge.to,
+                        live,
+                    });
                 }
-                log::info!("handle finished {}", action);
-                if next_node.is_empty() {
+
+                if next_nodes.is_empty() {
                     return result;
                 }
-                handle_nod(next_node, ctx, token, bus, emitter).await
+                handle_nod(next_nodes, ctx, state).await
             };
 
             tasks.spawn(async move {
                 tokio::select! {
-                    _ = token_clone.cancelled() => {
+                    _ = token.cancelled() => {
                         log::info!("Pipeline terminated, exiting loop");
                         emitter_clone.emit(NODE_EVENT, NodeEventPayload::cancel()).unwrap_or_default();
                         Ok(None)
                     },
-                    result = handle => result
+                    result = handle => {
+                        if let Some(branch) = branch {
+                            branch.close(&[]).await;
+                        }
+                        result
+                    }
                 }
             });
         }
@@ -371,77 +900,397 @@ fn handle_nod(
         let mut final_result = Ok(None);
 
         while let Some(res) = tasks.join_next().await {
-            if let Err(e) = res {
-                return Err(e.to_string());
-            }
-            if let Ok(result) = res {
-                final_result = result;
+            let result = res.map_err(|e| e.to_string()).and_then(|result| result);
+            if result.is_err() {
+                // The run failed, the other branches must not go on with
+                // their actions.
+                tasks.abort_all();
+                return result;
             }
+            final_result = result;
         }
 
         final_result
     })
 }
 
-pub async fn handle_retry<F, Fut, R>(
-    retry: i32,
-    delay_ms: u64,
-    min_interval: u64,
-    mut handle: F,
-) -> Result<R, String>
-where
-    F: FnMut() -> Fut,
-    Fut: Future<Output = Result<R, String>>,
-{
-    let mut last_err = match handle().await {
-        Ok(result) => return Ok(result),
-        Err(err) => Some(err),
-    };
-
-    let min_interval = Duration::from_millis(min_interval);
-    let mut next_tick = Instant::now();
-
-    if retry <= -1 {
-        loop {
-            match handle().await {
-                Ok(res) => return Ok(res),
-                Err(err) => {
-                    log::error!("{}", err);
-                    next_tick += min_interval;
+/// Runs the `body` port of a ForEach node once per item, each iteration in
+/// a scope of its own, and returns how many iterations ran. A resumed loop
+/// starts with the iteration its checkpoint was written in.
+async fn run_for_each(
+    node_id: &str,
+    node_name: &str,
+    items: Vec<serde_json::Value>,
+    break_condition: Option<String>,
+    edges: &[GraphEdge],
+    ctx: Arc<Context>,
+    state: Arc<RunState>,
+) -> Result<usize, String> {
+    let break_condition = break_condition.map(|condition| Conditions {
+        exist: None,
+        condition: Some(condition),
+        not_exist: None,
+    });
+
+    let resumed_at = state.iteration(node_id)?;
+    let mut iterations = 0;
+    let mut last = None;
+    let result = async {
+        for (index, item) in items.iter().enumerate() {
+            if state.control.is_cancelled() {
+                break;
+            }
+            if resumed_at.is_some_and(|at| index < at) {
+                iterations += 1;
+                continue;
+            }
 
-                    let now = Instant::now();
-                    if next_tick > now {
-                        sleep_until(next_tick).await;
-                    } else {
-                        next_tick = now;
-                    }
-                }
+            // An iteration does not see what the one before it set.
+            let scope = Arc::new(ctx.scoped(Scope::Iteration));
+            last = Some(scope.clone());
+            scope.set_value(for_each::node::LOOP_INDEX, index).await?;
+            scope.set_value(for_each::node::LOOP_ITEM, item).await?;
+            scope
+                .set_value(format!("ctx.{}.index", node_name).as_str(), index)
+                .await?;
+            scope
+                .set_value(format!("ctx.{}.item", node_name).as_str(), item)
+                .await?;
+
+            let mut body = vec![];
+            for edge in edges
+                .iter()
+                .filter(|edge| edge.port.as_deref() == Some(for_each::node::PORT_BODY))
+            {
+                body.push(Arrival {
+                    node: edge.to,
+                    live: edge
+                        .accepts(&scope, Some(for_each::node::PORT_BODY))
+                        .await?,
+                });
             }
-        }
-    } else {
-        for attempt in 0..retry {
-            match handle().await {
-                Ok(res) => return Ok(res),
-                Err(err) => {
-                    last_err = Some(err);
-                    if attempt + 1 < retry {
-                        tokio::time::sleep(Duration::from_millis(delay_ms)).await;
-                    }
-                }
+            let body_nodes: Vec<_> = body.iter().map(|arrival| arrival.node).collect();
+            let body_ids = reachable_ids(&state.graph, &body_nodes);
+            state.joins.reset(&body_ids)?;
+            // What succeeded in the iteration the checkpoint was written in
+            // is replayed, not run again.
+            if resumed_at != Some(index) {
+                state.forget(&body_ids)?;
+            }
+            state.set_iteration(node_id, Some(index))?;
+            handle_nod(body, scope.clone(), state.clone()).await?;
+            iterations += 1;
+
+            if let Some(condition) = &break_condition
+                && condition.check(&scope).await?.pass
+            {
+                log::info!("loop {} stopped by its break condition", node_name);
+                break;
             }
         }
+        Ok::<(), String>(())
     }
+    .await;
+
+    // What the last iteration set is still seen after the loop, an outer
+    // loop sees its own loop variables again.
+    if let Some(scope) = last {
+        scope
+            .close(&[for_each::node::LOOP_INDEX, for_each::node::LOOP_ITEM])
+            .await;
+    }
+    result?;
+    state.set_iteration(node_id, None)?;
+    ctx.set_value(format!("ctx.{}.iterations", node_name).as_str(), iterations)
+        .await?;
 
-    Err(last_err.unwrap_or_else(|| "retry failed, unknown error".to_string()))
+    Ok(iterations)
 }
 
-#[cfg(test)]
-#[allow(deprecated)]
+/// Runs the workflow a SubWorkflow node resolved in a scope nested in `ctx`
+/// and copies the selected variables back as `ctx.<name>.*`.
+async fn run_sub_workflow(
+    node_name: &str,
+    resolved: &HashMap<String, serde_json::Value>,
+    ctx: &Context,
+    state: Arc<RunState>,
+) -> Result<HashMap<String, serde_json::Value>, String> {
+    let path = resolved
+        .get("workflow")
+        .and_then(|path| path.as_str())
+        .ok_or_else(|| "Sub workflow file is required".to_string())?;
+    let path = Path::new(path)
+        .canonicalize()
+        .map_err(|e| format!("Sub workflow {} not found: {}", path, e))?;
+    if state.workflows.contains(&path) {
+        return Err(format!("Sub workflow {} calls itself", path.display()));
+    }
+
+    let runner = WorkflowRunner::create(WorkflowSchema::from_file(&path)?)?;
+    let inputs = match resolved.get("inputs") {
+        Some(serde_json::Value::Object(inputs)) => inputs.clone().into_iter().collect(),
+        _ => HashMap::new(),
+    };
+    let values = variables::resolve(runner.graph.variables(), &inputs, variables::env)?;
+    let inputs = runner.start_inputs(&inputs)?;
+
+    let workflow_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let child_ctx = Arc::new(ctx.sub_workflow(workflow_dir));
+    variables::seed(&child_ctx, &values).await?;
+    let mut workflows = state.workflows.clone();
+    workflows.push(path.clone());
+    // Node ids of the sub workflow mean nothing to whoever listens to this
+    // run, so its node events are not forwarded.
+    let child = Arc::new(RunState {
+        graph: runner.graph.clone(),
+        ctx: child_ctx.clone(),
+        control: state.control.clone(),
+        bus: state.bus.clone(),
+        emitter: Arc::new(NotificationEmitter::new()),
+        workflows,
+        inputs,
+        joins: JoinState::default(),
+        reports: Default::default(),
+        outputs: Default::default(),
+        // A resumed run starts its sub workflows over.
+        checkpoint: None,
+        completed: Default::default(),
+        loops: Default::default(),
+        checkpoint_write: Default::default(),
+        dry_run: state.dry_run.clone(),
+        dry_run_report: Default::default(),
+    });
+
+    log::info!("running sub workflow {}", path.display());
+    run_graph(runner.timeout, child.clone()).await?;
+
+    let mut outputs = HashMap::new();
+    outputs.insert(
+        "workflow".to_string(),
+        serde_json::Value::String(path.to_string_lossy().to_string()),
+    );
+    let mut selected = vec![];
+    if let Some(serde_json::Value::Object(aliases)) = resolved.get("outputs") {
+        for (alias, key) in aliases.iter() {
+            let Some(key) = key.as_str() else {
+                continue;
+            };
+            let value = child_ctx.get_value(key).await.unwrap_or_else(|| {
+                log::warn!("sub workflow variable {} not found", key);
+                serde_json::Value::Null
+            });
+            selected.push((alias.clone(), value));
+        }
+    }
+    // Without a selection the sub workflow returns what its End node
+    // declares.
+    if selected.is_empty() {
+        selected.extend(child.take_outputs()?);
+    }
+    for (name, value) in selected {
+        ctx.set_value(format!("ctx.{}.{}", node_name, name).as_str(), &value)
+            .await?;
+        outputs.insert(name, value);
+    }
+
+    Ok(outputs)
+}
+
+/// Ids of every node reachable from `nodes`, `nodes` included.
+fn reachable_ids(graph: &Graph, nodes: &[NodeIndex]) -> HashSet<String> {
+    graph
+        .reachable_from(nodes)
+        .into_iter()
+        .map(|index| graph.node(index).node_id.clone())
+        .collect()
+}
+
+/// The first node in `reports` that failed with neither an error port
+/// connection nor `err_return: false` to handle it.
+fn unhandled_failure<'a>(graph: &Graph, reports: &'a [NodeReport]) -> Option<&'a NodeReport> {
+    reports
+        .iter()
+        .filter(|report| report.status == NodeStatus::Failed)
+        .find(|report| {
+            let Some(index) = graph.index_of(&report.node_id) else {
+                return true;
+            };
+            let node = graph.node(index);
+            node.node_context.metadata.err_return != Some(false)
+                && !node
+                    .next
+                    .iter()
+                    .any(|edge| edge.port.as_deref() == Some(ERROR_PORT))
+        })
+}
+
+/// `NotRun` reports for the nodes reachable from a Start node that have
+/// none in `reports`.
+fn not_run(graph: &Graph, reports: &[NodeReport]) -> Vec<NodeReport> {
+    let reported: HashSet<&str> = reports
+        .iter()
+        .map(|report| report.node_id.as_str())
+        .collect();
+    graph
+        .reachable_from(graph.starts())
+        .into_iter()
+        .map(|index| graph.node(index))
+        .filter(|node| !reported.contains(node.node_id.as_str()))
+        .map(|node| {
+            NodeReport::new(
+                &node.node_id,
+                &node.node_context.metadata.name,
+                &node.node_context.action_type,
+                NodeStatus::NotRun,
+            )
+        })
+        .collect()
+}
+
+const DEBUG_SKIP_REASON: &str = "skipped in the debugger";
+
+/// Halts before `node_id` when a breakpoint or a step asks for it, and waits
+/// until the debugger lets the run go on. `None` when the run did not halt
+/// or was cancelled while halted.
+async fn debug_break(
+    state: &RunState,
+    ctx: &Context,
+    node_id: &str,
+) -> Result<Option<DebugAction>, String> {
+    let control = &state.control;
+    if !control.should_halt(node_id, ctx).await? {
+        return Ok(None);
+    }
+    log::info!("halted before node {}", node_id);
+    let mut commands = control.halt(node_id);
+    let token = control.token();
+    let emit = |status: DebugStatus, context: Option<HashMap<String, serde_json::Value>>| {
+        state
+            .emitter
+            .emit(
+                DEBUG_EVENT,
+                DebugEventPayload {
+                    status,
+                    node_id: node_id.to_string(),
+                    context,
+                },
+            )
+            .unwrap_or_default();
+    };
+
+    loop {
+        let context = ctx.values().await;
+        emit(DebugStatus::Halted, Some(context));
+        let command = tokio::select! {
+            _ = token.cancelled() => None,
+            command = commands.recv() => command,
+        };
+        match command {
+            Some(DebugCommand::SetValue { key, value }) => {
+                ctx.set_value(&key, value).await?;
+            }
+            Some(DebugCommand::Resume(action)) => {
+                control.release(node_id, action);
+                emit(
+                    match action {
+                        DebugAction::Continue => DebugStatus::Continued,
+                        DebugAction::StepOver => DebugStatus::SteppedOver,
+                        DebugAction::Skip => DebugStatus::Skipped,
+                    },
+                    None,
+                );
+                return Ok(Some(action));
+            }
+            None => {
+                control.release(node_id, DebugAction::Continue);
+                return Ok(None);
+            }
+        }
+    }
+}
+
+/// Reports every outgoing edge of a node that did not run as not taken, so
+/// joins further down stop waiting for it.
+async fn skip_branches(
+    edges: &[GraphEdge],
+    ctx: Arc<Context>,
+    state: Arc<RunState>,
+) -> WorkflowResult {
+    if edges.is_empty() {
+        return Ok(None);
+    }
+    let dead = edges.iter().map(|edge| Arrival::dead(edge.to)).collect();
+    handle_nod(dead, ctx, state).await
+}
+
+pub async fn handle_retry<F, Fut, R>(
+    retry: i32,
+    delay_ms: u64,
+    min_interval: u64,
+    mut handle: F,
+) -> Result<R, String>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<R, String>>,
+{
+    let mut last_err = match handle().await {
+        Ok(result) => return Ok(result),
+        Err(err) => Some(err),
+    };
+
+    let min_interval = Duration::from_millis(min_interval);
+    let mut next_tick = Instant::now();
+
+    if retry <= -1 {
+        loop {
+            match handle().await {
+                Ok(res) => return Ok(res),
+                Err(err) => {
+                    log::error!("{}", err);
+                    next_tick += min_interval;
+
+                    let now = Instant::now();
+                    if next_tick > now {
+                        sleep_until(next_tick).await;
+                    } else {
+                        next_tick = now;
+                    }
+                }
+            }
+        }
+    } else {
+        for attempt in 0..retry {
+            match handle().await {
+                Ok(res) => return Ok(res),
+                Err(err) => {
+                    last_err = Some(err);
+                    if attempt + 1 < retry {
+                        tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+                    }
+                }
+            }
+        }
+    }
+
+    Err(last_err.unwrap_or_else(|| "retry failed, unknown error".to_string()))
+}
+
+#[cfg(test)]
+#[allow(deprecated)]
 mod tests {
     use super::*;
+    use crate::node::branch::{node::BranchNode, runner::BranchRunnerFactory};
+    use crate::node::end::{node::EndNode, runner::EndRunnerFactory};
+    use crate::node::for_each::{node::ForEachNode, runner::ForEachRunnerFactory};
     use crate::node::start::{node::StartNode, runner::StartRunnerFactory};
+    use crate::node::sub_workflow::{node::SubWorkflowNode, runner::SubWorkflowRunnerFactory};
+    use crate::node::time_wait::{node::TimeWaitNode, runner::TimeWaitRunnerFactory};
+    use crate::notification::emitter::Emitter;
+    use crate::secret::{REDACTED, SecretStore};
     use crate::types::field::SchemaField;
+    use crate::types::join::JoinMode;
     use crate::types::node::{NodeRunnerControl, NodeRunnerController};
+    use crate::workflow::control::Breakpoint;
     use crate::{
         register::bus::NodeRegisterBus,
         schema::{node::Position, workflow::Connection},
@@ -467,6 +1316,10 @@ mod tests {
             interval: None,
             conditions: None,
             err_return: None,
+            timeout_ms: None,
+            retry_policy: None,
+            join: None,
+            disabled: false,
         }
     }
 
@@ -586,7 +1439,10 @@ mod tests {
             connections: vec![Connection {
                 from: "node-0".to_string(),
                 to: "node-1".to_string(),
+                port: None,
+                condition: None,
             }],
+            ..Default::default()
         };
 
         let runner = WorkflowRunner::create(workflow).expect("workflow should be valid");
@@ -619,6 +1475,7 @@ mod tests {
         runner
             .run(
                 ctx,
+                HashMap::new(),
                 CancellationToken::new(),
                 Arc::new(RwLock::new(bus)),
                 Arc::new(emitter),
@@ -635,6 +1492,2031 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn run_with_control_pauses_and_steps_between_nodes() {
+        let workflow = WorkflowSchema {
+            nodes: vec![
+                NodeSchema {
+                    node_id: "node-0".to_string(),
+                    action_type: "Start".to_string(),
+                    metadata: metadata("start"),
+                    params: None,
+                    input_data: None,
+                    position: Position::default(),
+                    icon: None,
+                    type_define: None,
+                },
+                NodeSchema {
+                    node_id: "node-1".to_string(),
+                    action_type: "Custom".to_string(),
+                    metadata: metadata("custom-1"),
+                    params: None,
+                    input_data: None,
+                    position: Position::default(),
+                    icon: None,
+                    type_define: None,
+                },
+                NodeSchema {
+                    node_id: "node-2".to_string(),
+                    action_type: "Custom".to_string(),
+                    metadata: metadata("custom-2"),
+                    params: None,
+                    input_data: None,
+                    position: Position::default(),
+                    icon: None,
+                    type_define: None,
+                },
+            ],
+            connections: vec![
+                Connection {
+                    from: "node-0".to_string(),
+                    to: "node-1".to_string(),
+                    port: None,
+                    condition: None,
+                },
+                Connection {
+                    from: "node-1".to_string(),
+                    to: "node-2".to_string(),
+                    port: None,
+                    condition: None,
+                },
+            ],
+            ..Default::default()
+        };
+
+        let runner = WorkflowRunner::create(workflow).expect("workflow should be valid");
+        let counter = Arc::new(AtomicUsize::new(0));
+
+        let mut bus = NodeRegisterBus::new();
+        bus.register(
+            Box::new(StartNode::new()),
+            Box::new(StartRunnerFactory::new()),
+        );
+        bus.register(
+            Box::new(TestNodeDefine),
+            Box::new(TestRunnerFactory::new(
+                Arc::clone(&counter),
+                Arc::new(Mutex::new(None)),
+            )),
+        );
+
+        #[cfg(feature = "tauri")]
+        let context = Context::new(PathBuf::new(), None);
+
+        #[cfg(not(feature = "tauri"))]
+        let context = Context::new(PathBuf::new());
+
+        let control = RunControl::new();
+        control.pause();
+
+        let handle = {
+            let control = control.clone();
+            tokio::spawn(async move {
+                runner
+                    .run_with_control(
+                        Arc::new(context),
+                        HashMap::new(),
+                        control,
+                        Arc::new(RwLock::new(bus)),
+                        Arc::new(NotificationEmitter::new()),
+                    )
+                    .await
+            })
+        };
+
+        let wait_for = |expected: usize| {
+            let counter = Arc::clone(&counter);
+            async move {
+                for _ in 0..100 {
+                    if counter.load(Ordering::SeqCst) == expected {
+                        return;
+                    }
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+                panic!("counter never reached {expected}");
+            }
+        };
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(
+            counter.load(Ordering::SeqCst),
+            0,
+            "paused run must not start"
+        );
+
+        // The first step runs the Start node, the second one the first custom node.
+        control.step();
+        control.step();
+        wait_for(1).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(
+            counter.load(Ordering::SeqCst),
+            1,
+            "run should stop after the step"
+        );
+        assert!(control.is_paused());
+
+        control.resume();
+        handle
+            .await
+            .expect("run task panicked")
+            .expect("workflow should run successfully");
+        assert_eq!(counter.load(Ordering::SeqCst), 2);
+    }
+
+    fn node_schema(
+        node_id: &str,
+        action_type: &str,
+        name: &str,
+        input_data: Option<HashMap<String, serde_json::Value>>,
+    ) -> NodeSchema {
+        NodeSchema {
+            node_id: node_id.to_string(),
+            action_type: action_type.to_string(),
+            metadata: metadata(name),
+            params: None,
+            input_data,
+            position: Position::default(),
+            icon: None,
+            type_define: None,
+        }
+    }
+
+    fn connection(from: &str, to: &str, port: Option<&str>) -> Connection {
+        Connection {
+            from: from.to_string(),
+            to: to.to_string(),
+            port: port.map(str::to_string),
+            condition: None,
+        }
+    }
+
+    async fn run_counting(workflow: WorkflowSchema) -> (usize, Option<JsonValue>) {
+        #[cfg(feature = "tauri")]
+        let context = Context::new(PathBuf::new(), None);
+
+        #[cfg(not(feature = "tauri"))]
+        let context = Context::new(PathBuf::new());
+
+        run_counting_in(workflow, Arc::new(context)).await
+    }
+
+    async fn run_counting_in(
+        workflow: WorkflowSchema,
+        ctx: Arc<Context>,
+    ) -> (usize, Option<JsonValue>) {
+        let runner = WorkflowRunner::create(workflow).expect("workflow should be valid");
+        let counter = Arc::new(AtomicUsize::new(0));
+        let params = Arc::new(Mutex::new(None));
+        let bus = counting_bus(Arc::clone(&counter), Arc::clone(&params));
+
+        let report = runner
+            .run(
+                ctx,
+                HashMap::new(),
+                CancellationToken::new(),
+                Arc::new(RwLock::new(bus)),
+                Arc::new(NotificationEmitter::new()),
+            )
+            .await
+            .expect("workflow should start");
+        assert!(report.is_success(), "workflow failed: {:?}", report.error);
+
+        let stored_params = params.lock().expect("lock params failed").clone();
+        (counter.load(Ordering::SeqCst), stored_params)
+    }
+
+    #[tokio::test]
+    async fn run_checks_node_conditions() {
+        let workflow = |condition: &str| {
+            let mut custom = node_schema("node-1", "Custom", "custom", None);
+            custom.metadata.conditions = Some(Conditions {
+                exist: Some("ctx.start.value".to_string()),
+                condition: Some(condition.to_string()),
+                not_exist: Some("ctx.start.missing".to_string()),
+            });
+            WorkflowSchema {
+                nodes: vec![
+                    node_schema(
+                        "node-0",
+                        "Start",
+                        "start",
+                        Some(HashMap::from([(
+                            "params".to_string(),
+                            serde_json::json!({"value": 3}),
+                        )])),
+                    ),
+                    custom,
+                ],
+                connections: vec![connection("node-0", "node-1", None)],
+                ..Default::default()
+            }
+        };
+
+        let (count, _) = run_counting(workflow("${ctx.start.value} > 1")).await;
+        assert_eq!(count, 1, "the conditions hold, the node should run");
+        let (count, _) = run_counting(workflow("${ctx.start.value} > 5")).await;
+        assert_eq!(count, 0, "the condition fails, the node should be skipped");
+    }
+
+    #[tokio::test]
+    async fn run_halts_at_breakpoints_for_the_debugger() {
+        let workflow = WorkflowSchema {
+            nodes: vec![
+                node_schema("node-0", "Start", "start", None),
+                node_schema("node-1", "Custom", "first", None),
+                node_schema("node-2", "Custom", "second", None),
+                node_schema("node-3", "Custom", "third", None),
+            ],
+            connections: vec![
+                connection("node-0", "node-1", None),
+                connection("node-1", "node-2", None),
+                connection("node-2", "node-3", None),
+            ],
+            ..Default::default()
+        };
+        let runner = WorkflowRunner::create(workflow).expect("workflow should be valid");
+        let counter = Arc::new(AtomicUsize::new(0));
+        let bus = counting_bus(Arc::clone(&counter), Arc::new(Mutex::new(None)));
+
+        #[cfg(feature = "tauri")]
+        let context = Context::new(PathBuf::new(), None);
+
+        #[cfg(not(feature = "tauri"))]
+        let context = Context::new(PathBuf::new());
+
+        let control = RunControl::new();
+        control.set_breakpoint(Breakpoint {
+            node_id: "node-1".to_string(),
+            condition: None,
+        });
+        control.set_breakpoint(Breakpoint {
+            node_id: "node-3".to_string(),
+            condition: Some(Conditions {
+                exist: Some("ctx.debug.flag".to_string()),
+                condition: None,
+                not_exist: None,
+            }),
+        });
+
+        let handle = {
+            let control = control.clone();
+            tokio::spawn(async move {
+                runner
+                    .run_with_control(
+                        Arc::new(context),
+                        HashMap::new(),
+                        control,
+                        Arc::new(RwLock::new(bus)),
+                        Arc::new(NotificationEmitter::new()),
+                    )
+                    .await
+            })
+        };
+        let halted_at = |node_id: &'static str| {
+            let control = control.clone();
+            async move {
+                for _ in 0..100 {
+                    if control.halted() == vec![node_id.to_string()] {
+                        return;
+                    }
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+                panic!("the run never halted before {node_id}");
+            }
+        };
+
+        halted_at("node-1").await;
+        assert_eq!(counter.load(Ordering::SeqCst), 0);
+        assert!(control.is_paused());
+        control
+            .set_value("node-1", "ctx.debug.flag", serde_json::json!(true))
+            .unwrap();
+        control.debug("node-1", DebugAction::StepOver).unwrap();
+
+        halted_at("node-2").await;
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+        control.debug("node-2", DebugAction::Skip).unwrap();
+
+        // The condition of the breakpoint holds since the value was set.
+        halted_at("node-3").await;
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+        control.debug("node-3", DebugAction::Continue).unwrap();
+
+        let report = handle
+            .await
+            .expect("run task panicked")
+            .expect("workflow should start");
+        assert!(report.is_success());
+        assert_eq!(counter.load(Ordering::SeqCst), 2);
+        let skipped = report.node("node-2").next().unwrap();
+        assert_eq!(skipped.status, NodeStatus::Skipped);
+        assert_eq!(skipped.skip_reason.as_deref(), Some(DEBUG_SKIP_REASON));
+        assert_eq!(report.context["ctx.debug.flag"], serde_json::json!(true));
+        assert!(control.debug("node-3", DebugAction::Continue).is_err());
+    }
+
+    #[tokio::test]
+    async fn releasing_a_breakpoint_keeps_a_manual_pause() {
+        let workflow = WorkflowSchema {
+            nodes: vec![
+                node_schema("node-0", "Start", "start", None),
+                node_schema("node-1", "Custom", "first", None),
+                node_schema("node-2", "Custom", "second", None),
+            ],
+            connections: vec![
+                connection("node-0", "node-1", None),
+                connection("node-1", "node-2", None),
+            ],
+            ..Default::default()
+        };
+        let runner = WorkflowRunner::create(workflow).expect("workflow should be valid");
+        let counter = Arc::new(AtomicUsize::new(0));
+        let bus = counting_bus(Arc::clone(&counter), Arc::new(Mutex::new(None)));
+
+        let control = RunControl::new();
+        control.set_breakpoint(Breakpoint {
+            node_id: "node-1".to_string(),
+            condition: None,
+        });
+        let handle = {
+            let control = control.clone();
+            tokio::spawn(async move {
+                runner
+                    .run_with_control(
+                        test_context(),
+                        HashMap::new(),
+                        control,
+                        Arc::new(RwLock::new(bus)),
+                        Arc::new(NotificationEmitter::new()),
+                    )
+                    .await
+            })
+        };
+        for _ in 0..100 {
+            if !control.halted().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(control.halted(), vec!["node-1".to_string()]);
+
+        control.pause();
+        control.debug("node-1", DebugAction::Continue).unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(
+            counter.load(Ordering::SeqCst),
+            1,
+            "the released node runs, the next one waits for the pause"
+        );
+        assert!(control.is_paused());
+
+        control.resume();
+        let report = handle
+            .await
+            .expect("run task panicked")
+            .expect("workflow should start");
+        assert!(report.is_success());
+        assert_eq!(counter.load(Ordering::SeqCst), 2);
+    }
+
+    /// A bus with the control nodes, `TimeWait` and a `Custom` node that
+    /// counts its runs.
+    fn counting_bus(
+        counter: Arc<AtomicUsize>,
+        params: Arc<Mutex<Option<JsonValue>>>,
+    ) -> NodeRegisterBus {
+        let mut bus = NodeRegisterBus::new();
+        bus.register(
+            Box::new(StartNode::new()),
+            Box::new(StartRunnerFactory::new()),
+        );
+        bus.register(
+            Box::new(BranchNode::new()),
+            Box::new(BranchRunnerFactory::new()),
+        );
+        bus.register(
+            Box::new(ForEachNode::new()),
+            Box::new(ForEachRunnerFactory::new()),
+        );
+        bus.register(
+            Box::new(SubWorkflowNode::new()),
+            Box::new(SubWorkflowRunnerFactory::new()),
+        );
+        bus.register(
+            Box::new(TimeWaitNode::new()),
+            Box::new(TimeWaitRunnerFactory::new()),
+        );
+        bus.register(Box::new(EndNode::new()), Box::new(EndRunnerFactory::new()));
+        bus.register(
+            Box::new(TestNodeDefine),
+            Box::new(TestRunnerFactory::new(counter, params)),
+        );
+        bus
+    }
+
+    #[tokio::test]
+    async fn run_follows_only_the_selected_port() {
+        let start_params = HashMap::from([("params".to_string(), serde_json::json!({"value": 3}))]);
+        let workflow = WorkflowSchema {
+            nodes: vec![
+                node_schema("node-0", "Start", "start", Some(start_params)),
+                node_schema(
+                    "node-1",
+                    "Branch",
+                    "branch",
+                    Some(HashMap::from([(
+                        "condition".to_string(),
+                        serde_json::json!("${ctx.start.value} > 2"),
+                    )])),
+                ),
+                node_schema(
+                    "node-2",
+                    "Custom",
+                    "yes",
+                    Some(HashMap::from([(
+                        "branch".to_string(),
+                        serde_json::json!("yes"),
+                    )])),
+                ),
+                node_schema(
+                    "node-3",
+                    "Custom",
+                    "no",
+                    Some(HashMap::from([(
+                        "branch".to_string(),
+                        serde_json::json!("no"),
+                    )])),
+                ),
+            ],
+            connections: vec![
+                connection("node-0", "node-1", None),
+                connection("node-1", "node-2", Some("true")),
+                connection("node-1", "node-3", Some("false")),
+            ],
+            ..Default::default()
+        };
+
+        let (count, params) = run_counting(workflow).await;
+        assert_eq!(count, 1, "only one branch should run");
+        assert_eq!(params, Some(serde_json::json!({"branch": "yes"})));
+    }
+
+    #[tokio::test]
+    async fn run_skips_edges_whose_condition_fails() {
+        let start_params = HashMap::from([("params".to_string(), serde_json::json!({"value": 3}))]);
+        let mut guarded = connection("node-0", "node-1", None);
+        guarded.condition = Some(Conditions {
+            exist: None,
+            condition: Some("${ctx.start.value} > 5".to_string()),
+            not_exist: None,
+        });
+        let workflow = WorkflowSchema {
+            nodes: vec![
+                node_schema("node-0", "Start", "start", Some(start_params)),
+                node_schema("node-1", "Custom", "guarded", None),
+                node_schema("node-2", "Custom", "open", None),
+            ],
+            connections: vec![guarded, connection("node-0", "node-2", None)],
+            ..Default::default()
+        };
+
+        let (count, _) = run_counting(workflow).await;
+        assert_eq!(count, 1, "the guarded edge must not be followed");
+    }
+
+    fn for_each_workflow(loop_input: HashMap<String, serde_json::Value>) -> WorkflowSchema {
+        WorkflowSchema {
+            nodes: vec![
+                node_schema("node-0", "Start", "start", None),
+                node_schema("node-1", "ForEach", "loop", Some(loop_input)),
+                node_schema("node-2", "Custom", "body", None),
+                node_schema("node-3", "Custom", "after", None),
+            ],
+            connections: vec![
+                connection("node-0", "node-1", None),
+                connection("node-1", "node-2", Some("body")),
+                connection("node-1", "node-3", Some("done")),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn run_for_each_repeats_the_body_then_continues() {
+        let workflow = for_each_workflow(HashMap::from([(
+            "items".to_string(),
+            serde_json::json!(r#"["a", "b", "c"]"#),
+        )]));
+
+        let (count, _) = run_counting(workflow).await;
+        assert_eq!(count, 4, "three body runs plus the done branch");
+    }
+
+    #[tokio::test]
+    async fn run_for_each_stops_on_break_condition_and_max_iterations() {
+        let workflow = for_each_workflow(HashMap::from([
+            ("count".to_string(), serde_json::json!("10")),
+            (
+                "break_condition".to_string(),
+                serde_json::json!("${loop.index} >= 1"),
+            ),
+        ]));
+        let (count, _) = run_counting(workflow).await;
+        assert_eq!(count, 3, "two body runs plus the done branch");
+
+        let workflow = for_each_workflow(HashMap::from([
+            ("count".to_string(), serde_json::json!("10")),
+            ("max_iterations".to_string(), serde_json::json!("4")),
+        ]));
+        let (count, _) = run_counting(workflow).await;
+        assert_eq!(count, 5, "the loop is capped by max_iterations");
+    }
+
+    fn sub_workflow_dir(name: &str, child: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("auto-engine-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).expect("create workflow dir");
+        std::fs::write(dir.join("child.yaml"), child).expect("write sub workflow");
+        dir
+    }
+
+    #[tokio::test]
+    async fn run_sub_workflow_maps_inputs_and_outputs() {
+        let dir = sub_workflow_dir(
+            "sub-workflow",
+            r#"
+nodes:
+  - node_id: start
+    action_type: Start
+    name: start
+    input_data:
+      params:
+        user: nobody
+        greeting: hello
+  - node_id: custom
+    action_type: Custom
+    name: custom
+connections:
+  - from: start
+    to: custom
+"#,
+        );
+        let workflow = WorkflowSchema {
+            nodes: vec![
+                node_schema(
+                    "node-0",
+                    "Start",
+                    "start",
+                    Some(HashMap::from([(
+                        "params".to_string(),
+                        serde_json::json!({"user": "alice"}),
+                    )])),
+                ),
+                node_schema(
+                    "node-1",
+                    "SubWorkflow",
+                    "login",
+                    Some(HashMap::from([
+                        ("workflow".to_string(), serde_json::json!("child.yaml")),
+                        (
+                            "inputs".to_string(),
+                            serde_json::json!(r#"{"user": "${ctx.start.user}"}"#),
+                        ),
+                        (
+                            "outputs".to_string(),
+                            serde_json::json!("name=ctx.start.user, ctx.start.greeting"),
+                        ),
+                    ])),
+                ),
+            ],
+            connections: vec![connection("node-0", "node-1", None)],
+            ..Default::default()
+        };
+
+        #[cfg(feature = "tauri")]
+        let context = Context::new(dir.clone(), None);
+
+        #[cfg(not(feature = "tauri"))]
+        let context = Context::new(dir.clone());
+
+        let ctx = Arc::new(context);
+        let (count, _) = run_counting_in(workflow, ctx.clone()).await;
+        std::fs::remove_dir_all(&dir).unwrap_or_default();
+
+        assert_eq!(count, 1, "the sub workflow should run its nodes");
+        assert_eq!(
+            ctx.get_value("ctx.login.name").await,
+            Some(serde_json::json!("alice"))
+        );
+        assert_eq!(
+            ctx.get_value("ctx.login.greeting").await,
+            Some(serde_json::json!("hello"))
+        );
+        assert_eq!(
+            ctx.get_value("ctx.custom.user").await,
+            None,
+            "the sub workflow must not write into the parent context"
+        );
+    }
+
+    #[tokio::test]
+    async fn run_returns_the_end_node_outputs() {
+        let dir = sub_workflow_dir(
+            "sub-workflow-end",
+            r#"
+nodes:
+  - node_id: start
+    action_type: Start
+    name: start
+  - node_id: done
+    action_type: End
+    name: done
+    input_data:
+      outputs:
+        token: "token-${ctx.start.user}"
+connections:
+  - from: start
+    to: done
+"#,
+        );
+        let workflow = WorkflowSchema {
+            nodes: vec![
+                node_schema(
+                    "node-0",
+                    "Start",
+                    "start",
+                    Some(HashMap::from([(
+                        "params".to_string(),
+                        serde_json::json!({"user": "alice", "age": 30}),
+                    )])),
+                ),
+                node_schema(
+                    "node-1",
+                    "SubWorkflow",
+                    "login",
+                    Some(HashMap::from([
+                        ("workflow".to_string(), serde_json::json!("child.yaml")),
+                        (
+                            "inputs".to_string(),
+                            serde_json::json!(r#"{"user": "${ctx.start.user}"}"#),
+                        ),
+                    ])),
+                ),
+                node_schema(
+                    "node-2",
+                    "End",
+                    "done",
+                    Some(HashMap::from([(
+                        "outputs".to_string(),
+                        serde_json::json!({
+                            "token": "${ctx.login.token}",
+                            "age": "${ctx.start.age}",
+                        }),
+                    )])),
+                ),
+            ],
+            connections: vec![
+                connection("node-0", "node-1", None),
+                connection("node-1", "node-2", None),
+            ],
+            ..Default::default()
+        };
+        let runner = WorkflowRunner::create(workflow).expect("workflow should be valid");
+        let bus = counting_bus(Arc::new(AtomicUsize::new(0)), Arc::new(Mutex::new(None)));
+
+        #[cfg(feature = "tauri")]
+        let context = Context::new(dir.clone(), None);
+
+        #[cfg(not(feature = "tauri"))]
+        let context = Context::new(dir.clone());
+
+        let report = runner
+            .run(
+                Arc::new(context),
+                HashMap::new(),
+                CancellationToken::new(),
+                Arc::new(RwLock::new(bus)),
+                Arc::new(NotificationEmitter::new()),
+            )
+            .await
+            .expect("workflow should start");
+        std::fs::remove_dir_all(&dir).unwrap_or_default();
+
+        assert!(report.is_success(), "workflow failed: {:?}", report.error);
+        assert_eq!(
+            report.outputs,
+            HashMap::from([
+                ("token".to_string(), serde_json::json!("token-alice")),
+                ("age".to_string(), serde_json::json!(30)),
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn run_validates_the_start_inputs() {
+        let start_input = HashMap::from([
+            (
+                "params".to_string(),
+                serde_json::json!({"greeting": "hello"}),
+            ),
+            (
+                "inputs".to_string(),
+                serde_json::json!([
+                    {"name": "account", "type": "string", "required": true},
+                    {"name": "count", "type": "number", "default": "2"},
+                ]),
+            ),
+        ]);
+        let workflow = WorkflowSchema {
+            nodes: vec![
+                node_schema("node-0", "Start", "start", Some(start_input)),
+                node_schema("node-1", "Custom", "custom", None),
+            ],
+            connections: vec![connection("node-0", "node-1", None)],
+            ..Default::default()
+        };
+        let runner = WorkflowRunner::create(workflow).expect("workflow should be valid");
+        let counter = Arc::new(AtomicUsize::new(0));
+        let bus = Arc::new(RwLock::new(counting_bus(
+            Arc::clone(&counter),
+            Arc::new(Mutex::new(None)),
+        )));
+
+        let err = runner
+            .run(
+                test_context(),
+                HashMap::from([("count".to_string(), serde_json::json!("many"))]),
+                CancellationToken::new(),
+                bus.clone(),
+                Arc::new(NotificationEmitter::new()),
+            )
+            .await
+            .unwrap_err();
+        assert!(err.contains("`account` is required"), "{err}");
+        assert!(err.contains("`count` must be a number"), "{err}");
+        assert_eq!(counter.load(Ordering::SeqCst), 0, "nothing should run");
+
+        let ctx = test_context();
+        let report = runner
+            .run(
+                ctx.clone(),
+                HashMap::from([("account".to_string(), serde_json::json!("bob"))]),
+                CancellationToken::new(),
+                bus,
+                Arc::new(NotificationEmitter::new()),
+            )
+            .await
+            .expect("workflow should start");
+        assert!(report.is_success(), "workflow failed: {:?}", report.error);
+        assert_eq!(
+            ctx.get_value("ctx.start.account").await,
+            Some(serde_json::json!("bob"))
+        );
+        assert_eq!(
+            ctx.get_value("ctx.start.count").await,
+            Some(serde_json::json!(2))
+        );
+        assert_eq!(
+            ctx.get_value("ctx.start.greeting").await,
+            Some(serde_json::json!("hello"))
+        );
+    }
+
+    #[tokio::test]
+    async fn resume_skips_the_nodes_that_already_succeeded() {
+        let path = std::env::temp_dir().join("auto-engine-resume-checkpoint.json");
+        std::fs::remove_file(&path).unwrap_or_default();
+        let workflow = |duration: &str| {
+            let mut wait = node_schema(
+                "node-2",
+                "TimeWait",
+                "wait",
+                Some(HashMap::from([(
+                    "duration".to_string(),
+                    serde_json::json!(duration),
+                )])),
+            );
+            wait.metadata.timeout_ms = Some(20);
+            WorkflowSchema {
+                nodes: vec![
+                    node_schema(
+                        "node-0",
+                        "Start",
+                        "start",
+                        Some(HashMap::from([(
+                            "params".to_string(),
+                            serde_json::json!({"value": 3}),
+                        )])),
+                    ),
+                    node_schema("node-1", "Custom", "before", None),
+                    wait,
+                    node_schema("node-3", "Custom", "after", None),
+                ],
+                connections: vec![
+                    connection("node-0", "node-1", None),
+                    connection("node-1", "node-2", None),
+                    connection("node-2", "node-3", None),
+                ],
+                ..Default::default()
+            }
+        };
+        let counter = Arc::new(AtomicUsize::new(0));
+        let bus = Arc::new(RwLock::new(counting_bus(
+            Arc::clone(&counter),
+            Arc::new(Mutex::new(None)),
+        )));
+
+        let runner = WorkflowRunner::create(workflow("5"))
+            .expect("workflow should be valid")
+            .with_checkpoint(path.clone());
+        let report = runner
+            .run(
+                test_context(),
+                HashMap::new(),
+                CancellationToken::new(),
+                bus.clone(),
+                Arc::new(NotificationEmitter::new()),
+            )
+            .await
+            .expect("workflow should start");
+        assert_eq!(report.status, RunStatus::Failed);
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+
+        let checkpoint = Checkpoint::load(&path).expect("checkpoint should be written");
+        let mut completed: Vec<_> = checkpoint.completed.keys().cloned().collect();
+        completed.sort();
+        assert_eq!(completed, vec!["node-0", "node-1"]);
+
+        let ctx = test_context();
+        let runner = WorkflowRunner::create(workflow("0"))
+            .expect("workflow should be valid")
+            .with_checkpoint(path.clone());
+        let report = runner
+            .resume(
+                ctx.clone(),
+                CancellationToken::new(),
+                bus,
+                Arc::new(NotificationEmitter::new()),
+            )
+            .await
+            .expect("workflow should resume");
+
+        assert!(report.is_success(), "workflow failed: {:?}", report.error);
+        assert_eq!(counter.load(Ordering::SeqCst), 2, "only node-3 should run");
+        let before = report.node("node-1").next().expect("before report");
+        assert_eq!(before.attempts, 0, "node-1 should be restored");
+        assert_eq!(
+            ctx.get_value("ctx.start.value").await,
+            Some(serde_json::json!(3))
+        );
+        assert!(!path.exists(), "a successful run removes its checkpoint");
+    }
+
+    #[tokio::test]
+    async fn resume_continues_a_loop_with_its_last_iteration() {
+        let path = std::env::temp_dir().join("auto-engine-resume-loop-checkpoint.json");
+        std::fs::remove_file(&path).unwrap_or_default();
+        let workflow = |items: &str| {
+            let mut wait = node_schema(
+                "node-3",
+                "TimeWait",
+                "wait",
+                Some(HashMap::from([(
+                    "duration".to_string(),
+                    serde_json::json!("${loop.item}"),
+                )])),
+            );
+            wait.metadata.timeout_ms = Some(20);
+            WorkflowSchema {
+                nodes: vec![
+                    node_schema("node-0", "Start", "start", None),
+                    node_schema(
+                        "node-1",
+                        "ForEach",
+                        "loop",
+                        Some(HashMap::from([(
+                            "items".to_string(),
+                            serde_json::json!(items),
+                        )])),
+                    ),
+                    node_schema("node-2", "Custom", "work", None),
+                    wait,
+                ],
+                connections: vec![
+                    connection("node-0", "node-1", None),
+                    connection("node-1", "node-2", Some(for_each::node::PORT_BODY)),
+                    connection("node-2", "node-3", None),
+                ],
+                ..Default::default()
+            }
+        };
+        let counter = Arc::new(AtomicUsize::new(0));
+        let bus = Arc::new(RwLock::new(counting_bus(
+            Arc::clone(&counter),
+            Arc::new(Mutex::new(None)),
+        )));
+
+        // The second iteration times out after `work` succeeded.
+        let runner = WorkflowRunner::create(workflow("[0, 5, 0]"))
+            .expect("workflow should be valid")
+            .with_checkpoint(path.clone());
+        let report = runner
+            .run(
+                test_context(),
+                HashMap::new(),
+                CancellationToken::new(),
+                bus.clone(),
+                Arc::new(NotificationEmitter::new()),
+            )
+            .await
+            .expect("workflow should start");
+        assert_eq!(report.status, RunStatus::Failed);
+        assert_eq!(counter.load(Ordering::SeqCst), 2);
+        let checkpoint = Checkpoint::load(&path).expect("checkpoint should be written");
+        assert_eq!(checkpoint.loops.get("node-1"), Some(&1));
+
+        let ctx = test_context();
+        let runner = WorkflowRunner::create(workflow("[0, 0, 0]"))
+            .expect("workflow should be valid")
+            .with_checkpoint(path.clone());
+        let report = runner
+            .resume(
+                ctx.clone(),
+                CancellationToken::new(),
+                bus,
+                Arc::new(NotificationEmitter::new()),
+            )
+            .await
+            .expect("workflow should resume");
+
+        assert!(report.is_success(), "workflow failed: {:?}", report.error);
+        assert_eq!(
+            counter.load(Ordering::SeqCst),
+            3,
+            "only the third iteration should run `work`"
+        );
+        assert_eq!(
+            ctx.get_value("ctx.loop.iterations").await,
+            Some(serde_json::json!(3))
+        );
+        assert!(!path.exists(), "a successful run removes its checkpoint");
+    }
+
+    #[tokio::test]
+    async fn resume_keeps_the_values_of_parallel_branches() {
+        let path = std::env::temp_dir().join("auto-engine-resume-branches-checkpoint.json");
+        std::fs::remove_file(&path).unwrap_or_default();
+        let wait = |node_id: &str, name: &str, duration: &str| {
+            let mut wait = node_schema(
+                node_id,
+                "TimeWait",
+                name,
+                Some(HashMap::from([(
+                    "duration".to_string(),
+                    serde_json::json!(duration),
+                )])),
+            );
+            wait.metadata.timeout_ms = Some(100);
+            wait
+        };
+        let workflow = |hold: &str| WorkflowSchema {
+            nodes: vec![
+                node_schema("node-0", "Start", "start", None),
+                node_schema(
+                    "node-1",
+                    "ForEach",
+                    "loop",
+                    Some(HashMap::from([(
+                        "items".to_string(),
+                        serde_json::json!("[1, 2]"),
+                    )])),
+                ),
+                wait("node-2", "pause", "0.03"),
+                wait("node-3", "hold", hold),
+            ],
+            connections: vec![
+                connection("node-0", "node-1", None),
+                connection("node-0", "node-2", None),
+                connection("node-1", "node-3", None),
+            ],
+            ..Default::default()
+        };
+        let bus = Arc::new(RwLock::new(counting_bus(
+            Arc::new(AtomicUsize::new(0)),
+            Arc::new(Mutex::new(None)),
+        )));
+
+        // `pause` saves the last checkpoint while the `loop` branch is still
+        // holding, then `hold` times out.
+        let runner = WorkflowRunner::create(workflow("5"))
+            .expect("workflow should be valid")
+            .with_checkpoint(path.clone());
+        let report = runner
+            .run(
+                test_context(),
+                HashMap::new(),
+                CancellationToken::new(),
+                bus.clone(),
+                Arc::new(NotificationEmitter::new()),
+            )
+            .await
+            .expect("workflow should start");
+        assert_eq!(report.status, RunStatus::Failed);
+        let checkpoint = Checkpoint::load(&path).expect("checkpoint should be written");
+        assert_eq!(
+            checkpoint.context.get("ctx.loop.count"),
+            Some(&serde_json::json!(2))
+        );
+
+        let ctx = test_context();
+        let runner = WorkflowRunner::create(workflow("0"))
+            .expect("workflow should be valid")
+            .with_checkpoint(path.clone());
+        let report = runner
+            .resume(
+                ctx.clone(),
+                CancellationToken::new(),
+                bus,
+                Arc::new(NotificationEmitter::new()),
+            )
+            .await
+            .expect("workflow should resume");
+
+        assert!(report.is_success(), "workflow failed: {:?}", report.error);
+        let restored = report.node("node-1").next().unwrap();
+        assert_eq!(restored.attempts, 0, "the loop branch should be restored");
+        assert_eq!(
+            ctx.get_value("ctx.loop.count").await,
+            Some(serde_json::json!(2))
+        );
+        assert!(!path.exists(), "a successful run removes its checkpoint");
+    }
+
+    #[tokio::test]
+    async fn dry_run_simulates_side_effects_and_reports_the_flow() {
+        let start_params = HashMap::from([("params".to_string(), serde_json::json!({"value": 3}))]);
+        let mut yes = node_schema(
+            "node-3",
+            "Custom",
+            "yes",
+            Some(HashMap::from([(
+                "label".to_string(),
+                serde_json::json!("${ctx.missing.label:none}"),
+            )])),
+        );
+        yes.metadata.conditions = Some(Conditions {
+            exist: Some("ctx.start.value".to_string()),
+            condition: None,
+            not_exist: None,
+        });
+        let workflow = WorkflowSchema {
+            nodes: vec![
+                node_schema("node-0", "Start", "start", Some(start_params)),
+                node_schema(
+                    "node-1",
+                    "TimeWait",
+                    "wait",
+                    Some(HashMap::from([(
+                        "duration".to_string(),
+                        serde_json::json!("5"),
+                    )])),
+                ),
+                node_schema(
+                    "node-2",
+                    "Branch",
+                    "branch",
+                    Some(HashMap::from([(
+                        "condition".to_string(),
+                        serde_json::json!("${ctx.start.value} > 2"),
+                    )])),
+                ),
+                yes,
+                node_schema("node-4", "Custom", "no", None),
+            ],
+            connections: vec![
+                connection("node-0", "node-1", None),
+                connection("node-1", "node-2", None),
+                connection("node-2", "node-3", Some("true")),
+                connection("node-2", "node-4", Some("false")),
+            ],
+            ..Default::default()
+        };
+        let counter = Arc::new(AtomicUsize::new(0));
+        let bus = Arc::new(RwLock::new(counting_bus(
+            Arc::clone(&counter),
+            Arc::new(Mutex::new(None)),
+        )));
+        let dry_run = |dry_run: DryRun| {
+            WorkflowRunner::create(workflow.clone())
+                .expect("workflow should be valid")
+                .with_dry_run(dry_run)
+        };
+
+        let started = Instant::now();
+        let report = dry_run(DryRun::new())
+            .run(
+                test_context(),
+                HashMap::new(),
+                CancellationToken::new(),
+                bus.clone(),
+                Arc::new(NotificationEmitter::new()),
+            )
+            .await
+            .expect("workflow should start");
+        assert!(report.is_success(), "workflow failed: {:?}", report.error);
+        assert!(started.elapsed() < Duration::from_secs(2), "nothing waits");
+        assert_eq!(
+            counter.load(Ordering::SeqCst),
+            0,
+            "custom nodes are simulated"
+        );
+
+        let flow = report.dry_run.expect("dry run report");
+        let branches: Vec<(&str, bool)> = flow
+            .branches
+            .iter()
+            .filter(|branch| branch.from == "node-2")
+            .map(|branch| (branch.to.as_str(), branch.taken))
+            .collect();
+        assert!(branches.contains(&("node-3", true)), "{branches:?}");
+        assert!(branches.contains(&("node-4", false)), "{branches:?}");
+        assert_eq!(flow.conditions.len(), 1);
+        assert!(
+            flow.conditions[0].passed,
+            "the simulated wait sets its outputs"
+        );
+        assert_eq!(flow.defaults.len(), 1);
+        assert_eq!(flow.defaults[0].variable, "ctx.missing.label");
+        assert_eq!(flow.defaults[0].default, "none");
+
+        let report = dry_run(DryRun::new().with_fixture(
+            "branch",
+            HashMap::from([("port".to_string(), serde_json::json!("false"))]),
+        ))
+        .run(
+            test_context(),
+            HashMap::new(),
+            CancellationToken::new(),
+            bus,
+            Arc::new(NotificationEmitter::new()),
+        )
+        .await
+        .expect("workflow should start");
+        assert!(report.is_success(), "workflow failed: {:?}", report.error);
+        assert_eq!(
+            report.node("node-4").next().map(|node| node.status),
+            Some(NodeStatus::Succeeded),
+            "the fixture picks the false branch"
+        );
+        assert_eq!(
+            report.node("node-3").next().map(|node| node.status),
+            Some(NodeStatus::Skipped)
+        );
+    }
+
+    #[tokio::test]
+    async fn run_sub_workflow_refuses_recursion() {
+        let dir = sub_workflow_dir(
+            "sub-workflow-recursion",
+            r#"
+nodes:
+  - node_id: start
+    action_type: Start
+    name: start
+  - node_id: again
+    action_type: SubWorkflow
+    name: again
+    input_data:
+      workflow: child.yaml
+connections:
+  - from: start
+    to: again
+"#,
+        );
+        let workflow =
+            WorkflowSchema::from_file(&dir.join("child.yaml")).expect("workflow should parse");
+        let runner = WorkflowRunner::create(workflow).expect("workflow should be valid");
+        let mut bus = NodeRegisterBus::new();
+        bus.register(
+            Box::new(StartNode::new()),
+            Box::new(StartRunnerFactory::new()),
+        );
+        bus.register(
+            Box::new(SubWorkflowNode::new()),
+            Box::new(SubWorkflowRunnerFactory::new()),
+        );
+
+        #[cfg(feature = "tauri")]
+        let context = Context::new(dir.clone(), None);
+
+        #[cfg(not(feature = "tauri"))]
+        let context = Context::new(dir.clone());
+
+        let report = runner
+            .run(
+                Arc::new(context),
+                HashMap::new(),
+                CancellationToken::new(),
+                Arc::new(RwLock::new(bus)),
+                Arc::new(NotificationEmitter::new()),
+            )
+            .await
+            .expect("workflow should start");
+        std::fs::remove_dir_all(&dir).unwrap_or_default();
+
+        assert_eq!(report.status, RunStatus::Failed);
+        let err = report.error.expect("the run should fail");
+        assert!(
+            err.contains("calls itself"),
+            "unexpected error message: {err}"
+        );
+    }
+
+    struct RecordingEmitter {
+        events: Arc<Mutex<Vec<JsonValue>>>,
+    }
+
+    impl Emitter for RecordingEmitter {
+        fn emit(&self, event: &str, payload: JsonValue) -> Result<(), String> {
+            if event == NODE_EVENT {
+                self.events.lock().map_err(|e| e.to_string())?.push(payload);
+            }
+            Ok(())
+        }
+    }
+
+    /// Runs `workflow` and returns its report together with every node event
+    /// emitted for `node_id`.
+    async fn run_recording(workflow: WorkflowSchema, node_id: &str) -> (RunReport, Vec<JsonValue>) {
+        let runner = WorkflowRunner::create(workflow).expect("workflow should be valid");
+        let events = Arc::new(Mutex::new(vec![]));
+
+        let mut bus = NodeRegisterBus::new();
+        bus.register(
+            Box::new(StartNode::new()),
+            Box::new(StartRunnerFactory::new()),
+        );
+        bus.register(
+            Box::new(TimeWaitNode::new()),
+            Box::new(TimeWaitRunnerFactory::new()),
+        );
+        bus.register(
+            Box::new(TestNodeDefine),
+            Box::new(TestRunnerFactory::new(
+                Arc::new(AtomicUsize::new(0)),
+                Arc::new(Mutex::new(None)),
+            )),
+        );
+
+        #[cfg(feature = "tauri")]
+        let context = Context::new(PathBuf::new(), None);
+
+        #[cfg(not(feature = "tauri"))]
+        let context = Context::new(PathBuf::new());
+
+        let emitter = NotificationEmitter::new().with_emitter(Box::new(RecordingEmitter {
+            events: Arc::clone(&events),
+        }));
+        let report = runner
+            .run(
+                Arc::new(context),
+                HashMap::new(),
+                CancellationToken::new(),
+                Arc::new(RwLock::new(bus)),
+                Arc::new(emitter),
+            )
+            .await
+            .expect("workflow should start");
+
+        let events = events
+            .lock()
+            .expect("lock events failed")
+            .iter()
+            .filter(|event| event["name"] == node_id)
+            .cloned()
+            .collect();
+        (report, events)
+    }
+
+    fn statuses(events: &[JsonValue]) -> Vec<&str> {
+        events
+            .iter()
+            .filter_map(|event| event["status"].as_str())
+            .collect()
+    }
+
+    fn wait_workflow(timeout_ms: Option<u64>) -> WorkflowSchema {
+        let mut wait = node_schema(
+            "node-1",
+            "TimeWait",
+            "wait",
+            Some(HashMap::from([(
+                "duration".to_string(),
+                serde_json::json!("5"),
+            )])),
+        );
+        wait.metadata.timeout_ms = timeout_ms;
+        wait.metadata.retry = Some(1);
+
+        WorkflowSchema {
+            nodes: vec![
+                node_schema("node-0", "Start", "start", None),
+                wait,
+                node_schema("node-2", "Custom", "after", None),
+            ],
+            connections: vec![
+                connection("node-0", "node-1", None),
+                connection("node-1", "node-2", None),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn run_fails_a_node_attempt_after_its_timeout() {
+        let started = Instant::now();
+        let (report, events) = run_recording(wait_workflow(Some(50)), "node-1").await;
+
+        let err = report.error.expect("the run should fail");
+        assert!(err.contains("timed out"), "unexpected error message: {err}");
+        assert!(started.elapsed() < Duration::from_secs(2));
+        let statuses = statuses(&events);
+        assert_eq!(
+            statuses
+                .iter()
+                .filter(|status| **status == "timeout")
+                .count(),
+            2,
+            "every attempt should time out: {statuses:?}"
+        );
+        assert_eq!(statuses.last(), Some(&"error"));
+    }
+
+    #[tokio::test]
+    async fn run_retries_by_policy_and_reports_attempts() {
+        let mut workflow = wait_workflow(Some(20));
+        workflow.nodes[1].metadata.retry_policy = Some(RetryPolicy {
+            max_retries: 2,
+            delay_ms: 10,
+            retry_on: vec!["timed out".to_string()],
+            ..Default::default()
+        });
+
+        let (report, events) = run_recording(workflow.clone(), "node-1").await;
+        assert!(!report.is_success());
+        let attempts: Vec<(&str, u64)> = events
+            .iter()
+            .filter_map(|event| Some((event["status"].as_str()?, event["attempt"].as_u64()?)))
+            .collect();
+        assert_eq!(
+            attempts,
+            vec![
+                ("running", 1),
+                ("timeout", 1),
+                ("running", 2),
+                ("timeout", 2),
+                ("running", 3),
+                ("timeout", 3),
+                ("error", 3),
+            ]
+        );
+
+        workflow.nodes[1].metadata.retry_policy = Some(RetryPolicy {
+            max_retries: 2,
+            abort_on: vec!["timed out".to_string()],
+            ..Default::default()
+        });
+        let (report, events) = run_recording(workflow, "node-1").await;
+        assert!(!report.is_success());
+        assert_eq!(statuses(&events), vec!["running", "timeout", "error"]);
+    }
+
+    #[tokio::test]
+    async fn run_stops_at_the_workflow_deadline() {
+        let mut workflow = wait_workflow(None);
+        workflow.timeout_ms = Some(50);
+
+        let started = Instant::now();
+        let (report, _) = run_recording(workflow, "*").await;
+
+        let err = report.error.expect("the run should fail");
+        assert!(
+            err.contains("Workflow timed out"),
+            "unexpected error message: {err}"
+        );
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    fn failing_workflow(err_return: Option<bool>, error_port: bool) -> WorkflowSchema {
+        let mut wait = node_schema(
+            "node-1",
+            "TimeWait",
+            "wait",
+            Some(HashMap::from([(
+                "duration".to_string(),
+                serde_json::json!("5"),
+            )])),
+        );
+        wait.metadata.timeout_ms = Some(20);
+        wait.metadata.err_return = err_return;
+
+        let mut connections = vec![
+            connection("node-0", "node-1", None),
+            connection("node-1", "node-2", None),
+        ];
+        if error_port {
+            connections.push(connection("node-1", "node-3", Some(ERROR_PORT)));
+        }
+        WorkflowSchema {
+            nodes: vec![
+                node_schema("node-0", "Start", "start", None),
+                wait,
+                node_schema(
+                    "node-2",
+                    "Custom",
+                    "after",
+                    Some(HashMap::from([(
+                        "path".to_string(),
+                        serde_json::json!("success"),
+                    )])),
+                ),
+                node_schema(
+                    "node-3",
+                    "Custom",
+                    "recover",
+                    Some(HashMap::from([(
+                        "path".to_string(),
+                        serde_json::json!("error"),
+                    )])),
+                ),
+            ],
+            connections,
+            ..Default::default()
+        }
+    }
+
+    /// start -> fail, which times out after 20 ms, and start -> slow ->
+    /// after, where `slow` takes 100 ms.
+    fn racing_workflow() -> WorkflowSchema {
+        let mut fail = node_schema(
+            "fail",
+            "TimeWait",
+            "fail",
+            Some(HashMap::from([(
+                "duration".to_string(),
+                serde_json::json!("5"),
+            )])),
+        );
+        fail.metadata.timeout_ms = Some(20);
+        WorkflowSchema {
+            nodes: vec![
+                node_schema("start", "Start", "start", None),
+                fail,
+                node_schema(
+                    "slow",
+                    "TimeWait",
+                    "slow",
+                    Some(HashMap::from([(
+                        "duration".to_string(),
+                        serde_json::json!("0.1"),
+                    )])),
+                ),
+                node_schema("after", "Custom", "after", None),
+            ],
+            connections: vec![
+                connection("start", "fail", None),
+                connection("start", "slow", None),
+                connection("slow", "after", None),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn run_fails_when_a_branch_fails_before_its_sibling() {
+        let runner = WorkflowRunner::create(racing_workflow()).expect("workflow should be valid");
+        let bus = counting_bus(Arc::new(AtomicUsize::new(0)), Arc::new(Mutex::new(None)));
+        let report = runner
+            .run(
+                test_context(),
+                HashMap::new(),
+                CancellationToken::new(),
+                Arc::new(RwLock::new(bus)),
+                Arc::new(NotificationEmitter::new()),
+            )
+            .await
+            .expect("workflow should start");
+
+        assert_eq!(report.status, RunStatus::Failed);
+        let err = report.error.expect("the run should fail");
+        assert!(err.contains("timed out"), "unexpected error message: {err}");
+    }
+
+    #[tokio::test]
+    async fn run_fails_a_wait_too_long_to_sleep() {
+        let workflow = WorkflowSchema {
+            nodes: vec![
+                node_schema("node-0", "Start", "start", None),
+                node_schema(
+                    "node-1",
+                    "TimeWait",
+                    "wait",
+                    Some(HashMap::from([(
+                        "duration".to_string(),
+                        serde_json::json!("1e20"),
+                    )])),
+                ),
+            ],
+            connections: vec![connection("node-0", "node-1", None)],
+            ..Default::default()
+        };
+        let (report, _) = run_recording(workflow, "node-1").await;
+        let err = report.error.expect("the run should fail");
+        assert!(
+            err.contains("Invalid wait duration"),
+            "unexpected error message: {err}"
+        );
+    }
+
+    #[tokio::test]
+    async fn run_aborts_sibling_branches_after_a_failure() {
+        let runner = WorkflowRunner::create(racing_workflow()).expect("workflow should be valid");
+        let counter = Arc::new(AtomicUsize::new(0));
+        let bus = counting_bus(Arc::clone(&counter), Arc::new(Mutex::new(None)));
+        let report = runner
+            .run(
+                test_context(),
+                HashMap::new(),
+                CancellationToken::new(),
+                Arc::new(RwLock::new(bus)),
+                Arc::new(NotificationEmitter::new()),
+            )
+            .await
+            .expect("workflow should start");
+        assert_eq!(report.status, RunStatus::Failed);
+
+        // Give the slow branch the time it would have needed.
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(counter.load(Ordering::SeqCst), 0, "`after` must not run");
+        let after = report
+            .nodes
+            .iter()
+            .find(|node| node.node_id == "after")
+            .expect("after is reported");
+        assert_eq!(after.status, NodeStatus::NotRun);
+    }
+
+    fn test_context() -> Arc<Context> {
+        #[cfg(feature = "tauri")]
+        let context = Context::new(PathBuf::new(), None);
+
+        #[cfg(not(feature = "tauri"))]
+        let context = Context::new(PathBuf::new());
+
+        Arc::new(context)
+    }
+
+    #[tokio::test]
+    async fn run_routes_a_failure_to_the_error_port() {
+        let ctx = test_context();
+        let (count, params) = run_counting_in(failing_workflow(None, true), ctx.clone()).await;
+
+        assert_eq!(count, 1, "only the error handler should run");
+        assert_eq!(params, Some(serde_json::json!({"path": "error"})));
+        let error = ctx.get_value("ctx.wait.error").await;
+        assert!(
+            error
+                .as_ref()
+                .and_then(|error| error.as_str())
+                .is_some_and(|error| error.contains("timed out")),
+            "the error should be recorded: {error:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn run_continues_after_an_error_when_err_return_is_false() {
+        let ctx = test_context();
+        let (count, params) =
+            run_counting_in(failing_workflow(Some(false), false), ctx.clone()).await;
+
+        assert_eq!(count, 1);
+        assert_eq!(params, Some(serde_json::json!({"path": "success"})));
+        assert!(ctx.get_value("ctx.wait.error").await.is_some());
+
+        let (report, _) = run_recording(failing_workflow(None, false), "node-1").await;
+        assert_eq!(
+            report.status,
+            RunStatus::Failed,
+            "errors abort the run by default"
+        );
+    }
+
+    fn join_workflow(branches: usize, join: Option<JoinMode>) -> WorkflowSchema {
+        let mut nodes = vec![node_schema("node-0", "Start", "start", None)];
+        let mut connections = vec![];
+        for branch in 1..=branches {
+            let id = format!("branch-{branch}");
+            nodes.push(node_schema(&id, "Custom", &id, None));
+            connections.push(connection("node-0", &id, None));
+            connections.push(connection(&id, "join", None));
+        }
+        let mut join_node = node_schema("join", "Custom", "join", None);
+        join_node.metadata.join = join;
+        nodes.push(join_node);
+
+        WorkflowSchema {
+            nodes,
+            connections,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn run_join_modes_fire_once() {
+        let (count, _) = run_counting(join_workflow(3, None)).await;
+        assert_eq!(count, 4, "join all runs once after every branch");
+
+        let (count, _) = run_counting(join_workflow(3, Some(JoinMode::Any))).await;
+        assert_eq!(count, 4, "join any runs once, on the first branch");
+
+        let (count, _) = run_counting(join_workflow(3, Some(JoinMode::AtLeast(2)))).await;
+        assert_eq!(count, 4, "join at least runs once");
+    }
+
+    #[tokio::test]
+    async fn run_join_ignores_branches_arriving_after_it_ran() {
+        let (report, events) = run_recording(join_workflow(3, Some(JoinMode::Any)), "join").await;
+        assert!(report.is_success(), "workflow failed: {:?}", report.error);
+        let statuses = statuses(&events);
+        assert!(
+            !statuses.contains(&"waiting"),
+            "late branches must not report the join as waiting: {statuses:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn run_join_does_not_wait_for_skipped_branches() {
+        let start_params = HashMap::from([("params".to_string(), serde_json::json!({"value": 3}))]);
+        let workflow = WorkflowSchema {
+            nodes: vec![
+                node_schema("node-0", "Start", "start", Some(start_params)),
+                node_schema(
+                    "node-1",
+                    "Branch",
+                    "branch",
+                    Some(HashMap::from([(
+                        "condition".to_string(),
+                        serde_json::json!("${ctx.start.value} > 2"),
+                    )])),
+                ),
+                node_schema("node-2", "Custom", "yes", None),
+                node_schema("node-3", "Custom", "no", None),
+                node_schema("node-4", "Custom", "no-followup", None),
+                node_schema(
+                    "node-5",
+                    "Custom",
+                    "join",
+                    Some(HashMap::from([(
+                        "joined".to_string(),
+                        serde_json::json!(true),
+                    )])),
+                ),
+            ],
+            connections: vec![
+                connection("node-0", "node-1", None),
+                connection("node-1", "node-2", Some("true")),
+                connection("node-1", "node-3", Some("false")),
+                connection("node-3", "node-4", None),
+                connection("node-2", "node-5", None),
+                connection("node-4", "node-5", None),
+            ],
+            ..Default::default()
+        };
+
+        let (count, params) = run_counting(workflow).await;
+        assert_eq!(count, 2, "the taken branch and the join should run");
+        assert_eq!(params, Some(serde_json::json!({"joined": true})));
+    }
+
+    #[tokio::test]
+    async fn run_passes_over_disabled_nodes() {
+        let mut workflow = join_workflow(2, None);
+        workflow.nodes[1].metadata.disabled = true;
+        let (count, _) = run_counting(workflow.clone()).await;
+        assert_eq!(count, 2, "the other branch and the join should run");
+
+        let (report, events) = run_recording(workflow, "branch-1").await;
+        assert!(report.is_success(), "workflow failed: {:?}", report.error);
+        assert_eq!(statuses(&events), vec!["disabled"]);
+        let status = |id: &str| {
+            report
+                .nodes
+                .iter()
+                .find(|node| node.node_id == id)
+                .map(|node| node.status)
+        };
+        assert_eq!(status("branch-1"), Some(NodeStatus::Disabled));
+        assert_eq!(status("join"), Some(NodeStatus::Succeeded));
+    }
+
+    #[tokio::test]
+    async fn run_seeds_workflow_variables() {
+        let workflow = WorkflowSchema {
+            nodes: vec![
+                node_schema("node-0", "Start", "start", None),
+                node_schema(
+                    "node-1",
+                    "Branch",
+                    "branch",
+                    Some(HashMap::from([(
+                        "condition".to_string(),
+                        serde_json::json!("${var.threshold} > 0.5"),
+                    )])),
+                ),
+                node_schema(
+                    "node-2",
+                    "Custom",
+                    "yes",
+                    Some(HashMap::from([(
+                        "path".to_string(),
+                        serde_json::json!("yes"),
+                    )])),
+                ),
+                node_schema(
+                    "node-3",
+                    "Custom",
+                    "no",
+                    Some(HashMap::from([(
+                        "path".to_string(),
+                        serde_json::json!("no"),
+                    )])),
+                ),
+            ],
+            connections: vec![
+                connection("node-0", "node-1", None),
+                connection("node-1", "node-2", Some("true")),
+                connection("node-1", "node-3", Some("false")),
+            ],
+            variables: HashMap::from([("threshold".to_string(), serde_json::json!(0.8))]),
+            ..Default::default()
+        };
+        let runner = WorkflowRunner::create(workflow).expect("workflow should be valid");
+        let params = Arc::new(Mutex::new(None));
+        let bus = Arc::new(RwLock::new(counting_bus(
+            Arc::new(AtomicUsize::new(0)),
+            Arc::clone(&params),
+        )));
+
+        for (inputs, expected) in [
+            (HashMap::new(), "yes"),
+            (
+                HashMap::from([("threshold".to_string(), serde_json::json!("0.1"))]),
+                "no",
+            ),
+        ] {
+            let ctx = test_context();
+            let report = runner
+                .run(
+                    ctx.clone(),
+                    inputs,
+                    CancellationToken::new(),
+                    bus.clone(),
+                    Arc::new(NotificationEmitter::new()),
+                )
+                .await
+                .expect("workflow should start");
+            assert!(report.is_success(), "workflow failed: {:?}", report.error);
+            assert_eq!(
+                params.lock().expect("lock params failed").clone(),
+                Some(serde_json::json!({"path": expected}))
+            );
+            assert!(ctx.get_value("var.threshold").await.is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn run_redacts_secrets() {
+        let workflow = WorkflowSchema {
+            nodes: vec![
+                node_schema("node-0", "Start", "start", None),
+                node_schema(
+                    "node-1",
+                    "End",
+                    "end",
+                    Some(HashMap::from([(
+                        "outputs".to_string(),
+                        serde_json::json!({
+                            "auth": "Bearer ${secret.token}",
+                            "token": "${secret.token}",
+                        }),
+                    )])),
+                ),
+            ],
+            connections: vec![connection("node-0", "node-1", None)],
+            ..Default::default()
+        };
+        let runner = WorkflowRunner::create(workflow).expect("workflow should be valid");
+        let bus = Arc::new(RwLock::new(NodeRegisterBus::new().with_internal_nodes()));
+        let events = Arc::new(Mutex::new(vec![]));
+        let emitter = NotificationEmitter::new().with_emitter(Box::new(RecordingEmitter {
+            events: Arc::clone(&events),
+        }));
+
+        let mut secrets = SecretStore::new();
+        secrets.insert("token", "s3cr3t".to_string());
+        let context = Arc::try_unwrap(test_context()).expect("context is not shared");
+        let ctx = Arc::new(context.with_secrets(secrets));
+        let report = runner
+            .run(
+                ctx.clone(),
+                HashMap::new(),
+                CancellationToken::new(),
+                bus,
+                Arc::new(emitter),
+            )
+            .await
+            .expect("workflow should start");
+        assert!(report.is_success(), "workflow failed: {:?}", report.error);
+
+        // Nodes see the value, the report and the events do not.
+        assert_eq!(
+            ctx.get_value("ctx.end.auth").await,
+            Some(serde_json::json!("Bearer s3cr3t"))
+        );
+        assert_eq!(
+            report.outputs.get("auth"),
+            Some(&serde_json::json!(format!("Bearer {}", REDACTED)))
+        );
+        assert_eq!(
+            report.outputs.get("token"),
+            Some(&serde_json::json!(REDACTED))
+        );
+        let report = serde_json::to_string(&report).expect("serialize report");
+        assert!(!report.contains("s3cr3t"), "{report}");
+        let events = serde_json::to_string(&*events.lock().expect("lock events failed"))
+            .expect("serialize events");
+        assert!(events.contains(REDACTED), "{events}");
+        assert!(!events.contains("s3cr3t"), "{events}");
+    }
+
+    #[tokio::test]
+    async fn run_keeps_parallel_branches_apart() {
+        let aggregate = |id: &str, name: &str, source: &str| {
+            node_schema(
+                id,
+                "DataAggregator",
+                name,
+                Some(HashMap::from([
+                    ("mode".to_string(), serde_json::json!("array")),
+                    ("sources".to_string(), serde_json::json!([source])),
+                ])),
+            )
+        };
+        let wait = |id: &str, seconds: f64| {
+            node_schema(
+                id,
+                "TimeWait",
+                id,
+                Some(HashMap::from([(
+                    "duration".to_string(),
+                    serde_json::json!(seconds),
+                )])),
+            )
+        };
+        // Both branches set `ctx.pick.*` and read it back after the other
+        // branch set it too.
+        let workflow = WorkflowSchema {
+            nodes: vec![
+                node_schema("start", "Start", "start", None),
+                aggregate("pick-a", "pick", "${var.a}"),
+                wait("wait-a", 0.1),
+                aggregate("read-a", "read_a", "${ctx.pick.result}"),
+                wait("wait-b", 0.05),
+                aggregate("pick-b", "pick", "${var.b}"),
+                wait("wait-b2", 0.1),
+                aggregate("read-b", "read_b", "${ctx.pick.result}"),
+                aggregate("join", "join", "${ctx.read_a.result}"),
+            ],
+            connections: vec![
+                connection("start", "pick-a", None),
+                connection("pick-a", "wait-a", None),
+                connection("wait-a", "read-a", None),
+                connection("read-a", "join", None),
+                connection("start", "wait-b", None),
+                connection("wait-b", "pick-b", None),
+                connection("pick-b", "wait-b2", None),
+                connection("wait-b2", "read-b", None),
+                connection("read-b", "join", None),
+            ],
+            variables: HashMap::from([
+                ("a".to_string(), serde_json::json!("A")),
+                ("b".to_string(), serde_json::json!("B")),
+            ]),
+            ..Default::default()
+        };
+        let runner = WorkflowRunner::create(workflow).expect("workflow should be valid");
+        let bus = Arc::new(RwLock::new(NodeRegisterBus::new().with_internal_nodes()));
+        let ctx = test_context();
+        let report = runner
+            .run(
+                ctx.clone(),
+                HashMap::new(),
+                CancellationToken::new(),
+                bus,
+                Arc::new(NotificationEmitter::new()),
+            )
+            .await
+            .expect("workflow should start");
+        assert!(report.is_success(), "workflow failed: {:?}", report.error);
+
+        assert_eq!(
+            ctx.get_value("ctx.read_a.result").await,
+            Some(serde_json::json!([["A"]]))
+        );
+        assert_eq!(
+            ctx.get_value("ctx.read_b.result").await,
+            Some(serde_json::json!([["B"]]))
+        );
+        // The join runs after both branches and sees what either set.
+        assert_eq!(
+            ctx.get_value("ctx.join.result").await,
+            Some(serde_json::json!([[["A"]]]))
+        );
+    }
+
+    #[tokio::test]
+    async fn run_twice_resets_the_joins() {
+        let runner =
+            WorkflowRunner::create(join_workflow(2, None)).expect("workflow should be valid");
+        let counter = Arc::new(AtomicUsize::new(0));
+        let bus = Arc::new(RwLock::new(counting_bus(
+            Arc::clone(&counter),
+            Arc::new(Mutex::new(None)),
+        )));
+
+        for _ in 0..2 {
+            runner
+                .run(
+                    test_context(),
+                    HashMap::new(),
+                    CancellationToken::new(),
+                    bus.clone(),
+                    Arc::new(NotificationEmitter::new()),
+                )
+                .await
+                .expect("workflow should run successfully");
+        }
+        assert_eq!(counter.load(Ordering::SeqCst), 6);
+    }
+
+    #[tokio::test]
+    async fn run_reports_every_node() {
+        let mut workflow = failing_workflow(Some(false), false);
+        workflow.nodes[1].metadata.retry = Some(1);
+        workflow.nodes.push(node_schema(
+            "node-4",
+            "Custom",
+            "skipped",
+            Some(HashMap::from([(
+                "value".to_string(),
+                serde_json::json!("${ctx.start.value}"),
+            )])),
+        ));
+        workflow.nodes[4].metadata.conditions = Some(Conditions {
+            exist: Some("ctx.missing".to_string()),
+            condition: None,
+            not_exist: None,
+        });
+        workflow
+            .connections
+            .push(connection("node-2", "node-4", None));
+
+        let (report, _) = run_recording(workflow, "node-1").await;
+        assert!(report.is_success(), "workflow failed: {:?}", report.error);
+        assert_eq!(report.nodes.len(), 4, "node-3 is not reachable");
+
+        let wait = report.node("node-1").next().expect("wait report");
+        assert_eq!(wait.status, NodeStatus::Failed);
+        assert_eq!(wait.attempts, 2);
+        assert_eq!(
+            wait.inputs
+                .as_ref()
+                .and_then(|inputs| inputs.get("duration")),
+            Some(&serde_json::json!(5))
+        );
+        assert!(wait.error.as_ref().is_some_and(|e| e.contains("timed out")));
+        assert!(wait.duration_ms.is_some());
+
+        let after = report.node("node-2").next().expect("after report");
+        assert_eq!(after.status, NodeStatus::Succeeded);
+        assert_eq!(after.attempts, 1);
+
+        let skipped = report.node("node-4").next().expect("skipped report");
+        assert_eq!(skipped.status, NodeStatus::Skipped);
+        assert!(skipped.skip_reason.is_some());
+        assert!(report.context.contains_key("ctx.wait.error"));
+    }
+
     #[test]
     fn create_fails_on_cycle() {
         let workflow = WorkflowSchema {
@@ -674,16 +3556,23 @@ mod tests {
                 Connection {
                     from: "node-0".to_string(),
                     to: "node-1".to_string(),
+                    port: None,
+                    condition: None,
                 },
                 Connection {
                     from: "node-1".to_string(),
                     to: "node-2".to_string(),
+                    port: None,
+                    condition: None,
                 },
                 Connection {
                     from: "node-2".to_string(),
                     to: "node-1".to_string(),
+                    port: None,
+                    condition: None,
                 },
             ],
+            ..Default::default()
         };
 
         let err = WorkflowRunner::create(workflow).unwrap_err();
@@ -0,0 +1,301 @@
+use crate::workflow::cron::{CronExpr, DAY_MINUTES, MINUTE_MS, weekday};
+use serde::{Deserialize, Serialize};
+
+const DAY_MS: i64 = DAY_MINUTES * MINUTE_MS;
+
+/// When and how a workflow is run repeatedly.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Schedule {
+    pub trigger: Trigger,
+    #[serde(default)]
+    pub overlap: OverlapPolicy,
+    #[serde(default)]
+    pub missed: MissedRunPolicy,
+    /// Timezone cron expressions and windows are read in, as minutes away
+    /// from UTC.
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+    /// How late a run may start and still count as on time.
+    #[serde(default = "Schedule::default_grace_ms")]
+    pub grace_ms: u64,
+}
+
+impl Schedule {
+    pub fn new(trigger: Trigger) -> Self {
+        Self {
+            trigger,
+            overlap: Default::default(),
+            missed: Default::default(),
+            utc_offset_minutes: 0,
+            grace_ms: Self::default_grace_ms(),
+        }
+    }
+
+    fn default_grace_ms() -> u64 {
+        1000
+    }
+
+    pub fn with_overlap(mut self, overlap: OverlapPolicy) -> Self {
+        self.overlap = overlap;
+        self
+    }
+
+    pub fn with_missed(mut self, missed: MissedRunPolicy) -> Self {
+        self.missed = missed;
+        self
+    }
+
+    pub fn with_utc_offset(mut self, minutes: i32) -> Self {
+        self.utc_offset_minutes = minutes;
+        self
+    }
+
+    pub fn with_grace(mut self, ms: u64) -> Self {
+        self.grace_ms = ms;
+        self
+    }
+
+    /// The first fire time after `after`. Intervals count from `anchor`.
+    pub fn next_fire(&self, after: u64, anchor: u64) -> Option<u64> {
+        match &self.trigger {
+            Trigger::Cron { expression } => expression.next_after(after, self.utc_offset_minutes),
+            Trigger::Interval { every_ms } => next_interval(after, anchor, *every_ms),
+            Trigger::Window {
+                start,
+                end,
+                every_ms,
+                weekdays,
+            } => {
+                let window = Window {
+                    start: start.minutes() * MINUTE_MS,
+                    end: end.minutes() * MINUTE_MS,
+                    every_ms: *every_ms as i64,
+                    weekdays,
+                };
+                window.next_after(after as i64, self.utc_offset_minutes as i64 * MINUTE_MS)
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Trigger {
+    Cron {
+        expression: CronExpr,
+    },
+    /// Every `every_ms`, counted from when the scheduler started.
+    Interval {
+        every_ms: u64,
+    },
+    /// Every `every_ms` from `start` until `end`, every day or only on
+    /// `weekdays` (Sunday is 0). A window ending before it starts runs past
+    /// midnight.
+    Window {
+        start: TimeOfDay,
+        end: TimeOfDay,
+        every_ms: u64,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        weekdays: Vec<u32>,
+    },
+}
+
+/// What happens when a run is due while the previous one is still going.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlapPolicy {
+    /// The new run is dropped.
+    #[default]
+    Skip,
+    /// The new run starts once the previous ones have finished.
+    Queue,
+    /// The previous run is cancelled and the new one starts once it stopped.
+    CancelPrevious,
+}
+
+/// What happens to fire times that passed without a run, e.g. while the
+/// machine was asleep or the scheduler was not running.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MissedRunPolicy {
+    /// Missed runs are dropped.
+    #[default]
+    Skip,
+    /// A single run makes up for all of them.
+    RunOnce,
+    /// Every missed run is made up for.
+    RunAll,
+}
+
+/// A time of day as `HH:MM`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeOfDay {
+    pub hour: u32,
+    pub minute: u32,
+}
+
+impl TimeOfDay {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid time of day `{}`, expected HH:MM", text);
+        let (hour, minute) = text.trim().split_once(':').ok_or_else(invalid)?;
+        let hour = hour.parse::<u32>().map_err(|_| invalid())?;
+        let minute = minute.parse::<u32>().map_err(|_| invalid())?;
+        if hour > 23 || minute > 59 {
+            return Err(invalid());
+        }
+        Ok(Self { hour, minute })
+    }
+
+    fn minutes(&self) -> i64 {
+        (self.hour * 60 + self.minute) as i64
+    }
+}
+
+impl TryFrom<String> for TimeOfDay {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        Self::parse(&text)
+    }
+}
+
+impl From<TimeOfDay> for String {
+    fn from(time: TimeOfDay) -> Self {
+        format!("{:02}:{:02}", time.hour, time.minute)
+    }
+}
+
+fn next_interval(after: u64, anchor: u64, every_ms: u64) -> Option<u64> {
+    if every_ms == 0 {
+        return None;
+    }
+    if after < anchor {
+        return Some(anchor);
+    }
+    Some(anchor + ((after - anchor) / every_ms + 1) * every_ms)
+}
+
+/// A daily window in milliseconds into the local day.
+struct Window<'a> {
+    start: i64,
+    end: i64,
+    every_ms: i64,
+    weekdays: &'a [u32],
+}
+
+impl Window<'_> {
+    fn next_after(&self, after: i64, offset: i64) -> Option<u64> {
+        if self.every_ms <= 0 {
+            return None;
+        }
+        // A window that ends when it starts lasts the whole day.
+        let length = match (self.end - self.start).rem_euclid(DAY_MS) {
+            0 => DAY_MS,
+            length => length,
+        };
+        let local = after + offset;
+        let today = local.div_euclid(DAY_MS);
+        // Yesterday's window may still be open past midnight.
+        for day in today - 1..=today + 7 {
+            if !self.weekdays.is_empty() && !self.weekdays.contains(&weekday(day)) {
+                continue;
+            }
+            let opens = day * DAY_MS + self.start;
+            let fire = if local < opens {
+                opens
+            } else {
+                opens + ((local - opens) / self.every_ms + 1) * self.every_ms
+            };
+            if fire < opens + length {
+                return u64::try_from(fire - offset).ok();
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2024-01-01T00:00:00Z, a Monday.
+    const MONDAY: u64 = 1_704_067_200_000;
+    const MINUTE: u64 = 60_000;
+    const HOUR: u64 = 3_600_000;
+
+    fn window(start: &str, end: &str, every_ms: u64, weekdays: Vec<u32>) -> Schedule {
+        Schedule::new(Trigger::Window {
+            start: TimeOfDay::parse(start).unwrap(),
+            end: TimeOfDay::parse(end).unwrap(),
+            every_ms,
+            weekdays,
+        })
+    }
+
+    #[test]
+    fn test_next_fire_interval() {
+        let schedule = Schedule::new(Trigger::Interval { every_ms: 1000 });
+        assert_eq!(schedule.next_fire(500, 500), Some(1500));
+        assert_eq!(schedule.next_fire(1500, 500), Some(2500));
+        assert_eq!(schedule.next_fire(2600, 500), Some(3500));
+    }
+
+    #[test]
+    fn test_next_fire_window() {
+        let office = window("09:00", "17:00", 30 * MINUTE, vec![]);
+        assert_eq!(office.next_fire(MONDAY, 0), Some(MONDAY + 9 * HOUR));
+        assert_eq!(
+            office.next_fire(MONDAY + 9 * HOUR, 0),
+            Some(MONDAY + 9 * HOUR + 30 * MINUTE)
+        );
+        assert_eq!(
+            office.next_fire(MONDAY + 16 * HOUR + 30 * MINUTE, 0),
+            Some(MONDAY + 33 * HOUR)
+        );
+        // 09:00 at UTC+1 is 08:00 UTC.
+        assert_eq!(
+            office.clone().with_utc_offset(60).next_fire(MONDAY, 0),
+            Some(MONDAY + 8 * HOUR)
+        );
+
+        let night = window("22:00", "02:00", HOUR, vec![]);
+        assert_eq!(night.next_fire(MONDAY, 0), Some(MONDAY + HOUR));
+        assert_eq!(night.next_fire(MONDAY + HOUR, 0), Some(MONDAY + 22 * HOUR));
+
+        let weekend = window("10:00", "11:00", HOUR, vec![0, 6]);
+        assert_eq!(
+            weekend.next_fire(MONDAY, 0),
+            Some(MONDAY + 5 * 24 * HOUR + 10 * HOUR)
+        );
+    }
+
+    #[test]
+    fn test_deserialize_schedule() {
+        let schedule: Schedule = serde_yaml::from_str(
+            r#"
+trigger:
+  type: cron
+  expression: "0 9 * * MON-FRI"
+overlap: queue
+missed: run_once
+"#,
+        )
+        .unwrap();
+        assert_eq!(schedule.overlap, OverlapPolicy::Queue);
+        assert_eq!(schedule.missed, MissedRunPolicy::RunOnce);
+        assert_eq!(schedule.grace_ms, 1000);
+        assert_eq!(schedule.next_fire(MONDAY, 0), Some(MONDAY + 9 * HOUR));
+
+        let invalid = serde_yaml::from_str::<Schedule>(
+            r#"
+trigger:
+  type: window
+  start: "25:00"
+  end: "26:00"
+  every_ms: 1000
+"#,
+        );
+        assert!(invalid.is_err());
+    }
+}
@@ -0,0 +1,438 @@
+use crate::context::Context;
+use crate::event::{SCHEDULE_EVENT, ScheduleEventPayload, ScheduleStatus};
+use crate::notification::emitter::NotificationEmitter;
+use crate::register::bus::NodeRegisterBus;
+use crate::workflow::BoxFuture;
+use crate::workflow::clock::{Clock, SystemClock};
+use crate::workflow::control::RunControl;
+use crate::workflow::runner::WorkflowRunner;
+use crate::workflow::schedule::{MissedRunPolicy, OverlapPolicy, Schedule};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// At most this many missed runs are made up for or reported at once, the
+/// rest are dropped.
+const MAX_MISSED: usize = 100;
+
+/// A run the scheduler starts.
+#[derive(Clone, Copy, Debug)]
+pub struct Fire {
+    /// The fire time the run is for.
+    pub scheduled_at: u64,
+    /// When the scheduler noticed it was due.
+    pub fired_at: u64,
+}
+
+pub type ScheduledJob =
+    Arc<dyn Fn(Fire, RunControl) -> BoxFuture<Result<(), String>> + Send + Sync>;
+
+/// Runs a job, usually a workflow, whenever its `Schedule` fires.
+pub struct Scheduler {
+    schedule: Schedule,
+    clock: Arc<dyn Clock>,
+    emitter: Arc<NotificationEmitter>,
+    last_fire: Option<u64>,
+}
+
+impl Scheduler {
+    pub fn new(schedule: Schedule, emitter: Arc<NotificationEmitter>) -> Self {
+        Self {
+            schedule,
+            clock: Arc::new(SystemClock),
+            emitter,
+            last_fire: None,
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Continues a schedule that last fired at `at`, e.g. before a restart.
+    /// Fire times since then count as missed runs, intervals count from
+    /// `at`.
+    pub fn with_last_fire(mut self, at: u64) -> Self {
+        self.last_fire = Some(at);
+        self
+    }
+
+    pub fn start(self, job: ScheduledJob) -> ScheduleHandle {
+        let token = CancellationToken::new();
+        let started = self.clock.now_ms();
+        let task = tokio::spawn(self.run(job, started, token.clone()));
+        ScheduleHandle { token, task }
+    }
+
+    /// Runs the workflow of `runner` on every fire, each time with a new
+    /// `Context` for the same workflow directory as `ctx`.
+    pub fn start_workflow(
+        self,
+        runner: Arc<WorkflowRunner>,
+        ctx: Arc<Context>,
+        inputs: HashMap<String, Value>,
+        bus: Arc<RwLock<NodeRegisterBus>>,
+    ) -> ScheduleHandle {
+        let emitter = self.emitter.clone();
+        self.start(Arc::new(move |_, control| {
+            let runner = runner.clone();
+            let ctx = Arc::new(ctx.child(ctx.workflow_path.clone()));
+            let inputs = inputs.clone();
+            let bus = bus.clone();
+            let emitter = emitter.clone();
+            Box::pin(async move {
+                let report = runner
+                    .run_with_control(ctx, inputs, control, bus, emitter)
+                    .await?;
+                match report.error {
+                    Some(e) if !report.is_success() => Err(e),
+                    _ => Ok(()),
+                }
+            })
+        }))
+    }
+
+    async fn run(self, job: ScheduledJob, started: u64, token: CancellationToken) {
+        let anchor = self.last_fire.unwrap_or(started);
+        let mut cursor = anchor;
+        let mut runs = Runs {
+            overlap: self.schedule.overlap,
+            emitter: self.emitter.clone(),
+            previous: None,
+        };
+
+        while let Some(next) = self.schedule.next_fire(cursor, anchor) {
+            emit(&self.emitter, ScheduleStatus::Scheduled, None, Some(next));
+            tokio::select! {
+                _ = token.cancelled() => break,
+                _ = self.clock.sleep_until(next) => {}
+            }
+
+            let now = self.clock.now_ms();
+            let mut due = vec![next];
+            while due.len() < MAX_MISSED
+                && let Some(at) = self.schedule.next_fire(cursor_of(&due), anchor)
+                && at <= now
+            {
+                due.push(at);
+            }
+            cursor = if due.len() < MAX_MISSED {
+                cursor_of(&due)
+            } else {
+                now
+            };
+
+            let late = now.saturating_sub(next) > self.schedule.grace_ms;
+            if due.len() > 1 || late {
+                let made_up = match self.schedule.missed {
+                    MissedRunPolicy::Skip => 0,
+                    MissedRunPolicy::RunOnce => 1,
+                    MissedRunPolicy::RunAll => due.len(),
+                };
+                for at in due.drain(..due.len() - made_up) {
+                    log::warn!("missed the scheduled run at {}", at);
+                    emit(&self.emitter, ScheduleStatus::Missed, Some(at), None);
+                }
+            }
+            for at in due {
+                let fire = Fire {
+                    scheduled_at: at,
+                    fired_at: now,
+                };
+                runs.fire(fire, &job, &token);
+            }
+        }
+
+        runs.wait().await;
+        emit(&self.emitter, ScheduleStatus::Stopped, None, None);
+    }
+}
+
+fn cursor_of(due: &[u64]) -> u64 {
+    due.last().copied().unwrap_or_default()
+}
+
+fn emit(
+    emitter: &NotificationEmitter,
+    status: ScheduleStatus,
+    fire_at: Option<u64>,
+    next_fire_at: Option<u64>,
+) {
+    emitter
+        .emit(
+            SCHEDULE_EVENT,
+            ScheduleEventPayload {
+                status,
+                fire_at,
+                next_fire_at,
+            },
+        )
+        .unwrap_or_default();
+}
+
+/// The runs the scheduler started, as far as the overlap policy needs them.
+struct Runs {
+    overlap: OverlapPolicy,
+    emitter: Arc<NotificationEmitter>,
+    /// The run started last. With `Queue` it waits for the one before it,
+    /// so it finishes last.
+    previous: Option<(RunControl, JoinHandle<()>)>,
+}
+
+impl Runs {
+    fn fire(&mut self, fire: Fire, job: &ScheduledJob, token: &CancellationToken) {
+        let previous = self.previous.take().filter(|(_, task)| !task.is_finished());
+        let previous = match (self.overlap, previous) {
+            (OverlapPolicy::Skip, Some(previous)) => {
+                log::info!(
+                    "skipped the run at {}, the previous one still runs",
+                    fire.scheduled_at
+                );
+                emit(
+                    &self.emitter,
+                    ScheduleStatus::Skipped,
+                    Some(fire.scheduled_at),
+                    None,
+                );
+                self.previous = Some(previous);
+                return;
+            }
+            (OverlapPolicy::CancelPrevious, Some((control, task))) => {
+                control.cancel();
+                Some(task)
+            }
+            (_, previous) => previous.map(|(_, task)| task),
+        };
+
+        let control = RunControl::from_token(token.child_token());
+        let task = tokio::spawn({
+            let control = control.clone();
+            let job = job.clone();
+            let emitter = self.emitter.clone();
+            async move {
+                if let Some(previous) = previous {
+                    let _ = previous.await;
+                }
+                if control.is_cancelled() {
+                    return;
+                }
+                emit(
+                    &emitter,
+                    ScheduleStatus::Fired,
+                    Some(fire.scheduled_at),
+                    None,
+                );
+                if let Err(e) = job(fire, control).await {
+                    log::error!("the scheduled run at {} failed: {}", fire.scheduled_at, e);
+                }
+            }
+        });
+        self.previous = Some((control, task));
+    }
+
+    async fn wait(self) {
+        if let Some((_, task)) = self.previous {
+            let _ = task.await;
+        }
+    }
+}
+
+pub struct ScheduleHandle {
+    token: CancellationToken,
+    task: JoinHandle<()>,
+}
+
+impl ScheduleHandle {
+    /// Stops firing and cancels the runs that have not finished.
+    pub fn stop(&self) {
+        self.token.cancel();
+    }
+
+    /// Waits until the scheduler and its runs have stopped, either after
+    /// `stop` or once the schedule never fires again.
+    pub async fn stopped(self) -> Result<(), String> {
+        self.task.await.map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notification::emitter::Emitter;
+    use crate::workflow::clock::FakeClock;
+    use crate::workflow::schedule::Trigger;
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    struct RecordingEmitter {
+        events: Arc<Mutex<Vec<Value>>>,
+    }
+
+    impl Emitter for RecordingEmitter {
+        fn emit(&self, event: &str, payload: Value) -> Result<(), String> {
+            if event == SCHEDULE_EVENT {
+                self.events.lock().map_err(|e| e.to_string())?.push(payload);
+            }
+            Ok(())
+        }
+    }
+
+    struct Harness {
+        clock: FakeClock,
+        events: Arc<Mutex<Vec<Value>>>,
+        fired: mpsc::UnboundedReceiver<u64>,
+        handle: ScheduleHandle,
+    }
+
+    impl Harness {
+        async fn next_fire(&mut self) -> u64 {
+            tokio::time::timeout(Duration::from_secs(2), self.fired.recv())
+                .await
+                .expect("no run started")
+                .unwrap()
+        }
+
+        fn events(&self, status: &str) -> Vec<u64> {
+            self.events
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|event| event["status"] == status)
+                .filter_map(|event| event["fire_at"].as_u64())
+                .collect()
+        }
+
+        async fn wait_for(&self, status: &str, fire_at: u64) {
+            for _ in 0..200 {
+                if self.events(status).contains(&fire_at) {
+                    return;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            panic!("no {status} event for {fire_at}");
+        }
+
+        async fn stop(self) {
+            self.handle.stop();
+            self.handle.stopped().await.unwrap();
+        }
+    }
+
+    /// Starts a scheduler on a fake clock set to `now`. Its job reports
+    /// every fire, then runs `run` until it resolves or is cancelled.
+    fn start<F>(schedule: Schedule, last_fire: Option<u64>, now: u64, run: F) -> Harness
+    where
+        F: Fn(Fire) -> BoxFuture<()> + Send + Sync + 'static,
+    {
+        let clock = FakeClock::new(now);
+        let events = Arc::new(Mutex::new(vec![]));
+        let emitter = NotificationEmitter::new().with_emitter(Box::new(RecordingEmitter {
+            events: events.clone(),
+        }));
+        let mut scheduler =
+            Scheduler::new(schedule, Arc::new(emitter)).with_clock(Arc::new(clock.clone()));
+        if let Some(at) = last_fire {
+            scheduler = scheduler.with_last_fire(at);
+        }
+
+        let (sender, fired) = mpsc::unbounded_channel();
+        let run = Arc::new(run);
+        let handle = scheduler.start(Arc::new(move |fire, control| {
+            let sender = sender.clone();
+            let run = run.clone();
+            Box::pin(async move {
+                sender.send(fire.scheduled_at).unwrap();
+                let token = control.token();
+                tokio::select! {
+                    _ = token.cancelled() => {}
+                    _ = run(fire) => {}
+                }
+                Ok(())
+            })
+        }));
+        Harness {
+            clock,
+            events,
+            fired,
+            handle,
+        }
+    }
+
+    fn interval(every_ms: u64) -> Schedule {
+        Schedule::new(Trigger::Interval { every_ms }).with_grace(100)
+    }
+
+    #[tokio::test]
+    async fn scheduler_fires_on_the_fake_clock() {
+        let mut harness = start(interval(1000), None, 0, |_| Box::pin(async {}));
+
+        harness.clock.set(1000);
+        assert_eq!(harness.next_fire().await, 1000);
+        harness.clock.advance(1000);
+        assert_eq!(harness.next_fire().await, 2000);
+        harness.wait_for("fired", 2000).await;
+
+        let events = harness.events.lock().unwrap().clone();
+        assert_eq!(events[0]["status"], "scheduled");
+        assert_eq!(events[0]["next_fire_at"], 1000);
+        harness.stop().await;
+    }
+
+    #[tokio::test]
+    async fn scheduler_handles_missed_runs() {
+        let schedule = interval(1000).with_missed(MissedRunPolicy::RunOnce);
+        let mut harness = start(schedule, None, 0, |_| Box::pin(async {}));
+        harness.clock.set(3500);
+        assert_eq!(harness.next_fire().await, 3000);
+        harness.wait_for("fired", 3000).await;
+        assert_eq!(harness.events("missed"), vec![1000, 2000]);
+        harness.stop().await;
+
+        // Catching up after a restart, one run after the other.
+        let schedule = interval(1000)
+            .with_missed(MissedRunPolicy::RunAll)
+            .with_overlap(OverlapPolicy::Queue);
+        let mut harness = start(schedule, Some(0), 3500, |_| {
+            Box::pin(tokio::time::sleep(Duration::from_millis(20)))
+        });
+        assert_eq!(harness.next_fire().await, 1000);
+        assert_eq!(harness.next_fire().await, 2000);
+        assert_eq!(harness.next_fire().await, 3000);
+        assert!(harness.events("missed").is_empty());
+        harness.stop().await;
+
+        let schedule = interval(1000).with_missed(MissedRunPolicy::Skip);
+        let harness = start(schedule, Some(0), 2500, |_| Box::pin(async {}));
+        harness.wait_for("missed", 2000).await;
+        assert_eq!(harness.events("missed"), vec![1000, 2000]);
+        assert!(harness.events("fired").is_empty());
+        harness.stop().await;
+    }
+
+    #[tokio::test]
+    async fn scheduler_applies_the_overlap_policy() {
+        let forever = |_| -> BoxFuture<()> { Box::pin(futures::future::pending()) };
+
+        let mut harness = start(interval(1000), None, 0, forever);
+        harness.clock.set(1000);
+        assert_eq!(harness.next_fire().await, 1000);
+        harness.clock.set(2000);
+        harness.wait_for("skipped", 2000).await;
+        assert!(harness.fired.try_recv().is_err());
+        harness.stop().await;
+
+        let schedule = interval(1000).with_overlap(OverlapPolicy::CancelPrevious);
+        let mut harness = start(schedule, None, 0, forever);
+        harness.clock.set(1000);
+        assert_eq!(harness.next_fire().await, 1000);
+        harness.clock.set(2000);
+        assert_eq!(harness.next_fire().await, 2000);
+        assert!(harness.events("skipped").is_empty());
+        harness.stop().await;
+    }
+}
@@ -0,0 +1,510 @@
+use crate::node::for_each;
+use crate::register::bus::NodeRegisterBus;
+use crate::schema::node::NodeSchema;
+use crate::schema::workflow::WorkflowSchema;
+use crate::types::field::{FieldType, SchemaField};
+use crate::types::node::ERROR_OUTPUT;
+use crate::utils::REGEX_PARSE_VARIABLES;
+use crate::workflow::builder::Builder;
+use crate::workflow::dry_run::collect_strings;
+use crate::workflow::graph::Graph;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// The workflow fails when it gets there.
+    Error,
+    /// The workflow runs, but probably not as intended.
+    Warning,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticKind {
+    /// The graph cannot be built, e.g. it has a cycle or no Start node.
+    InvalidGraph,
+    DuplicateId,
+    DuplicateName,
+    UnknownAction,
+    MissingInput,
+    InvalidEnum,
+    /// A `${ctx.<name>.<key>}` reference no upstream node produces.
+    UnresolvedReference,
+    Unreachable,
+}
+
+/// A problem `validate` found in a workflow.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub kind: DiagnosticKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node_id: Option<String>,
+    /// Input the problem is in, nested values joined with `.`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn new(
+        severity: Severity,
+        kind: DiagnosticKind,
+        node_id: Option<&str>,
+        message: String,
+    ) -> Self {
+        Self {
+            severity,
+            kind,
+            node_id: node_id.map(str::to_string),
+            field: None,
+            message,
+        }
+    }
+
+    fn with_field(mut self, field: &str) -> Self {
+        self.field = Some(field.to_string());
+        self
+    }
+}
+
+/// Checks a workflow without running it. Diagnostics come in the order of
+/// the nodes in the workflow.
+pub async fn validate(workflow: &WorkflowSchema, bus: &NodeRegisterBus) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+
+    let graph = match Builder::new(workflow.clone()).build().await {
+        Ok(graph) => Some(graph),
+        Err(e) => {
+            diagnostics.push(Diagnostic::new(
+                Severity::Error,
+                DiagnosticKind::InvalidGraph,
+                None,
+                e,
+            ));
+            None
+        }
+    };
+
+    check_duplicates(workflow, &mut diagnostics);
+    for node in workflow.nodes.iter() {
+        check_inputs(node, bus, &mut diagnostics);
+    }
+    if let Some(graph) = graph {
+        check_reachable(&graph, &mut diagnostics);
+        check_references(&graph, bus, &mut diagnostics).await;
+    }
+    diagnostics
+}
+
+fn check_duplicates(workflow: &WorkflowSchema, diagnostics: &mut Vec<Diagnostic>) {
+    let mut ids = HashSet::new();
+    let mut names = HashSet::new();
+    for node in workflow.nodes.iter() {
+        if !ids.insert(node.node_id.as_str()) {
+            diagnostics.push(Diagnostic::new(
+                Severity::Error,
+                DiagnosticKind::DuplicateId,
+                Some(&node.node_id),
+                format!("node id `{}` is used more than once", node.node_id),
+            ));
+        }
+        // Outputs are stored as `ctx.<name>.*`, nodes sharing a name
+        // overwrite each other.
+        if !names.insert(node.metadata.name.as_str()) {
+            diagnostics.push(Diagnostic::new(
+                Severity::Error,
+                DiagnosticKind::DuplicateName,
+                Some(&node.node_id),
+                format!("node name `{}` is used more than once", node.metadata.name),
+            ));
+        }
+    }
+}
+
+fn check_inputs(node: &NodeSchema, bus: &NodeRegisterBus, diagnostics: &mut Vec<Diagnostic>) {
+    let Some(define) = bus.load_node(&node.action_type) else {
+        diagnostics.push(Diagnostic::new(
+            Severity::Error,
+            DiagnosticKind::UnknownAction,
+            Some(&node.node_id),
+            format!("action type `{}` is not registered", node.action_type),
+        ));
+        return;
+    };
+
+    let input = node.input_data.clone().unwrap_or_default();
+    let mut fields = define.input_schema();
+    fields.sort_by(|a, b| a.name.cmp(&b.name));
+    for field in fields.iter() {
+        let value = input.get(&field.name);
+        if is_missing(value) {
+            if is_required(field) {
+                diagnostics.push(
+                    Diagnostic::new(
+                        Severity::Error,
+                        DiagnosticKind::MissingInput,
+                        Some(&node.node_id),
+                        format!("input `{}` is required", field.name),
+                    )
+                    .with_field(&field.name),
+                );
+            }
+            continue;
+        }
+
+        if let Some(text) = value.and_then(literal)
+            && !field.enums.is_empty()
+            && !field.enums.contains(&text)
+        {
+            diagnostics.push(
+                Diagnostic::new(
+                    Severity::Error,
+                    DiagnosticKind::InvalidEnum,
+                    Some(&node.node_id),
+                    format!(
+                        "input `{}` must be one of {}, got: {}",
+                        field.name,
+                        field.enums.join(", "),
+                        text
+                    ),
+                )
+                .with_field(&field.name),
+            );
+        }
+    }
+}
+
+fn is_missing(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null) => true,
+        Some(Value::String(s)) => s.trim().is_empty(),
+        Some(_) => false,
+    }
+}
+
+/// Fields with a default or shown only under a condition may be left out,
+/// an object defaults to empty when resolved.
+fn is_required(field: &SchemaField) -> bool {
+    field.default.is_none()
+        && field.condition.is_none()
+        && !matches!(field.field_type, FieldType::Object)
+}
+
+/// The value as text, `None` when it is only known once the run has set
+/// the variables it references.
+fn literal(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) if REGEX_PARSE_VARIABLES.is_match(s) => None,
+        Value::String(s) => Some(s.clone()),
+        Value::Number(_) | Value::Bool(_) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+fn check_reachable(graph: &Graph, diagnostics: &mut Vec<Diagnostic>) {
+    let mut reached = vec![false; graph.nodes().len()];
+    for index in graph.reachable_from(graph.starts()) {
+        reached[index] = true;
+    }
+
+    for (node, _) in graph
+        .nodes()
+        .iter()
+        .zip(reached)
+        .filter(|(_, reached)| !reached)
+    {
+        diagnostics.push(Diagnostic::new(
+            Severity::Warning,
+            DiagnosticKind::Unreachable,
+            Some(&node.node_id),
+            format!(
+                "node `{}` cannot be reached from a Start node",
+                node.node_context.metadata.name
+            ),
+        ));
+    }
+}
+
+/// Outputs by node name a node can reference.
+type Available = HashMap<String, Vec<SchemaField>>;
+
+async fn check_references(graph: &Graph, bus: &NodeRegisterBus, diagnostics: &mut Vec<Diagnostic>) {
+    let shared_bus = Arc::new(RwLock::new(bus.clone()));
+
+    for (index, graph_node) in graph.nodes().iter().enumerate() {
+        let node = &graph_node.node_context;
+        // Upstream nodes that are not registered are reported already.
+        let Ok(mut available) = graph
+            .node_params_from_ctx(node.node_id.clone(), shared_bus.clone())
+            .await
+        else {
+            continue;
+        };
+        // The break condition sees the item and the outputs of the body.
+        if node.action_type == for_each::node::NODE_TYPE {
+            add_outputs(node, bus, &mut available);
+            for body in graph.loop_body(index) {
+                add_outputs(&graph.node(body).node_context, bus, &mut available);
+            }
+        }
+
+        let mut strings = vec![];
+        for (name, value) in node.input_data.iter().flatten() {
+            collect_strings(name.clone(), value, &mut strings);
+        }
+        strings.sort();
+        if let Some(condition) = node
+            .metadata
+            .conditions
+            .as_ref()
+            .and_then(|conditions| conditions.condition.as_deref())
+        {
+            strings.push(("conditions.condition".to_string(), condition));
+        }
+        for (field, text) in strings {
+            check_text(&node.node_id, &field, text, &available, diagnostics);
+        }
+    }
+
+    // Connection conditions are checked once `from` has finished.
+    for from in graph.nodes().iter() {
+        for edge in from.next.iter() {
+            let Some(condition) = edge
+                .condition
+                .as_ref()
+                .and_then(|conditions| conditions.condition.as_deref())
+            else {
+                continue;
+            };
+            let Ok(mut available) = graph
+                .node_params_from_ctx(from.node_id.clone(), shared_bus.clone())
+                .await
+            else {
+                continue;
+            };
+            add_outputs(&from.node_context, bus, &mut available);
+            let field = format!("connections.{}.condition", graph.node(edge.to).node_id);
+            check_text(&from.node_id, &field, condition, &available, diagnostics);
+        }
+    }
+}
+
+fn add_outputs(node: &NodeSchema, bus: &NodeRegisterBus, available: &mut Available) {
+    if let Some(define) = bus.load_node(&node.action_type) {
+        let input = node.input_data.clone().unwrap_or_default();
+        available.insert(node.metadata.name.clone(), define.output_schema(input));
+    }
+}
+
+fn check_text(
+    node_id: &str,
+    field: &str,
+    text: &str,
+    available: &Available,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    for caps in REGEX_PARSE_VARIABLES.captures_iter(text) {
+        let variable = &caps[1];
+        // Only node outputs are checked, other variables are set at run time.
+        let mut parts = variable.splitn(3, '.');
+        if parts.next() != Some("ctx") {
+            continue;
+        }
+        let Some(name) = parts.next() else {
+            continue;
+        };
+        let key = parts.next().map(|key| key.split('.').next().unwrap_or(key));
+
+        let Some(outputs) = available.get(name) else {
+            diagnostics.push(
+                Diagnostic::new(
+                    Severity::Error,
+                    DiagnosticKind::UnresolvedReference,
+                    Some(node_id),
+                    format!("`${{{}}}` is not produced by any upstream node", variable),
+                )
+                .with_field(field),
+            );
+            continue;
+        };
+        if let Some(key) = key
+            && key != ERROR_OUTPUT
+            && !outputs.iter().any(|output| output.name == key)
+        {
+            diagnostics.push(
+                Diagnostic::new(
+                    Severity::Warning,
+                    DiagnosticKind::UnresolvedReference,
+                    Some(node_id),
+                    format!("node `{}` does not declare the output `{}`", name, key),
+                )
+                .with_field(field),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workflow(yaml: &str) -> WorkflowSchema {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn found(diagnostics: &[Diagnostic]) -> Vec<(Severity, DiagnosticKind, Option<&str>)> {
+        diagnostics
+            .iter()
+            .map(|d| (d.severity, d.kind, d.node_id.as_deref()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn validate_accepts_a_valid_workflow() {
+        let workflow = workflow(
+            r#"
+nodes:
+  - node_id: start
+    action_type: Start
+    name: start
+    input_data:
+      inputs:
+        - name: url
+          type: string
+  - node_id: collect
+    action_type: DataAggregator
+    name: collect
+    input_data:
+      mode: array
+      sources: ["${ctx.start.url}"]
+  - node_id: wait
+    action_type: TimeWait
+    name: wait
+    conditions:
+      condition: "${ctx.collect.count} == 1"
+    input_data:
+      duration: 10
+connections:
+  - from: start
+    to: collect
+  - from: collect
+    to: wait
+    condition:
+      condition: "${ctx.collect.count} > 0"
+"#,
+        );
+        let bus = NodeRegisterBus::new().with_internal_nodes();
+
+        let diagnostics = validate(&workflow, &bus).await;
+
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    }
+
+    #[tokio::test]
+    async fn validate_reports_every_problem() {
+        let workflow = workflow(
+            r#"
+nodes:
+  - node_id: start
+    action_type: Start
+    name: start
+  - node_id: collect
+    action_type: DataAggregator
+    name: collect
+    input_data:
+      mode: nested
+      sources: ["${ctx.later.url}"]
+  - node_id: wait
+    action_type: TimeWait
+    name: collect
+    input_data:
+      duration: "${ctx.collect.elapsed}"
+  - node_id: later
+    action_type: Unknown
+    name: later
+connections:
+  - from: start
+    to: collect
+  - from: collect
+    to: wait
+"#,
+        );
+        let bus = NodeRegisterBus::new().with_internal_nodes();
+
+        let diagnostics = validate(&workflow, &bus).await;
+
+        assert_eq!(
+            found(&diagnostics),
+            vec![
+                (Severity::Error, DiagnosticKind::DuplicateName, Some("wait")),
+                (
+                    Severity::Error,
+                    DiagnosticKind::InvalidEnum,
+                    Some("collect")
+                ),
+                (
+                    Severity::Error,
+                    DiagnosticKind::UnknownAction,
+                    Some("later")
+                ),
+                (
+                    Severity::Warning,
+                    DiagnosticKind::Unreachable,
+                    Some("later")
+                ),
+                (
+                    Severity::Error,
+                    DiagnosticKind::UnresolvedReference,
+                    Some("collect")
+                ),
+                (
+                    Severity::Warning,
+                    DiagnosticKind::UnresolvedReference,
+                    Some("wait")
+                ),
+            ]
+        );
+        assert_eq!(diagnostics[1].field.as_deref(), Some("mode"));
+        assert_eq!(diagnostics[4].field.as_deref(), Some("sources.0"));
+    }
+
+    #[tokio::test]
+    async fn validate_reports_missing_inputs_and_invalid_graphs() {
+        let workflow = workflow(
+            r#"
+nodes:
+  - node_id: start
+    action_type: Start
+    name: start
+  - node_id: wait
+    action_type: TimeWait
+    name: wait
+    input_data:
+      duration: ""
+connections:
+  - from: start
+    to: wait
+  - from: wait
+    to: missing
+"#,
+        );
+        let bus = NodeRegisterBus::new().with_internal_nodes();
+
+        let diagnostics = validate(&workflow, &bus).await;
+
+        assert_eq!(
+            found(&diagnostics),
+            vec![
+                (Severity::Error, DiagnosticKind::InvalidGraph, None),
+                (Severity::Error, DiagnosticKind::MissingInput, Some("wait")),
+            ]
+        );
+        assert_eq!(diagnostics[1].field.as_deref(), Some("duration"));
+    }
+}
@@ -0,0 +1,145 @@
+use crate::context::Context;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Variables are kept in the `Context` under this prefix, nodes read them as
+/// `${var.<name>}`.
+pub const VARIABLE_PREFIX: &str = "var.";
+
+/// `AUTO_ENGINE_VAR_BASE_URL` overrides the variable `base_url`.
+pub const ENV_PREFIX: &str = "AUTO_ENGINE_VAR_";
+
+/// Checks that `name` can be written as `${var.<name>}`.
+pub(crate) fn check_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+    if !valid {
+        return Err(format!(
+            "invalid variable name '{}', use letters, digits, `_` and `-`",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// The value of every variable of a run. A run input of the same name wins
+/// over the environment variable, which wins over the declared default.
+/// Overrides given as text are converted to the type of the default.
+pub(crate) fn resolve(
+    declared: &HashMap<String, Value>,
+    inputs: &HashMap<String, Value>,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<HashMap<String, Value>, String> {
+    let mut values = HashMap::with_capacity(declared.len());
+    for (name, default) in declared.iter() {
+        let env_name = format!("{}{}", ENV_PREFIX, name.to_uppercase().replace('-', "_"));
+        let value = match inputs.get(name).filter(|value| !value.is_null()) {
+            Some(value) => {
+                convert(default, value.clone()).map_err(|e| format!("input `{}` {}", name, e))?
+            }
+            None => match env(&env_name) {
+                Some(text) => convert(default, Value::String(text))
+                    .map_err(|e| format!("environment variable {} {}", env_name, e))?,
+                None => default.clone(),
+            },
+        };
+        values.insert(name.clone(), value);
+    }
+    Ok(values)
+}
+
+/// Reads an override from the environment of the process.
+pub(crate) fn env(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+/// Stores `values` in `ctx` where `${var.<name>}` finds them.
+pub(crate) async fn seed(ctx: &Context, values: &HashMap<String, Value>) -> Result<(), String> {
+    for (name, value) in values.iter() {
+        ctx.set_value(&format!("{}{}", VARIABLE_PREFIX, name), value)
+            .await?;
+    }
+    Ok(())
+}
+
+fn convert(default: &Value, value: Value) -> Result<Value, String> {
+    let Value::String(text) = &value else {
+        return Ok(value);
+    };
+    let text = text.trim();
+    match default {
+        Value::Number(_) => text
+            .parse::<i64>()
+            .map(Value::from)
+            .ok()
+            .or_else(|| text.parse::<f64>().ok().map(Value::from))
+            .filter(|number| !number.is_null())
+            .ok_or_else(|| format!("is not a number: {}", text)),
+        Value::Bool(_) => match text.to_lowercase().as_str() {
+            "true" | "1" => Ok(Value::Bool(true)),
+            "false" | "0" => Ok(Value::Bool(false)),
+            _ => Err(format!("is not a boolean: {}", text)),
+        },
+        Value::Array(_) => match serde_json::from_str(text) {
+            Ok(array @ Value::Array(_)) => Ok(array),
+            _ => Err(format!("is not an array: {}", text)),
+        },
+        Value::Object(_) => match serde_json::from_str(text) {
+            Ok(object @ Value::Object(_)) => Ok(object),
+            _ => Err(format!("is not an object: {}", text)),
+        },
+        _ => Ok(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_resolve_overrides() {
+        let declared = HashMap::from([
+            ("base_url".to_string(), json!("https://example.com")),
+            ("threshold".to_string(), json!(0.8)),
+            ("offset".to_string(), json!({"x": 0, "y": 0})),
+            ("debug".to_string(), json!(false)),
+        ]);
+        let env = |name: &str| match name {
+            "AUTO_ENGINE_VAR_THRESHOLD" => Some("0.9".to_string()),
+            "AUTO_ENGINE_VAR_DEBUG" => Some("true".to_string()),
+            "AUTO_ENGINE_VAR_BASE_URL" => Some("https://env.example.com".to_string()),
+            _ => None,
+        };
+        let inputs = HashMap::from([
+            ("base_url".to_string(), json!("https://input.example.com")),
+            ("offset".to_string(), json!(r#"{"x": 10, "y": 20}"#)),
+        ]);
+
+        let values = resolve(&declared, &inputs, env).unwrap();
+        assert_eq!(values["base_url"], json!("https://input.example.com"));
+        assert_eq!(values["threshold"], json!(0.9));
+        assert_eq!(values["offset"], json!({"x": 10, "y": 20}));
+        assert_eq!(values["debug"], json!(true));
+
+        let values = resolve(&declared, &HashMap::new(), |_| None).unwrap();
+        assert_eq!(values["threshold"], json!(0.8));
+
+        let err = resolve(&declared, &HashMap::new(), |name| {
+            (name == "AUTO_ENGINE_VAR_THRESHOLD").then(|| "high".to_string())
+        })
+        .unwrap_err();
+        assert!(err.contains("AUTO_ENGINE_VAR_THRESHOLD"), "{err}");
+    }
+
+    #[test]
+    fn test_check_name() {
+        assert!(check_name("base_url").is_ok());
+        assert!(check_name("retry-count").is_ok());
+        assert!(check_name("").is_err());
+        assert!(check_name("a.b").is_err());
+        assert!(check_name("a}").is_err());
+    }
+}